@@ -1,17 +1,10 @@
-extern crate async_docker;
-extern crate http;
-extern crate futures;
-extern crate tokio;
-
-
 use std::env;
 use async_docker::communicate::DockerApi;
 use async_docker::communicate::new_docker;
-use futures::Stream;
-use futures::future;
-use futures::Future;
+use futures::{future, TryStreamExt};
 
-fn main() {
+#[tokio::main]
+async fn main() {
     if env::args().count() < 2 {
         println!("Too few arguments (<2).");
         return;
@@ -20,15 +13,18 @@ fn main() {
     let container = env::args().nth(1).unwrap();
     let remote_path = env::args().nth(2).unwrap();
 
-    let work = future::lazy(move || {
-        let docker: Box<DockerApi> = new_docker(None).unwrap();
+    let docker: Box<dyn DockerApi> = new_docker(None).unwrap();
 
-        docker
-            .container(container.into())
-            .archive_get(remote_path.as_str())
-            .for_each(|a| Ok(println!("{:?}", a)))
-            .map_err(|a| eprintln!("{:?}", a))
-    });
+    let result = docker
+        .container(container.into())
+        .archive_get(remote_path.as_str())
+        .try_for_each(|a| {
+            println!("{:?}", a);
+            future::ok(())
+        })
+        .await;
 
-    tokio::runtime::run(work);
+    if let Err(e) = result {
+        eprintln!("{:?}", e);
+    }
 }
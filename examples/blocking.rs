@@ -0,0 +1,15 @@
+use async_docker::blocking::BlockingDocker;
+use async_docker::new_docker;
+
+fn main() {
+    let docker = BlockingDocker::new(new_docker(None).unwrap()).unwrap();
+
+    match docker.images().list(&Default::default()) {
+        Ok(images) => {
+            for i in images {
+                println!("{:?}", i.RepoTags);
+            }
+        }
+        Err(e) => eprintln!("{:?}", e),
+    }
+}
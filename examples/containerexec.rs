@@ -1,10 +1,5 @@
-extern crate async_docker;
-extern crate http;
-extern crate futures;
-extern crate tokio;
-
 use async_docker::{DockerApi, new_docker, ExecContainerOptions};
-use futures::{future, Future, Stream};
+use futures::{future, TryStreamExt};
 use std::env;
 
 fn get_opts() -> ExecContainerOptions {
@@ -20,7 +15,8 @@ fn get_opts() -> ExecContainerOptions {
         .build()
 }
 
-fn main() {
+#[tokio::main]
+async fn main() {
     let id = match env::args().nth(1) {
         Some(val) => val,
         None => {
@@ -29,14 +25,18 @@ fn main() {
         }
     };
 
-    let work = future::lazy(move||  {
-        let docker: Box<DockerApi> = new_docker(None).unwrap();
-        docker
-            .container(id.into())
-            .exec(&get_opts())
-            .for_each(|a| Ok(println!("{:?}", a)))
-            .map_err(|e| println!("{:#?}", e))
-    });
+    let docker: Box<dyn DockerApi> = new_docker(None).unwrap();
+
+    let result = docker
+        .container(id.into())
+        .exec(&get_opts())
+        .try_for_each(|a| {
+            println!("{:?}", a);
+            future::ok(())
+        })
+        .await;
 
-    tokio::runtime::run(work);
-}
\ No newline at end of file
+    if let Err(e) = result {
+        println!("{:#?}", e);
+    }
+}
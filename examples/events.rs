@@ -1,21 +1,21 @@
-extern crate async_docker;
-extern crate http;
-extern crate futures;
-extern crate tokio;
-
 use async_docker::{DockerApi, new_docker, EventsOptionsBuilder};
-use futures::{future, Stream, Future};
+use futures::{future, TryStreamExt};
+
+#[tokio::main]
+async fn main() {
+    let opts = EventsOptionsBuilder::default().build();
 
-fn main() {
-    let work = future::lazy(||  {
-        let opts = EventsOptionsBuilder::default().build();
-        let docker: Box<DockerApi> = new_docker(None).unwrap();
+    let docker: Box<dyn DockerApi> = new_docker(None).unwrap();
 
-        docker
-            .events(&opts)
-            .for_each(|a| Ok(println!("{:?}", a)))
-            .map_err(|e| eprintln!("{:?}", e))
-    });
+    let result = docker
+        .events(&opts)
+        .try_for_each(|a| {
+            println!("{:?}", a);
+            future::ok(())
+        })
+        .await;
 
-    tokio::runtime::run(work);
-}
\ No newline at end of file
+    if let Err(e) = result {
+        eprintln!("{:?}", e);
+    }
+}
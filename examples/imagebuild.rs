@@ -1,13 +1,8 @@
-extern crate async_docker;
-extern crate http;
-extern crate futures;
-extern crate tokio;
-
 use async_docker::{DockerApi, new_docker, BuildOptions};
-use futures::{future, Future};
 use std::env;
 
-fn main() {
+#[tokio::main]
+async fn main() {
     let path = match env::args().nth(1) {
         Some(val) => val,
         None => {
@@ -16,16 +11,20 @@ fn main() {
         }
     };
 
-    let work = future::lazy(|| {
-        let opts = BuildOptions::builder(path).tag("async_docker_test").build();
-        let docker: Box<DockerApi> = new_docker(None).unwrap();
+    let docker: Box<dyn DockerApi> = new_docker(None).unwrap();
+    let opts = BuildOptions::builder(path).tag("async_docker_test").build();
 
-        docker
-            .images()
-            .build(&opts)
-            .and_then(|a| Ok(println!("{:#?}", a)))
-            .map_err(|e| eprintln!("{:?}", e))
-    });
+    let built = docker
+        .images()
+        .build_with_output(&opts, |event| {
+            if let Some(ref line) = event.stream {
+                print!("{}", line);
+            }
+        })
+        .await;
 
-    tokio::runtime::run(work);
-}
\ No newline at end of file
+    match built {
+        Ok(id) => println!("{}", id),
+        Err(e) => eprintln!("{:?}", e),
+    }
+}
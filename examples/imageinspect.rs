@@ -1,13 +1,8 @@
-extern crate async_docker;
-extern crate http;
-extern crate futures;
-extern crate tokio;
-
 use async_docker::{DockerApi, new_docker};
-use futures::{future, Future};
 use std::env;
 
-fn main() {
+#[tokio::main]
+async fn main() {
     let image = match env::args().nth(1) {
         Some(val) => val,
         None => {
@@ -16,14 +11,12 @@ fn main() {
         }
     };
 
-    let work = future::lazy(|| {
-        let docker: Box<DockerApi> = new_docker(None).unwrap();
+    let docker: Box<dyn DockerApi> = new_docker(None).unwrap();
 
-        docker
-            .image(image.into())
-            .delete()
-            .then(|a| Ok(println!("{:?}", a)))
-    });
+    let result = docker
+        .image(image.into())
+        .inspect()
+        .await;
 
-    tokio::runtime::run(work);
-}
\ No newline at end of file
+    println!("{:?}", result);
+}
@@ -1,13 +1,8 @@
-extern crate async_docker;
-extern crate http;
-extern crate futures;
-extern crate tokio;
-
 use async_docker::{DockerApi, new_docker, PullOptions};
-use futures::{future, Future};
 use std::env;
 
-fn main() {
+#[tokio::main]
+async fn main() {
     let image = match env::args().nth(1) {
         Some(val) => val,
         None => {
@@ -16,16 +11,18 @@ fn main() {
         }
     };
 
-    let work = future::lazy(|| {
-        let opts = PullOptions::builder().image(image).build();
-        let docker: Box<DockerApi> = new_docker(None).unwrap();
+    let docker: Box<dyn DockerApi> = new_docker(None).unwrap();
+    let opts = PullOptions::builder().image(image).build();
 
-        docker
-            .images()
-            .pull(&opts)
-            .and_then(|a| Ok(println!("{:#?}", a)))
-            .map_err(|e| eprintln!("{:?}", e))
-    });
+    let pulled = docker
+        .images()
+        .pull_with_progress(&opts, |progress| {
+            eprint!("\r{}/{} bytes", progress.current(), progress.total())
+        })
+        .await;
 
-    tokio::runtime::run(work);
-}
\ No newline at end of file
+    match pulled {
+        Ok(digest) => println!("\n{:?}", digest),
+        Err(e) => eprintln!("\n{:?}", e),
+    }
+}
@@ -1,19 +1,12 @@
-extern crate async_docker;
-extern crate http;
-extern crate futures;
-extern crate tokio;
-
 use async_docker::{DockerApi, new_docker};
-use futures::{future, Future};
 
-fn main() {
-    let work = future::lazy(||  {
-        let docker: Box<DockerApi> = new_docker(None).unwrap();
+#[tokio::main]
+async fn main() {
+    let docker: Box<dyn DockerApi> = new_docker(None).unwrap();
 
-        docker
-            .info()
-            .then(|a| Ok(println!("{:?}", a)))
-    });
+    let result = docker
+        .info()
+        .await;
 
-    tokio::runtime::run(work);
-}
\ No newline at end of file
+    println!("{:?}", result);
+}
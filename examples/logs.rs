@@ -1,15 +1,10 @@
-extern crate async_docker;
-extern crate http;
-extern crate futures;
-extern crate tokio;
-
 use std::env;
 use async_docker::{DockerApi, new_docker};
-use futures::{future, Future};
 use async_docker::LogsOptionsBuilder;
-use futures::Stream;
+use futures::{future, TryStreamExt};
 
-fn main() {
+#[tokio::main]
+async fn main() {
     if env::args().count() < 2 {
         println!("Too few arguments (<1).");
         return;
@@ -17,17 +12,20 @@ fn main() {
 
     let container = env::args().nth(1).unwrap();
 
-    let work = future::lazy(move ||  {
-        let docker: Box<DockerApi> = new_docker(None).unwrap();
+    let docker: Box<dyn DockerApi> = new_docker(None).unwrap();
 
-        let opts = LogsOptionsBuilder::new().stdout(true).build();
+    let opts = LogsOptionsBuilder::new().stdout(true).build();
 
-        docker
-            .container(container.into())
-            .logs(&opts)
-            .for_each(|a| Ok(println!("{:?}", a)))
-            .map_err(|e|eprintln!("{:?}", e))
-    });
+    let result = docker
+        .container(container.into())
+        .logs(&opts)
+        .try_for_each(|a| {
+            println!("{:?}", a);
+            future::ok(())
+        })
+        .await;
 
-    tokio::runtime::run(work);
-}
\ No newline at end of file
+    if let Err(e) = result {
+        eprintln!("{:?}", e);
+    }
+}
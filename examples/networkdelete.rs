@@ -1,16 +1,9 @@
-extern crate async_docker;
-extern crate http;
-extern crate futures;
-extern crate tokio;
-
-
 use std::env;
 use async_docker::communicate::DockerApi;
 use async_docker::communicate::new_docker;
-use futures::future;
-use futures::Future;
 
-fn main() {
+#[tokio::main]
+async fn main() {
     if env::args().count() < 2 {
         println!("Too few arguments (<1).");
         return;
@@ -18,15 +11,14 @@ fn main() {
 
     let network_name = env::args().nth(1).unwrap();
 
-    let work = future::lazy(move || {
-        let docker: Box<DockerApi> = new_docker(None).unwrap();
+    let docker: Box<dyn DockerApi> = new_docker(None).unwrap();
 
-        docker
-            .network(network_name.into())
-            .delete()
-            .and_then(|a| Ok(println!("{:?}", a)))
-            .map_err(|a| eprintln!("{:?}", a))
-    });
-
-    tokio::runtime::run(work);
+    match docker
+        .network(network_name.into())
+        .delete()
+        .await
+    {
+        Ok(a) => println!("{:?}", a),
+        Err(e) => eprintln!("{:?}", e),
+    }
 }
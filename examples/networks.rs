@@ -1,21 +1,15 @@
-extern crate async_docker;
-extern crate http;
-extern crate futures;
-extern crate tokio;
-
 use async_docker::{DockerApi, new_docker};
-use futures::{future, Future};
-
-fn main() {
-    let work = future::lazy(||  {
-        let docker: Box<DockerApi> = new_docker(None).unwrap();
-
-        docker
-            .networks()
-            .list(&Default::default())
-            .and_then(|a| Ok(println!("{:?}", a)))
-            .map_err(|e| eprintln!("{:?}", e))
-    });
 
-    tokio::runtime::run(work);
-}
\ No newline at end of file
+#[tokio::main]
+async fn main() {
+    let docker: Box<dyn DockerApi> = new_docker(None).unwrap();
+
+    match docker
+        .networks()
+        .list(&Default::default())
+        .await
+    {
+        Ok(a) => println!("{:?}", a),
+        Err(e) => eprintln!("{:?}", e),
+    }
+}
@@ -1,13 +1,9 @@
-extern crate async_docker;
-extern crate http;
-extern crate futures;
-extern crate tokio;
-
 use async_docker::{DockerApi, new_docker};
 use std::env;
-use futures::{future, Future, Stream};
+use futures::{future, TryStreamExt};
 
-fn main() {
+#[tokio::main]
+async fn main() {
     let id = match env::args().nth(1) {
         Some(val) => val,
         None => {
@@ -16,15 +12,18 @@ fn main() {
         }
     };
 
-    let work = future::lazy(move||  {
-        let docker: Box<DockerApi> = new_docker(None).unwrap();
+    let docker: Box<dyn DockerApi> = new_docker(None).unwrap();
 
-        docker
-            .container(id.into())
-            .stats()
-            .for_each(|a| Ok(println!("{:?}", a)))
-            .map_err(|e| eprintln!("{:?}", e))
-    });
+    let result = docker
+        .container(id.into())
+        .stats(&Default::default())
+        .try_for_each(|a| {
+            println!("{:?}", a);
+            future::ok(())
+        })
+        .await;
 
-    tokio::runtime::run(work);
-}
\ No newline at end of file
+    if let Err(e) = result {
+        eprintln!("{:?}", e);
+    }
+}
@@ -0,0 +1,525 @@
+//! Synchronous client for plain, non-async programs
+//!
+//! `BlockingDocker` owns a tokio runtime and drives every request of the
+//! wrapped `DockerApi` to completion on it. Streams such as logs, stats and
+//! events are handed out as iterators which block for each item.
+//!
+//! The blocking interfaces must not be used from within an async context,
+//! as the runtime refuses to block a thread that is already driving tasks.
+//!
+//! ```no_run
+//! # use async_docker::blocking::BlockingDocker;
+//! # use async_docker::new_docker;
+//! # fn main() -> async_docker::Result<()> {
+//! let docker = BlockingDocker::new(new_docker(None)?)?;
+//!
+//! for event in docker.events(&Default::default()) {
+//!     println!("{:?}", event?);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::borrow::Cow;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use futures::{Stream, StreamExt};
+use http::StatusCode;
+use tokio::runtime::Runtime;
+
+use crate::build::{BuildOptions, ContainerArchivePutOptions, ContainerConnectionOptions,
+                   ContainerListOptions, ContainerOptions, EventsOptions, ExecContainerOptions,
+                   ImageListOptions, ImageLoadOptions, ImageRemoveOptions, LogsOptions,
+                   NetworkCreateOptions, NetworkListOptions, PullOptions, PushOptions,
+                   RmContainerOptions, StatsOptions, WaitOptions};
+use crate::communicate::docker::DockerApi;
+use crate::communicate::progress::PullProgress;
+use crate::communicate::version::ApiVersion;
+use crate::communicate::{Container, Image, Images, Network};
+use crate::communicate::containers::Containers;
+use crate::communicate::networks::Networks;
+use crate::errors::Result;
+use crate::transport::body::Body;
+use crate::representation::rep::{BuildEvent, Change, Container as ContainerRep, ContainerCreateInfo,
+                                 ContainerDetails, Event, Exit, History, Image as ImageRep,
+                                 ImageDetails, Info, LoadEvent, NetworkCreateInfo, NetworkDetails,
+                                 PullEvent, PushEvent, SearchResult, Stats, Status, Top, Version};
+
+/// Docker client blocking the calling thread until each request completes
+pub struct BlockingDocker {
+    docker: Box<dyn DockerApi>,
+    runtime: Arc<Runtime>,
+}
+
+impl BlockingDocker {
+    /// Wraps a client, its requests are driven on a runtime owned by the
+    /// returned value
+    pub fn new(docker: Box<dyn DockerApi>) -> Result<BlockingDocker> {
+        Ok(BlockingDocker::with_runtime(docker, Arc::new(Runtime::new()?)))
+    }
+
+    /// Wraps a client, its requests are driven on the given runtime
+    pub fn with_runtime(docker: Box<dyn DockerApi>, runtime: Arc<Runtime>) -> BlockingDocker {
+        BlockingDocker {
+            docker,
+            runtime,
+        }
+    }
+
+    /// The wrapped client, for the interfaces not mirrored here
+    pub fn docker(&self) -> &dyn DockerApi {
+        &*self.docker
+    }
+
+    /// The runtime the requests are driven on
+    pub fn runtime(&self) -> &Arc<Runtime> {
+        &self.runtime
+    }
+
+    /// Returns version information associated with the docker daemon
+    pub fn version(&self) -> Result<Version> {
+        self.runtime.block_on(self.docker.version())
+    }
+
+    /// Returns information associated with the docker daemon
+    pub fn info(&self) -> Result<Info> {
+        self.runtime.block_on(self.docker.info())
+    }
+
+    /// Returns a simple ping response indicating the docker daemon is accessible
+    pub fn ping(&self) -> Result<StatusCode> {
+        self.runtime.block_on(self.docker.ping())
+    }
+
+    /// Queries the daemon's supported API versions and pins the highest one
+    /// this client supports as well
+    pub fn negotiate_version(&self) -> Result<ApiVersion> {
+        self.runtime.block_on(self.docker.negotiate_version())
+    }
+
+    /// Returns an iterator over docker events
+    pub fn events(&self, opts: &EventsOptions) -> Iter<Event> {
+        Iter::new(self.docker.events(opts), &self.runtime)
+    }
+
+    /// Exports an interface for interacting with a docker container
+    pub fn container(&self, id: Cow<'static, str>) -> BlockingContainer {
+        BlockingContainer {
+            container: self.docker.container(id),
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    /// Exports an interface for interacting with docker containers
+    pub fn containers(&self) -> BlockingContainers {
+        BlockingContainers {
+            containers: self.docker.containers(),
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    /// Exports an interface for interacting with a docker image
+    pub fn image<'a>(&self, id: Cow<'a, str>) -> BlockingImage<'a> {
+        BlockingImage {
+            image: self.docker.image(id),
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    /// Exports an interface for interacting with docker images
+    pub fn images(&self) -> BlockingImages {
+        BlockingImages {
+            images: self.docker.images(),
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    /// Exports an interface for interacting with a docker network
+    pub fn network<'a>(&self, id: Cow<'a, str>) -> BlockingNetwork<'a> {
+        BlockingNetwork {
+            network: self.docker.network(id),
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    /// Exports an interface for interacting with docker networks
+    pub fn networks(&self) -> BlockingNetworks {
+        BlockingNetworks {
+            networks: self.docker.networks(),
+            runtime: self.runtime.clone(),
+        }
+    }
+}
+
+/// Iterator blocking on each item of a stream
+pub struct Iter<T> {
+    stream: BoxStream<'static, Result<T>>,
+    runtime: Arc<Runtime>,
+}
+
+impl<T> Iter<T> {
+    fn new<S>(stream: S, runtime: &Arc<Runtime>) -> Iter<T>
+        where S: Stream<Item=Result<T>> + Send + 'static
+    {
+        Iter {
+            stream: stream.boxed(),
+            runtime: runtime.clone(),
+        }
+    }
+}
+
+impl<T> Iterator for Iter<T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Result<T>> {
+        let stream = &mut self.stream;
+        self.runtime.block_on(stream.next())
+    }
+}
+
+/// Blocking interface for accessing and manipulating a docker container
+pub struct BlockingContainer {
+    container: Container,
+    runtime: Arc<Runtime>,
+}
+
+impl BlockingContainer {
+    /// a getter for the container id
+    pub fn id(&self) -> &str {
+        self.container.id()
+    }
+
+    /// Inspects the current docker container instance's details
+    pub fn inspect(&self) -> Result<ContainerDetails> {
+        self.runtime.block_on(self.container.inspect())
+    }
+
+    /// Returns a `top` view of information about the container process
+    pub fn top(&self, psargs: Option<&str>) -> Result<Top> {
+        self.runtime.block_on(self.container.top(psargs))
+    }
+
+    /// Returns an iterator over logs emitted but the container instance
+    pub fn logs(&self, opts: &LogsOptions) -> Iter<String> {
+        Iter::new(self.container.logs(opts), &self.runtime)
+    }
+
+    /// Returns a set of changes made to the container instance
+    pub fn changes(&self) -> Result<Vec<Change>> {
+        self.runtime.block_on(self.container.changes())
+    }
+
+    /// Exports the current docker container into a tarball
+    pub fn export(&self) -> Iter<Bytes> {
+        Iter::new(self.container.export(), &self.runtime)
+    }
+
+    /// Returns an iterator over stats specific to this container instance
+    pub fn stats(&self, opts: &StatsOptions) -> Iter<Stats> {
+        Iter::new(self.container.stats(opts), &self.runtime)
+    }
+
+    /// Start the container instance
+    pub fn start(&self) -> Result<StatusCode> {
+        self.runtime.block_on(self.container.start())
+    }
+
+    /// Stop the container instance
+    pub fn stop(&self, wait: Option<Duration>) -> Result<StatusCode> {
+        self.runtime.block_on(self.container.stop(wait))
+    }
+
+    /// Restart the container instance
+    pub fn restart(&self, wait: Option<Duration>) -> Result<StatusCode> {
+        self.runtime.block_on(self.container.restart(wait))
+    }
+
+    /// Kill the container instance
+    pub fn kill(&self, signal: Option<&str>) -> Result<StatusCode> {
+        self.runtime.block_on(self.container.kill(signal))
+    }
+
+    /// Rename the container instance
+    pub fn rename(&self, name: &str) -> Result<StatusCode> {
+        self.runtime.block_on(self.container.rename(name))
+    }
+
+    /// Pause the container instance
+    pub fn pause(&self) -> Result<StatusCode> {
+        self.runtime.block_on(self.container.pause())
+    }
+
+    /// Unpause the container instance
+    pub fn unpause(&self) -> Result<StatusCode> {
+        self.runtime.block_on(self.container.unpause())
+    }
+
+    /// Wait until the container stops
+    pub fn wait(&self, opts: &WaitOptions) -> Result<Exit> {
+        self.runtime.block_on(self.container.wait(opts))
+    }
+
+    /// Delete the container instance
+    pub fn delete(&self) -> Result<StatusCode> {
+        self.runtime.block_on(self.container.delete())
+    }
+
+    /// Delete the container instance (todo: force/v)
+    pub fn remove(&self, opts: &RmContainerOptions) -> Result<StatusCode> {
+        self.runtime.block_on(self.container.remove(opts))
+    }
+
+    /// Creates an exec instance, returning its id
+    pub fn create_exec(&self, opts: &ExecContainerOptions) -> Result<String> {
+        self.runtime.block_on(self.container.create_exec(opts))
+    }
+
+    /// Returns an iterator over the output of a created exec instance
+    pub fn start_exec(&self, id: String) -> Iter<(u32, Bytes)> {
+        Iter::new(self.container.start_exec(id), &self.runtime)
+    }
+
+    /// Creates and starts an exec instance, returning an iterator over its output
+    pub fn exec(&self, opts: &ExecContainerOptions) -> Iter<(u32, Bytes)> {
+        Iter::new(self.container.exec(opts), &self.runtime)
+    }
+
+    /// Returns an iterator over a tarball of the given path in the container
+    pub fn archive_get(&self, pth: &str) -> Iter<Bytes> {
+        Iter::new(self.container.archive_get(pth), &self.runtime)
+    }
+
+    /// Uploads a local directory into the container
+    pub fn archive_put(&self, opts: &ContainerArchivePutOptions) -> Result<StatusCode> {
+        self.runtime.block_on(self.container.archive_put(opts))
+    }
+}
+
+/// Blocking interface for docker containers
+pub struct BlockingContainers {
+    containers: Containers,
+    runtime: Arc<Runtime>,
+}
+
+impl BlockingContainers {
+    /// Lists the container instances on the docker host
+    pub fn list(&self, opts: &ContainerListOptions) -> Result<Vec<ContainerRep>> {
+        self.runtime.block_on(self.containers.list(opts))
+    }
+
+    /// Returns a builder interface for creating a new container instance
+    pub fn create(&self, opts: &ContainerOptions) -> Result<ContainerCreateInfo> {
+        self.runtime.block_on(self.containers.create(opts))
+    }
+}
+
+/// Blocking interface for accessing and manipulating a named docker image
+pub struct BlockingImage<'b> {
+    image: Image<'b>,
+    runtime: Arc<Runtime>,
+}
+
+impl<'b> BlockingImage<'b> {
+    /// Inspects a named image's details
+    pub fn inspect(&self) -> Result<ImageDetails> {
+        self.runtime.block_on(self.image.inspect())
+    }
+
+    /// Lists the history of the images set of changes
+    pub fn history(&self) -> Result<History> {
+        self.runtime.block_on(self.image.history())
+    }
+
+    /// Tags the image as `repo:tag`
+    pub fn tag(&self, repo: &str, tag: &str) -> Result<StatusCode> {
+        self.runtime.block_on(self.image.tag(repo, tag))
+    }
+
+    /// Deletes an image
+    pub fn delete(&self) -> Result<Vec<Status>> {
+        self.runtime.block_on(self.image.delete())
+    }
+
+    /// Removes the image, or only untags it
+    pub fn remove(&self, opts: &ImageRemoveOptions) -> Result<Vec<Status>> {
+        self.runtime.block_on(self.image.remove(opts))
+    }
+
+    /// Pushes the image to its registry, yielding the progress of every layer
+    pub fn push(&self, opts: &PushOptions) -> Iter<PushEvent> {
+        Iter::new(self.image.push(opts), &self.runtime)
+    }
+
+    /// Export this image to a tarball
+    pub fn export(&self) -> Iter<Bytes> {
+        Iter::new(self.image.export(), &self.runtime)
+    }
+}
+
+/// Blocking interface for docker images
+pub struct BlockingImages {
+    images: Images,
+    runtime: Arc<Runtime>,
+}
+
+impl BlockingImages {
+    /// Builds a new image by reading a Dockerfile in a target directory
+    pub fn build(&self, opts: &BuildOptions) -> Iter<BuildEvent> {
+        Iter::new(self.images.build(opts), &self.runtime)
+    }
+
+    /// Builds an image, calling `on_event` with every output message. returns
+    /// the id of the built image
+    pub fn build_with_output<F>(&self, opts: &BuildOptions, on_event: F) -> Result<String>
+    where
+        F: FnMut(&BuildEvent),
+    {
+        self.runtime.block_on(self.images.build_with_output(opts, on_event))
+    }
+
+    /// Lists the docker images on the current docker host
+    pub fn list(&self, opts: &ImageListOptions) -> Result<Vec<ImageRep>> {
+        self.runtime.block_on(self.images.list(opts))
+    }
+
+    /// Search for docker images by term
+    pub fn search(&self, term: &str) -> Result<Vec<SearchResult>> {
+        self.runtime.block_on(self.images.search(term))
+    }
+
+    /// Pull and create a new docker images from an existing image, yielding
+    /// the progress of every layer
+    pub fn pull(&self, opts: &PullOptions) -> Iter<PullEvent> {
+        Iter::new(self.images.pull(opts), &self.runtime)
+    }
+
+    /// Pulls an image, calling `on_progress` after every message. returns the
+    /// digest of the pulled image
+    pub fn pull_with_progress<F>(&self, opts: &PullOptions, on_progress: F)
+        -> Result<Option<String>>
+    where
+        F: FnMut(&PullProgress),
+    {
+        self.runtime.block_on(self.images.pull_with_progress(opts, on_progress))
+    }
+
+    /// exports a collection of named images,
+    /// either by name, name:tag, or image id, into a tarball
+    pub fn export(&self, names: Vec<&str>) -> Iter<Bytes> {
+        Iter::new(self.images.export(names), &self.runtime)
+    }
+
+    /// exports a collection of named images into a tarball at `filepath`,
+    /// gzipped if asked to. returns the bytes written
+    pub fn export_to_path<P>(&self, names: Vec<&str>, filepath: P, gzip: bool) -> Result<u64>
+    where
+        P: AsRef<Path>,
+    {
+        self.runtime.block_on(self.images.export_to_path(names, filepath, gzip))
+    }
+
+    /// Loads the images of a tarball as produced by `export`
+    pub fn load<B>(&self, tarball: B, opts: &ImageLoadOptions) -> Iter<LoadEvent>
+    where
+        B: Into<Body>,
+    {
+        Iter::new(self.images.load(tarball.into(), opts), &self.runtime)
+    }
+
+    /// Creates an image `repo:tag` from the root filesystem in `source`
+    pub fn import<B>(&self, source: B, repo: &str, tag: &str, changes: &[&str]) -> Iter<PullEvent>
+    where
+        B: Into<Body>,
+    {
+        Iter::new(self.images.import(source.into(), repo, tag, changes), &self.runtime)
+    }
+}
+
+/// Blocking interface for accessing and manipulating a docker network
+pub struct BlockingNetwork<'b> {
+    network: Network<'b>,
+    runtime: Arc<Runtime>,
+}
+
+impl<'b> BlockingNetwork<'b> {
+    /// a getter for the network id
+    pub fn id(&self) -> &str {
+        self.network.id()
+    }
+
+    /// Inspects the current docker network instance's details
+    pub fn inspect(&self) -> Result<NetworkDetails> {
+        self.runtime.block_on(self.network.inspect())
+    }
+
+    /// Delete the network instance
+    pub fn delete(&self) -> Result<StatusCode> {
+        self.runtime.block_on(self.network.delete())
+    }
+
+    /// Connect container to network
+    pub fn connect(&self, opts: &ContainerConnectionOptions) -> Result<StatusCode> {
+        self.runtime.block_on(self.network.connect(opts))
+    }
+
+    /// Disconnect container to network
+    pub fn disconnect(&self, opts: &ContainerConnectionOptions) -> Result<StatusCode> {
+        self.runtime.block_on(self.network.disconnect(opts))
+    }
+}
+
+/// Blocking interface for docker networks
+pub struct BlockingNetworks {
+    networks: Networks,
+    runtime: Arc<Runtime>,
+}
+
+impl BlockingNetworks {
+    /// List the docker networks on the current docker host
+    pub fn list(&self, opts: &NetworkListOptions) -> Result<Vec<NetworkDetails>> {
+        self.runtime.block_on(self.networks.list(opts))
+    }
+
+    /// Create a new Network instance
+    pub fn create(&self, opts: &NetworkCreateOptions) -> Result<NetworkCreateInfo> {
+        self.runtime.block_on(self.networks.create(opts))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BlockingDocker;
+    use crate::build::{ContainerOptions, EventsOptions};
+    use crate::communicate::fake::FakeDocker;
+    use http::StatusCode;
+    use std::borrow::Cow;
+
+    #[test]
+    fn blocking_client() {
+        let fake = FakeDocker::new();
+        fake.add_image("alpine");
+        let docker = BlockingDocker::new(Box::new(fake.clone())).expect("Runtime");
+
+        assert_eq!(StatusCode::OK, docker.ping().expect("Ping"));
+
+        let mut opts = ContainerOptions::builder("alpine");
+        opts.name("web");
+        docker.containers().create(&opts.build()).expect("Created");
+        let container = docker.container(Cow::Borrowed("web"));
+        container.start().expect("Started");
+        assert!(container.inspect().expect("Inspected").State.Running);
+
+        let listed = docker.images().list(&Default::default()).expect("Listed");
+        assert_eq!(1, listed.len());
+
+        let events = EventsOptions::builder().since(&0).until(&u64::MAX).build();
+        let statuses: Vec<String> = docker.events(&events)
+            .map(|e| e.expect("Event").status.expect("Status"))
+            .collect();
+        assert_eq!(vec!["pull", "create", "start"], statuses);
+    }
+}
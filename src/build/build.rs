@@ -11,16 +11,20 @@ use self::serde_json::to_value as de_to_value;
 
 use std::cmp::Eq;
 use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 use std::hash::Hash;
 use std::iter::Peekable;
 use url::form_urlencoded;
 
-use errors::Error;
-use errors::Result;
+use crate::errors::Error;
+use crate::errors::Result;
+use crate::communicate::timeout::{RequestTimeouts, Timeout};
 
 #[derive(Default)]
 pub struct PullOptions {
     params: HashMap<&'static str, String>,
+    auth: Option<RegistryAuth>,
+    timeouts: RequestTimeouts,
 }
 
 impl PullOptions {
@@ -37,11 +41,23 @@ impl PullOptions {
             Some(form_urlencoded::serialize(&self.params))
         }
     }
+
+    /// credentials of the registry the image is pulled from
+    pub fn auth(&self) -> Option<&RegistryAuth> {
+        self.auth.as_ref()
+    }
+
+    /// timeouts of the request, overriding the client's ones
+    pub fn timeouts(&self) -> RequestTimeouts {
+        self.timeouts
+    }
 }
 
 #[derive(Default)]
 pub struct PullOptionsBuilder {
     params: HashMap<&'static str, String>,
+    auth: Option<RegistryAuth>,
+    timeouts: RequestTimeouts,
 }
 
 impl PullOptionsBuilder {
@@ -83,9 +99,124 @@ impl PullOptionsBuilder {
         self
     }
 
+    /// credentials of the registry the image is pulled from
+    pub fn auth(&mut self, auth: RegistryAuth) -> &mut PullOptionsBuilder {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Response timeout of the request, see [`RequestTimeouts`]
+    pub fn timeout<T>(&mut self, timeout: T) -> &mut PullOptionsBuilder
+    where
+        T: Into<Timeout>,
+    {
+        self.timeouts.response = timeout.into();
+        self
+    }
+
+    /// Idle timeout of the response, see [`RequestTimeouts`]
+    pub fn idle_timeout<T>(&mut self, timeout: T) -> &mut PullOptionsBuilder
+    where
+        T: Into<Timeout>,
+    {
+        self.timeouts.idle = timeout.into();
+        self
+    }
+
     pub fn build(&self) -> PullOptions {
         PullOptions {
             params: self.params.clone(),
+            auth: self.auth.clone(),
+            timeouts: self.timeouts,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct PushOptions {
+    params: HashMap<&'static str, String>,
+    auth: Option<RegistryAuth>,
+    timeouts: RequestTimeouts,
+}
+
+impl PushOptions {
+    /// return a new instance of a builder for options
+    pub fn builder() -> PushOptionsBuilder {
+        PushOptionsBuilder::new()
+    }
+
+    /// serialize options as a string. returns None if no options are defined
+    pub fn serialize(&self) -> Option<String> {
+        if self.params.is_empty() {
+            None
+        } else {
+            Some(form_urlencoded::serialize(&self.params))
+        }
+    }
+
+    /// credentials of the registry the image is pushed to
+    pub fn auth(&self) -> Option<&RegistryAuth> {
+        self.auth.as_ref()
+    }
+
+    /// timeouts of the request, overriding the client's ones
+    pub fn timeouts(&self) -> RequestTimeouts {
+        self.timeouts
+    }
+}
+
+#[derive(Default)]
+pub struct PushOptionsBuilder {
+    params: HashMap<&'static str, String>,
+    auth: Option<RegistryAuth>,
+    timeouts: RequestTimeouts,
+}
+
+impl PushOptionsBuilder {
+    pub fn new() -> PushOptionsBuilder {
+        PushOptionsBuilder {
+            ..Default::default()
+        }
+    }
+
+    /// tag to push, every tag of the image is pushed if not set
+    pub fn tag<T>(&mut self, t: T) -> &mut PushOptionsBuilder
+    where
+        T: Into<String>,
+    {
+        self.params.insert("tag", t.into());
+        self
+    }
+
+    /// credentials of the registry the image is pushed to
+    pub fn auth(&mut self, auth: RegistryAuth) -> &mut PushOptionsBuilder {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Response timeout of the request, see [`RequestTimeouts`]
+    pub fn timeout<T>(&mut self, timeout: T) -> &mut PushOptionsBuilder
+    where
+        T: Into<Timeout>,
+    {
+        self.timeouts.response = timeout.into();
+        self
+    }
+
+    /// Idle timeout of the response, see [`RequestTimeouts`]
+    pub fn idle_timeout<T>(&mut self, timeout: T) -> &mut PushOptionsBuilder
+    where
+        T: Into<Timeout>,
+    {
+        self.timeouts.idle = timeout.into();
+        self
+    }
+
+    pub fn build(&self) -> PushOptions {
+        PushOptions {
+            params: self.params.clone(),
+            auth: self.auth.clone(),
+            timeouts: self.timeouts,
         }
     }
 }
@@ -94,6 +225,10 @@ impl PullOptionsBuilder {
 pub struct BuildOptions {
     pub path: String,
     params: HashMap<&'static str, String>,
+    /// parameters which may be given several times, such as `t`
+    repeated: Vec<(&'static str, String)>,
+    registry_auths: BTreeMap<String, RegistryAuth>,
+    timeouts: RequestTimeouts,
 }
 
 impl BuildOptions {
@@ -109,18 +244,50 @@ impl BuildOptions {
 
     /// serialize options as a string. returns None if no options are defined
     pub fn serialize(&self) -> Option<String> {
-        if self.params.is_empty() {
+        if self.params.is_empty() && self.repeated.is_empty() {
             None
         } else {
-            Some(form_urlencoded::serialize(&self.params))
+            let params = self.params.iter()
+                .chain(self.repeated.iter().map(|(k, v)| (k, v)))
+                .map(|(k, v)| (*k, v.as_str()));
+            Some(form_urlencoded::serialize(params.collect::<Vec<_>>()))
         }
     }
+
+    /// path of the Dockerfile within the build context
+    pub fn dockerfile(&self) -> &str {
+        self.params.get("dockerfile").map(String::as_str).unwrap_or("Dockerfile")
+    }
+
+    /// serialize the registry credentials as `X-Registry-Config`. returns
+    /// None if no credentials are defined
+    pub fn registry_config(&self) -> Option<String> {
+        if self.registry_auths.is_empty() {
+            None
+        } else {
+            let json = ser_to_string(&self.registry_auths)
+                .expect("Registry config serialization failed");
+            Some(base64::encode_config(&json, base64::URL_SAFE))
+        }
+    }
+
+    /// timeouts of the request, overriding the client's ones
+    pub fn timeouts(&self) -> RequestTimeouts {
+        self.timeouts
+    }
 }
 
 #[derive(Default)]
 pub struct BuildOptionsBuilder {
     path: String,
     params: HashMap<&'static str, String>,
+    tags: Vec<String>,
+    extra_hosts: Vec<String>,
+    buildargs: BTreeMap<String, String>,
+    labels: BTreeMap<String, String>,
+    cachefrom: Vec<String>,
+    registry_auths: BTreeMap<String, RegistryAuth>,
+    timeouts: RequestTimeouts,
 }
 
 impl BuildOptionsBuilder {
@@ -145,12 +312,13 @@ impl BuildOptionsBuilder {
         self
     }
 
-    /// tag this image with a name after building it
+    /// tag this image with a name after building it, may be called several
+    /// times to apply multiple tags
     pub fn tag<T>(&mut self, t: T) -> &mut BuildOptionsBuilder
     where
         T: Into<String>,
     {
-        self.params.insert("t", t.into());
+        self.tags.push(t.into());
         self
     }
 
@@ -163,7 +331,7 @@ impl BuildOptionsBuilder {
     }
 
     /// don't use the image cache when building image
-    pub fn nocache<R>(&mut self, nc: bool) -> &mut BuildOptionsBuilder {
+    pub fn nocache(&mut self, nc: bool) -> &mut BuildOptionsBuilder {
         self.params.insert("nocache", nc.to_string());
         self
     }
@@ -187,18 +355,151 @@ impl BuildOptionsBuilder {
         self
     }
 
-    // todo: memory
+    /// value of an `ARG` of the Dockerfile
+    pub fn buildarg<K, V>(&mut self, key: K, value: V) -> &mut BuildOptionsBuilder
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.buildargs.insert(key.into(), value.into());
+        self
+    }
+
+    /// label to set on the image
+    pub fn label<K, V>(&mut self, key: K, value: V) -> &mut BuildOptionsBuilder
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.labels.insert(key.into(), value.into());
+        self
+    }
+
+    /// stage of a multi-stage Dockerfile to build
+    pub fn target<T>(&mut self, t: T) -> &mut BuildOptionsBuilder
+    where
+        T: Into<String>,
+    {
+        self.params.insert("target", t.into());
+        self
+    }
+
+    /// image used as a cache source, may be called several times
+    pub fn cachefrom<I>(&mut self, image: I) -> &mut BuildOptionsBuilder
+    where
+        I: Into<String>,
+    {
+        self.cachefrom.push(image.into());
+        self
+    }
+
+    /// platform to build for, e.g. `linux/arm64`
+    pub fn platform<P>(&mut self, p: P) -> &mut BuildOptionsBuilder
+    where
+        P: Into<String>,
+    {
+        self.params.insert("platform", p.into());
+        self
+    }
+
+    /// attempt to pull the base images even if older ones exist locally
+    pub fn pull(&mut self, p: bool) -> &mut BuildOptionsBuilder {
+        self.params.insert("pull", p.to_string());
+        self
+    }
+
+    /// squash the resulting layers into a single one, experimental daemons only
+    pub fn squash(&mut self, s: bool) -> &mut BuildOptionsBuilder {
+        self.params.insert("squash", s.to_string());
+        self
+    }
+
+    /// size of `/dev/shm` in bytes
+    pub fn shmsize(&mut self, bytes: u64) -> &mut BuildOptionsBuilder {
+        self.params.insert("shmsize", bytes.to_string());
+        self
+    }
+
+    /// memory limit of the build containers in bytes
+    pub fn memory(&mut self, bytes: u64) -> &mut BuildOptionsBuilder {
+        self.params.insert("memory", bytes.to_string());
+        self
+    }
+
+    /// microseconds of CPU time per period the build containers may use
+    pub fn cpuquota(&mut self, quota: u64) -> &mut BuildOptionsBuilder {
+        self.params.insert("cpuquota", quota.to_string());
+        self
+    }
+
+    /// `host:ip` entry added to `/etc/hosts` of the build containers, may be
+    /// called several times
+    pub fn extra_host<H>(&mut self, host: H) -> &mut BuildOptionsBuilder
+    where
+        H: Into<String>,
+    {
+        self.extra_hosts.push(host.into());
+        self
+    }
+
     // todo: memswap
     // todo: cpushares
     // todo: cpusetcpus
     // todo: cpuperiod
-    // todo: cpuquota
-    // todo: buildargs
+
+    /// credentials of a registry base images are pulled from
+    pub fn registry_auth<R>(&mut self, registry: R, auth: RegistryAuth) -> &mut BuildOptionsBuilder
+    where
+        R: Into<String>,
+    {
+        self.registry_auths.insert(registry.into(), auth);
+        self
+    }
+
+    /// Response timeout of the request, see [`RequestTimeouts`]
+    pub fn timeout<T>(&mut self, timeout: T) -> &mut BuildOptionsBuilder
+    where
+        T: Into<Timeout>,
+    {
+        self.timeouts.response = timeout.into();
+        self
+    }
+
+    /// Idle timeout of the response, see [`RequestTimeouts`]
+    pub fn idle_timeout<T>(&mut self, timeout: T) -> &mut BuildOptionsBuilder
+    where
+        T: Into<Timeout>,
+    {
+        self.timeouts.idle = timeout.into();
+        self
+    }
 
     pub fn build(&self) -> BuildOptions {
+        let mut params = self.params.clone();
+        if !self.buildargs.is_empty() {
+            params.insert("buildargs", ser_to_string(&self.buildargs)
+                .expect("Build args serialization failed"));
+        }
+        if !self.labels.is_empty() {
+            params.insert("labels", ser_to_string(&self.labels)
+                .expect("Labels serialization failed"));
+        }
+        if !self.cachefrom.is_empty() {
+            params.insert("cachefrom", ser_to_string(&self.cachefrom)
+                .expect("Cache sources serialization failed"));
+        }
+
+        let repeated = self.tags.iter()
+            .map(|t| ("t", t.clone()))
+            .chain(self.extra_hosts.iter().map(|h| ("extrahosts", h.clone())))
+            .collect();
+
         BuildOptions {
             path: self.path.clone(),
-            params: self.params.clone(),
+            params,
+            repeated,
+            registry_auths: self.registry_auths.clone(),
+            timeouts: self.timeouts,
         }
     }
 }
@@ -338,10 +639,19 @@ impl ContainerOptions {
 
     /// serialize options as a string. returns None if no options are defined
     pub fn serialize(&self) -> Result<String> {
-        Ok(ser_to_string(&de_to_value(self)?)?)
+        let mut body = Value::Object(Map::new());
+        self.parse_from(&self.params, &mut body);
+        self.parse_from(&self.params_list, &mut body);
+        self.parse_from(&self.params_hash, &mut body);
+        body.as_object_mut()
+            .expect("Body is not an object")
+            .entry("HostConfig")
+            .or_insert(Value::Object(Map::new()));
+
+        Ok(ser_to_string(&body)?)
     }
 
-    pub fn parse_from<'a, K, V>(&self, params: &'a HashMap<K, V>, body: &mut Value)
+    pub fn parse_from<K, V>(&self, params: &HashMap<K, V>, body: &mut Value)
     where
         K: ToString + Eq + Hash,
         V: Serialize,
@@ -385,7 +695,7 @@ impl ContainerOptionsBuilder {
         for v in volumes {
             self.params_list
                 .entry("HostConfig.Binds")
-                .or_insert(Vec::new())
+                .or_default()
                 .push(v.to_owned());
         }
         self
@@ -395,7 +705,7 @@ impl ContainerOptionsBuilder {
         for link in links {
             self.params_list
                 .entry("HostConfig.Links")
-                .or_insert(Vec::new())
+                .or_default()
                 .push(link.to_owned());
         }
         self
@@ -405,7 +715,7 @@ impl ContainerOptionsBuilder {
         for host in hosts {
             self.params_list
                 .entry("HostConfig.ExtraHosts")
-                .or_insert(Vec::new())
+                .or_default()
                 .push(host.to_owned());
         }
 
@@ -416,7 +726,7 @@ impl ContainerOptionsBuilder {
         for volume in volumes {
             self.params_list
                 .entry("HostConfig.VolumesFrom")
-                .or_insert(Vec::new())
+                .or_default()
                 .push(volume.to_owned());
         }
         self
@@ -434,7 +744,7 @@ impl ContainerOptionsBuilder {
         for env in envs {
             self.params_list
                 .entry("Env")
-                .or_insert(Vec::new())
+                .or_default()
                 .push(env.to_owned());
         }
         self
@@ -444,7 +754,7 @@ impl ContainerOptionsBuilder {
         for cmd in cmds {
             self.params_list
                 .entry("Cmd")
-                .or_insert(Vec::new())
+                .or_default()
                 .push(cmd.to_owned());
         }
         self
@@ -462,7 +772,7 @@ impl ContainerOptionsBuilder {
         for c in capabilities {
             self.params_list
                 .entry("HostConfig.CapAdd")
-                .or_insert(Vec::new())
+                .or_default()
                 .push(c.to_owned());
         }
         self
@@ -475,7 +785,7 @@ impl ContainerOptionsBuilder {
         for d in devices {
             self.params_hash
                 .entry("HostConfig.Devices".to_string())
-                .or_insert(Vec::new())
+                .or_default()
                 .push(d);
         }
         self
@@ -528,6 +838,8 @@ pub struct ExecContainerOptions {
     params: HashMap<&'static str, Vec<String>>,
     #[serde(flatten)]
     params_bool: HashMap<&'static str, bool>,
+    #[serde(skip)]
+    timeouts: RequestTimeouts,
 }
 
 impl ExecContainerOptions {
@@ -541,12 +853,19 @@ impl ExecContainerOptions {
         let a = ser_to_string(&self).map_err(Error::from);
         Some(a.expect("Exec options serialization failed"))
     }
+
+    /// timeouts of the exec's output stream, which never idles out unless
+    /// asked to
+    pub fn timeouts(&self) -> RequestTimeouts {
+        self.timeouts.streaming()
+    }
 }
 
 #[derive(Default)]
 pub struct ExecContainerOptionsBuilder {
     params: HashMap<&'static str, Vec<String>>,
     params_bool: HashMap<&'static str, bool>,
+    timeouts: RequestTimeouts,
 }
 
 impl ExecContainerOptionsBuilder {
@@ -554,6 +873,7 @@ impl ExecContainerOptionsBuilder {
         ExecContainerOptionsBuilder {
             params: HashMap::new(),
             params_bool: HashMap::new(),
+            timeouts: RequestTimeouts::default(),
         }
     }
 
@@ -562,7 +882,7 @@ impl ExecContainerOptionsBuilder {
         for cmd in cmds {
             self.params
                 .entry("Cmd")
-                .or_insert(Vec::new())
+                .or_default()
                 .push(cmd.to_owned());
         }
         self
@@ -573,7 +893,7 @@ impl ExecContainerOptionsBuilder {
         for env in envs {
             self.params
                 .entry("Env")
-                .or_insert(Vec::new())
+                .or_default()
                 .push(env.to_owned());
         }
         self
@@ -591,10 +911,29 @@ impl ExecContainerOptionsBuilder {
         self
     }
 
+    /// Response timeout of the request, see [`RequestTimeouts`]
+    pub fn timeout<T>(&mut self, timeout: T) -> &mut ExecContainerOptionsBuilder
+    where
+        T: Into<Timeout>,
+    {
+        self.timeouts.response = timeout.into();
+        self
+    }
+
+    /// Idle timeout of the response, see [`RequestTimeouts`]
+    pub fn idle_timeout<T>(&mut self, timeout: T) -> &mut ExecContainerOptionsBuilder
+    where
+        T: Into<Timeout>,
+    {
+        self.timeouts.idle = timeout.into();
+        self
+    }
+
     pub fn build(&self) -> ExecContainerOptions {
         ExecContainerOptions {
             params: self.params.clone(),
             params_bool: self.params_bool.clone(),
+            timeouts: self.timeouts,
         }
     }
 }
@@ -670,6 +1009,7 @@ impl ContainerArchiveOptionsBuilder {
 #[derive(Default)]
 pub struct EventsOptions {
     params: HashMap<&'static str, String>,
+    timeouts: RequestTimeouts,
 }
 
 impl EventsOptions {
@@ -685,6 +1025,11 @@ impl EventsOptions {
             Some(form_urlencoded::serialize(&self.params))
         }
     }
+
+    /// timeouts of the event stream, which never idles out unless asked to
+    pub fn timeouts(&self) -> RequestTimeouts {
+        self.timeouts.streaming()
+    }
 }
 
 pub enum EventFilterType {
@@ -729,6 +1074,7 @@ pub struct EventsOptionsBuilder {
     networks: Vec<String>,
     daemons: Vec<String>,
     types: Vec<String>,
+    timeouts: RequestTimeouts,
 }
 
 impl EventsOptionsBuilder {
@@ -795,9 +1141,28 @@ impl EventsOptionsBuilder {
         self
     }
 
+    /// Response timeout of the request, see [`RequestTimeouts`]
+    pub fn timeout<T>(&mut self, timeout: T) -> &mut EventsOptionsBuilder
+    where
+        T: Into<Timeout>,
+    {
+        self.timeouts.response = timeout.into();
+        self
+    }
+
+    /// Idle timeout of the response, see [`RequestTimeouts`]
+    pub fn idle_timeout<T>(&mut self, timeout: T) -> &mut EventsOptionsBuilder
+    where
+        T: Into<Timeout>,
+    {
+        self.timeouts.idle = timeout.into();
+        self
+    }
+
     pub fn build(&self) -> EventsOptions {
         EventsOptions {
             params: self.params.clone(),
+            timeouts: self.timeouts,
         }
     }
 }
@@ -806,6 +1171,7 @@ impl EventsOptionsBuilder {
 #[derive(Default)]
 pub struct LogsOptions {
     params: HashMap<&'static str, String>,
+    timeouts: RequestTimeouts,
 }
 
 impl LogsOptions {
@@ -822,12 +1188,21 @@ impl LogsOptions {
             Some(form_urlencoded::serialize(&self.params))
         }
     }
+
+    /// timeouts of the request, followed logs never idle out unless asked to
+    pub fn timeouts(&self) -> RequestTimeouts {
+        match self.params.get("follow").map(String::as_str) {
+            Some("true") => self.timeouts.streaming(),
+            _ => self.timeouts,
+        }
+    }
 }
 
 /// Builder interface for `LogsOptions`
 #[derive(Default)]
 pub struct LogsOptionsBuilder {
     params: HashMap<&'static str, String>,
+    timeouts: RequestTimeouts,
 }
 
 impl LogsOptionsBuilder {
@@ -863,9 +1238,141 @@ impl LogsOptionsBuilder {
         self
     }
 
+    /// Response timeout of the request, see [`RequestTimeouts`]
+    pub fn timeout<T>(&mut self, timeout: T) -> &mut LogsOptionsBuilder
+    where
+        T: Into<Timeout>,
+    {
+        self.timeouts.response = timeout.into();
+        self
+    }
+
+    /// Idle timeout of the response, see [`RequestTimeouts`]
+    pub fn idle_timeout<T>(&mut self, timeout: T) -> &mut LogsOptionsBuilder
+    where
+        T: Into<Timeout>,
+    {
+        self.timeouts.idle = timeout.into();
+        self
+    }
+
     pub fn build(&self) -> LogsOptions {
         LogsOptions {
             params: self.params.clone(),
+            timeouts: self.timeouts,
+        }
+    }
+}
+
+/// Options of waiting for a container to stop
+#[derive(Default)]
+pub struct WaitOptions {
+    timeouts: RequestTimeouts,
+}
+
+impl WaitOptions {
+    /// return a new instance of a builder for options
+    pub fn builder() -> WaitOptionsBuilder {
+        WaitOptionsBuilder::new()
+    }
+
+    /// timeouts of the request, which waits for the container as long as it runs
+    /// unless asked not to
+    pub fn timeouts(&self) -> RequestTimeouts {
+        self.timeouts.streaming()
+    }
+}
+
+/// Builder interface for `WaitOptions`
+#[derive(Default)]
+pub struct WaitOptionsBuilder {
+    timeouts: RequestTimeouts,
+}
+
+impl WaitOptionsBuilder {
+    pub fn new() -> WaitOptionsBuilder {
+        WaitOptionsBuilder {
+            ..Default::default()
+        }
+    }
+
+    /// Response timeout of the request, see [`RequestTimeouts`]
+    pub fn timeout<T>(&mut self, timeout: T) -> &mut WaitOptionsBuilder
+    where
+        T: Into<Timeout>,
+    {
+        self.timeouts.response = timeout.into();
+        self
+    }
+
+    /// Idle timeout of the response, see [`RequestTimeouts`]
+    pub fn idle_timeout<T>(&mut self, timeout: T) -> &mut WaitOptionsBuilder
+    where
+        T: Into<Timeout>,
+    {
+        self.timeouts.idle = timeout.into();
+        self
+    }
+
+    pub fn build(&self) -> WaitOptions {
+        WaitOptions {
+            timeouts: self.timeouts,
+        }
+    }
+}
+
+/// Options of streaming the stats of a container
+#[derive(Default)]
+pub struct StatsOptions {
+    timeouts: RequestTimeouts,
+}
+
+impl StatsOptions {
+    /// return a new instance of a builder for options
+    pub fn builder() -> StatsOptionsBuilder {
+        StatsOptionsBuilder::new()
+    }
+
+    /// timeouts of the stats stream, which never idles out unless asked to
+    pub fn timeouts(&self) -> RequestTimeouts {
+        self.timeouts.streaming()
+    }
+}
+
+/// Builder interface for `StatsOptions`
+#[derive(Default)]
+pub struct StatsOptionsBuilder {
+    timeouts: RequestTimeouts,
+}
+
+impl StatsOptionsBuilder {
+    pub fn new() -> StatsOptionsBuilder {
+        StatsOptionsBuilder {
+            ..Default::default()
+        }
+    }
+
+    /// Response timeout of the request, see [`RequestTimeouts`]
+    pub fn timeout<T>(&mut self, timeout: T) -> &mut StatsOptionsBuilder
+    where
+        T: Into<Timeout>,
+    {
+        self.timeouts.response = timeout.into();
+        self
+    }
+
+    /// Idle timeout of the response, see [`RequestTimeouts`]
+    pub fn idle_timeout<T>(&mut self, timeout: T) -> &mut StatsOptionsBuilder
+    where
+        T: Into<Timeout>,
+    {
+        self.timeouts.idle = timeout.into();
+        self
+    }
+
+    pub fn build(&self) -> StatsOptions {
+        StatsOptions {
+            timeouts: self.timeouts,
         }
     }
 }
@@ -1000,6 +1507,135 @@ impl RmContainerOptionsBuilder {
     }
 }
 
+/// Options of removing an image or one of its tags
+#[derive(Default)]
+pub struct ImageRemoveOptions {
+    params: HashMap<&'static str, String>,
+}
+
+impl ImageRemoveOptions {
+    /// return a new instance of a builder for options
+    pub fn builder() -> ImageRemoveOptionsBuilder {
+        ImageRemoveOptionsBuilder::new()
+    }
+
+    /// serialize options as a string. returns None if no options are defined
+    pub fn serialize(&self) -> Option<String> {
+        if self.params.is_empty() {
+            None
+        } else {
+            Some(form_urlencoded::serialize(&self.params))
+        }
+    }
+}
+
+/// Builder interface for `ImageRemoveOptions`
+#[derive(Default)]
+pub struct ImageRemoveOptionsBuilder {
+    params: HashMap<&'static str, String>,
+}
+
+impl ImageRemoveOptionsBuilder {
+    pub fn new() -> ImageRemoveOptionsBuilder {
+        ImageRemoveOptionsBuilder {
+            ..Default::default()
+        }
+    }
+
+    /// Remove the image even if it is used by stopped containers or has
+    /// other tags
+    pub fn force(&mut self, f: bool) -> &mut ImageRemoveOptionsBuilder {
+        self.params.insert("force", f.to_string());
+        self
+    }
+
+    /// Keep untagged parent images
+    pub fn noprune(&mut self, n: bool) -> &mut ImageRemoveOptionsBuilder {
+        self.params.insert("noprune", n.to_string());
+        self
+    }
+
+    pub fn build(&self) -> ImageRemoveOptions {
+        ImageRemoveOptions {
+            params: self.params.clone(),
+        }
+    }
+}
+
+/// Options of loading images from a tarball
+#[derive(Default)]
+pub struct ImageLoadOptions {
+    params: HashMap<&'static str, String>,
+    timeouts: RequestTimeouts,
+}
+
+impl ImageLoadOptions {
+    /// return a new instance of a builder for options
+    pub fn builder() -> ImageLoadOptionsBuilder {
+        ImageLoadOptionsBuilder::new()
+    }
+
+    /// serialize options as a string. returns None if no options are defined
+    pub fn serialize(&self) -> Option<String> {
+        if self.params.is_empty() {
+            None
+        } else {
+            Some(form_urlencoded::serialize(&self.params))
+        }
+    }
+
+    /// timeouts of the request, overriding the client's ones
+    pub fn timeouts(&self) -> RequestTimeouts {
+        self.timeouts
+    }
+}
+
+/// Builder interface for `ImageLoadOptions`
+#[derive(Default)]
+pub struct ImageLoadOptionsBuilder {
+    params: HashMap<&'static str, String>,
+    timeouts: RequestTimeouts,
+}
+
+impl ImageLoadOptionsBuilder {
+    pub fn new() -> ImageLoadOptionsBuilder {
+        ImageLoadOptionsBuilder {
+            ..Default::default()
+        }
+    }
+
+    /// Suppress the progress messages, only the loaded images are reported
+    pub fn quiet(&mut self, q: bool) -> &mut ImageLoadOptionsBuilder {
+        self.params.insert("quiet", q.to_string());
+        self
+    }
+
+    /// Response timeout of the request, see [`RequestTimeouts`]
+    pub fn timeout<T>(&mut self, timeout: T) -> &mut ImageLoadOptionsBuilder
+    where
+        T: Into<Timeout>,
+    {
+        self.timeouts.response = timeout.into();
+        self
+    }
+
+    /// Idle timeout of the response, see [`RequestTimeouts`]
+    pub fn idle_timeout<T>(&mut self, timeout: T) -> &mut ImageLoadOptionsBuilder
+    where
+        T: Into<Timeout>,
+    {
+        self.timeouts.idle = timeout.into();
+        self
+    }
+
+    pub fn build(&self) -> ImageLoadOptions {
+        ImageLoadOptions {
+            params: self.params.clone(),
+            timeouts: self.timeouts,
+        }
+    }
+}
+
 /// Options for filtering networks list results
 #[derive(Default)]
 pub struct NetworkListOptions {
@@ -1037,9 +1673,9 @@ impl NetworkCreateOptions {
         ser_to_string(&self).map_err(Error::from)
     }
 
-    pub fn parse_from<'a, K, V>(
+    pub fn parse_from<K, V>(
         &self,
-        params: &'a HashMap<K, V>,
+        params: &HashMap<K, V>,
         body: &mut BTreeMap<String, Value>,
     ) where
         K: ToString + Eq + Hash,
@@ -1087,7 +1723,7 @@ impl NetworkCreateOptionsBuilder {
         for l in labels {
             self.params_hash
                 .entry("Labels".to_string())
-                .or_insert(Vec::new())
+                .or_default()
                 .push(l)
         }
         self
@@ -1125,9 +1761,9 @@ impl ContainerConnectionOptions {
             .expect("ContainerConnectionOptions serialization failed"))
     }
 
-    pub fn parse_from<'a, K, V>(
+    pub fn parse_from<K, V>(
         &self,
-        params: &'a HashMap<K, V>,
+        params: &HashMap<K, V>,
         body: &mut BTreeMap<String, Value>,
     ) where
         K: ToString + Eq + Hash,
@@ -1151,9 +1787,136 @@ impl ContainerConnectionOptions {
     }
 }
 
+/// Credentials for a docker registry, sent as `X-Registry-Auth`
+#[derive(Clone, Default, PartialEq, Serialize)]
+pub struct RegistryAuth {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    password: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    email: Option<String>,
+    #[serde(rename = "serveraddress", skip_serializing_if = "Option::is_none")]
+    server_address: Option<String>,
+    #[serde(rename = "identitytoken", skip_serializing_if = "Option::is_none")]
+    identity_token: Option<String>,
+}
+
+impl RegistryAuth {
+    /// return a new instance of a builder for registry credentials
+    pub fn builder() -> RegistryAuthBuilder {
+        RegistryAuthBuilder::new()
+    }
+
+    /// Credentials consisting of an identity token, as handed out by
+    /// `docker login` for registries using OAuth
+    pub fn token<T>(identity_token: T) -> RegistryAuth
+    where
+        T: Into<String>,
+    {
+        RegistryAuth {
+            identity_token: Some(identity_token.into()),
+            ..Default::default()
+        }
+    }
+
+    pub fn username(&self) -> Option<&str> {
+        self.username.as_deref()
+    }
+
+    pub fn server_address(&self) -> Option<&str> {
+        self.server_address.as_deref()
+    }
+
+    /// serialize the credentials as the daemon expects them, base64url
+    /// encoded JSON
+    pub fn serialize(&self) -> String {
+        let json = ser_to_string(self).expect("Registry auth serialization failed");
+        base64::encode_config(&json, base64::URL_SAFE)
+    }
+}
+
+/// Secrets are redacted, the credentials end up in the logs of options
+/// holding them otherwise
+impl fmt::Debug for RegistryAuth {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let redacted = |secret: &Option<String>| secret.as_ref().map(|_| "<redacted>");
+
+        f.debug_struct("RegistryAuth")
+            .field("username", &self.username)
+            .field("password", &redacted(&self.password))
+            .field("email", &self.email)
+            .field("server_address", &self.server_address)
+            .field("identity_token", &redacted(&self.identity_token))
+            .finish()
+    }
+}
+
+/// Builder interface for `RegistryAuth`
+#[derive(Default)]
+pub struct RegistryAuthBuilder {
+    auth: RegistryAuth,
+}
+
+impl RegistryAuthBuilder {
+    pub fn new() -> RegistryAuthBuilder {
+        RegistryAuthBuilder {
+            ..Default::default()
+        }
+    }
+
+    pub fn username<U>(&mut self, username: U) -> &mut RegistryAuthBuilder
+    where
+        U: Into<String>,
+    {
+        self.auth.username = Some(username.into());
+        self
+    }
+
+    pub fn password<P>(&mut self, password: P) -> &mut RegistryAuthBuilder
+    where
+        P: Into<String>,
+    {
+        self.auth.password = Some(password.into());
+        self
+    }
+
+    pub fn email<E>(&mut self, email: E) -> &mut RegistryAuthBuilder
+    where
+        E: Into<String>,
+    {
+        self.auth.email = Some(email.into());
+        self
+    }
+
+    /// Registry the credentials are meant for, e.g. `registry.example.com:5000`
+    pub fn server_address<S>(&mut self, address: S) -> &mut RegistryAuthBuilder
+    where
+        S: Into<String>,
+    {
+        self.auth.server_address = Some(address.into());
+        self
+    }
+
+    pub fn identity_token<T>(&mut self, token: T) -> &mut RegistryAuthBuilder
+    where
+        T: Into<String>,
+    {
+        self.auth.identity_token = Some(token.into());
+        self
+    }
+
+    pub fn build(&self) -> RegistryAuth {
+        self.auth.clone()
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::ContainerOptionsBuilder;
+    use super::{BuildOptions, ContainerOptionsBuilder, RegistryAuth, WaitOptions};
+    use crate::communicate::timeout::Timeout;
+    use std::collections::HashMap;
+    use std::time::Duration;
 
     #[test]
     fn container_options_simple() {
@@ -1222,4 +1985,88 @@ mod tests {
             options.serialize().expect("Error during serialization")
         );
     }
+
+    /// Options of all three kinds end up in one nested create body
+    #[test]
+    fn container_options_create_body() {
+        let mut device = HashMap::new();
+        device.insert("PathOnHost".to_owned(), "/dev/fuse".to_owned());
+        let options = ContainerOptionsBuilder::new("test_image")
+            .cmd(vec!["sh"])
+            .volumes(vec!["/data:/data"])
+            .network_mode("host")
+            .devices(vec![device])
+            .build();
+
+        assert_eq!(
+            concat!(r#"{"Cmd":["sh"],"HostConfig":{"Binds":["/data:/data"],"#,
+                    r#""Devices":[{"PathOnHost":"/dev/fuse"}],"NetworkMode":"host"},"#,
+                    r#""Image":"test_image"}"#),
+            options.serialize().expect("Error during serialization")
+        );
+    }
+
+    #[test]
+    fn registry_auth_encoding() {
+        let auth = RegistryAuth::builder()
+            .username("jane")
+            .password("hunter2")
+            .server_address("registry.example.com")
+            .build();
+
+        assert_eq!(
+            "eyJ1c2VybmFtZSI6ImphbmUiLCJwYXNzd29yZCI6Imh1bnRlcjIiLCJzZXJ2ZXJhZGRyZXNzIjoi\
+             cmVnaXN0cnkuZXhhbXBsZS5jb20ifQ==",
+            auth.serialize()
+        );
+
+        let options = BuildOptions::builder(".")
+            .registry_auth("registry.example.com", RegistryAuth::token("t0k3n"))
+            .build();
+        let config = base64::decode_config(&options.registry_config().expect("Config"),
+                                           base64::URL_SAFE).expect("Base64url");
+        assert_eq!(&br#"{"registry.example.com":{"identitytoken":"t0k3n"}}"#[..], &config[..]);
+    }
+
+    #[test]
+    fn registry_auth_debug_hides_secrets() {
+        let auth = RegistryAuth::builder().username("jane").password("hunter2").build();
+        let debug = format!("{:?} {:?}", Some(auth), RegistryAuth::token("t0k3n"));
+
+        assert!(debug.contains("jane"));
+        assert!(!debug.contains("hunter2"));
+        assert!(!debug.contains("t0k3n"));
+    }
+
+    #[test]
+    fn build_options_serialization() {
+        let options = BuildOptions::builder(".")
+            .tag("app:sha-123")
+            .tag("app:prod")
+            .buildarg("VERSION", "1.2")
+            .label("team", "web")
+            .cachefrom("app:prod")
+            .target("runtime")
+            .extra_host("db:10.0.0.2")
+            .build();
+
+        let mut query = options.serialize().expect("Query");
+        let mut pairs: Vec<String> = query.split('&').map(str::to_owned).collect();
+        pairs.sort();
+        query = pairs.join("&");
+        assert_eq!(concat!(
+            "buildargs=%7B%22VERSION%22%3A%221.2%22%7D&cachefrom=%5B%22app%3Aprod%22%5D",
+            "&extrahosts=db%3A10.0.0.2&labels=%7B%22team%22%3A%22web%22%7D",
+            "&t=app%3Aprod&t=app%3Asha-123&target=runtime"), query);
+    }
+
+    #[test]
+    fn wait_options_timeouts() {
+        let timeouts = WaitOptions::builder().build().timeouts();
+        assert_eq!(Timeout::Default, timeouts.response);
+        assert_eq!(Timeout::Never, timeouts.idle);
+
+        let timeouts = WaitOptions::builder().idle_timeout(Duration::from_secs(5)).build().timeouts();
+        assert_eq!(Timeout::After(Duration::from_secs(5)), timeouts.idle);
+    }
 }
@@ -1,2 +1,3 @@
+#[allow(clippy::module_inception)]
 mod build;
 pub use self::build::*;
\ No newline at end of file
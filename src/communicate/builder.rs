@@ -0,0 +1,453 @@
+//! Client construction from the docker CLI environment and context store
+
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use hyper::Uri;
+use serde_json::from_reader as de_from_reader;
+
+use crate::communicate::docker::{connect, DockerApi};
+use crate::communicate::ssh_docker::SshConfig;
+use crate::communicate::tls::TlsConfig;
+use crate::communicate::timeout::Timeouts;
+use crate::communicate::retry::{Retry, RetryPolicy};
+use crate::communicate::middleware::{Layer, Layered};
+use crate::communicate::docker::new_docker_with_interact;
+use crate::communicate::util::{DEFAULT_URI, URI_ENV};
+use crate::errors::{Error, ErrorKind, Result};
+
+pub(crate) const HOST_ENV: &str = "DOCKER_HOST";
+pub(crate) const CONTEXT_ENV: &str = "DOCKER_CONTEXT";
+pub(crate) const CONFIG_ENV: &str = "DOCKER_CONFIG";
+pub(crate) const TLS_VERIFY_ENV: &str = "DOCKER_TLS_VERIFY";
+pub(crate) const CERT_PATH_ENV: &str = "DOCKER_CERT_PATH";
+
+const DEFAULT_CONTEXT: &str = "default";
+
+/// Host of a docker daemon together with the TLS material or ssh client used
+/// to reach it
+pub(crate) struct Endpoint {
+    pub host: Uri,
+    #[cfg_attr(not(any(feature = "ssl", feature = "rustls")), allow(dead_code))]
+    pub tls: Option<TlsConfig>,
+    pub ssh: Option<SshConfig>,
+    pub timeouts: Timeouts,
+}
+
+impl Endpoint {
+    /// Endpoint for `host` using the TLS settings of `DOCKER_TLS_VERIFY` and
+    /// `DOCKER_CERT_PATH`
+    pub(crate) fn from_env_tls<F>(host: Uri, var: &F) -> Result<Endpoint>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let verify = is_set(var, TLS_VERIFY_ENV);
+
+        Ok(Endpoint {
+            host,
+            tls: var(CERT_PATH_ENV).map(|dir| TlsConfig::from_cert_path(dir, verify)).transpose()?,
+            ssh: None,
+            timeouts: Timeouts::default(),
+        })
+    }
+}
+
+/// `~/.docker/config.json`, only the parts this crate cares about
+#[derive(Default, Deserialize)]
+#[allow(non_snake_case)]
+pub(crate) struct CliConfig {
+    pub currentContext: Option<String>,
+    #[serde(default)]
+    pub auths: HashMap<String, AuthEntry>,
+    pub credsStore: Option<String>,
+    #[serde(default)]
+    pub credHelpers: HashMap<String, String>,
+}
+
+/// Credentials stored in `config.json` by `docker login`
+#[derive(Clone, Default, Deserialize)]
+pub(crate) struct AuthEntry {
+    /// base64 of `username:password`
+    pub auth: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub email: Option<String>,
+    pub identitytoken: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[allow(non_snake_case)]
+struct ContextMeta {
+    Name: String,
+    #[serde(default)]
+    Endpoints: HashMap<String, ContextEndpoint>,
+}
+
+#[derive(Deserialize)]
+#[allow(non_snake_case)]
+struct ContextEndpoint {
+    Host: Option<String>,
+    #[serde(default)]
+    SkipTLSVerify: bool,
+}
+
+/// Builder interface for a docker client resolving the daemon the way the
+/// docker CLI does
+///
+/// The explicitly set host wins over an explicitly set context, followed by
+/// `DOCKER_HOST`, `DOCKER_CONTEXT` and the `currentContext` of
+/// `~/.docker/config.json`.
+#[derive(Default)]
+pub struct DockerBuilder {
+    host: Option<Uri>,
+    context: Option<String>,
+    config_dir: Option<PathBuf>,
+    tls: Option<TlsConfig>,
+    ssh: Option<SshConfig>,
+    timeouts: Option<Timeouts>,
+    retry: Option<RetryPolicy>,
+    layers: Vec<Arc<dyn Layer>>,
+}
+
+impl DockerBuilder {
+    pub fn new() -> DockerBuilder {
+        DockerBuilder {
+            ..Default::default()
+        }
+    }
+
+    /// Creates a client for the daemon selected by `DOCKER_HOST`,
+    /// `DOCKER_CONTEXT` or the current docker CLI context
+    pub fn from_env() -> Result<Box<dyn DockerApi>> {
+        DockerBuilder::new().build()
+    }
+
+    /// Connect to this host, ignoring contexts and `DOCKER_HOST`. `tcp://`
+    /// hosts are read the same way as in `DOCKER_HOST`
+    pub fn host(&mut self, host: Uri) -> &mut DockerBuilder {
+        self.host = Some(host);
+        self
+    }
+
+    /// Use the named docker CLI context
+    pub fn context<S>(&mut self, name: S) -> &mut DockerBuilder
+    where
+        S: Into<String>,
+    {
+        self.context = Some(name.into());
+        self
+    }
+
+    /// Directory of the docker CLI configuration. defaults to `DOCKER_CONFIG`
+    /// or `~/.docker`
+    pub fn config_dir<P>(&mut self, dir: P) -> &mut DockerBuilder
+    where
+        P: Into<PathBuf>,
+    {
+        self.config_dir = Some(dir.into());
+        self
+    }
+
+    /// Use this TLS configuration instead of the certificates of the
+    /// environment or the context store
+    pub fn tls(&mut self, tls: TlsConfig) -> &mut DockerBuilder {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// Use this ssh client configuration for `ssh://` hosts
+    pub fn ssh(&mut self, ssh: SshConfig) -> &mut DockerBuilder {
+        self.ssh = Some(ssh);
+        self
+    }
+
+    /// Timeouts of the client's requests. defaults to `Timeouts::default()`
+    pub fn timeouts(&mut self, timeouts: Timeouts) -> &mut DockerBuilder {
+        self.timeouts = Some(timeouts);
+        self
+    }
+
+    /// Retry idempotent requests failing with transient transport errors
+    pub fn retry(&mut self, policy: RetryPolicy) -> &mut DockerBuilder {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Send every request through this layer, below the ones added before.
+    /// Retried requests pass the layers again
+    pub fn layer<L>(&mut self, layer: L) -> &mut DockerBuilder
+    where
+        L: Layer + 'static,
+    {
+        self.layers.push(Arc::new(layer));
+        self
+    }
+
+    pub fn build(&self) -> Result<Box<dyn DockerApi>> {
+        let mut endpoint = self.resolve(&|name| env::var(name).ok())?;
+        if self.tls.is_some() {
+            endpoint.tls = self.tls.clone();
+        }
+        endpoint.ssh = self.ssh.clone();
+        if let Some(timeouts) = self.timeouts {
+            endpoint.timeouts = timeouts;
+        }
+
+        let mut docker = connect(endpoint)?;
+        if !self.layers.is_empty() {
+            let mut layered = Layered::builder(docker.interact());
+            for layer in &self.layers {
+                layered.shared_layer(layer.clone());
+            }
+            docker = new_docker_with_interact(Arc::new(layered.build()));
+        }
+        if let Some(ref policy) = self.retry {
+            docker = new_docker_with_interact(Arc::new(Retry::new(docker.interact(), policy.clone())));
+        }
+
+        Ok(docker)
+    }
+
+    fn resolve<F>(&self, var: &F) -> Result<Endpoint>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        if let Some(ref host) = self.host {
+            let tls = self.tls.is_some() || is_set(var, TLS_VERIFY_ENV);
+            return Endpoint::from_env_tls(parse_host(&host.to_string(), tls)?, var);
+        }
+
+        let config_dir = self.config_dir.clone().or_else(|| config_dir(var));
+
+        let context = match self.context {
+            Some(ref name) => Some(name.clone()),
+            None if var(HOST_ENV).is_some() => None,
+            None => match var(CONTEXT_ENV) {
+                Some(name) => Some(name),
+                None => match config_dir {
+                    Some(ref dir) => load_config(dir)?.currentContext,
+                    None => None,
+                },
+            },
+        };
+
+        match (context, config_dir) {
+            (Some(ref name), Some(ref dir)) if name != DEFAULT_CONTEXT =>
+                load_context(dir, name),
+            (Some(ref name), None) if name != DEFAULT_CONTEXT =>
+                Err(ErrorKind::ContextNotFound(name.clone()).into()),
+            (_, dir) => default_endpoint(var, dir),
+        }
+    }
+}
+
+/// Location of the docker CLI configuration directory
+pub(crate) fn config_dir<F>(var: &F) -> Option<PathBuf>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    var(CONFIG_ENV)
+        .map(PathBuf::from)
+        .or_else(|| var("HOME")
+            .or_else(|| var("USERPROFILE"))
+            .map(|home| Path::new(&home).join(".docker")))
+}
+
+/// Reads `config.json` of the docker CLI, an absent file is an empty config
+pub(crate) fn load_config(config_dir: &Path) -> Result<CliConfig> {
+    let path = config_dir.join("config.json");
+
+    if !path.is_file() {
+        return Ok(CliConfig::default());
+    }
+
+    de_from_reader(File::open(path)?).map_err(Error::from)
+}
+
+fn is_set<F>(var: &F, name: &str) -> bool
+where
+    F: Fn(&str) -> Option<String>,
+{
+    var(name).map(|v| !v.is_empty()).unwrap_or(false)
+}
+
+/// Endpoint of the `default` context: `DOCKER_HOST`, the legacy
+/// `SHIPLIFT_URI` or the local unix socket
+fn default_endpoint<F>(var: &F, config_dir: Option<PathBuf>) -> Result<Endpoint>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let tls = is_set(var, TLS_VERIFY_ENV);
+    let host = var(HOST_ENV)
+        .or_else(|| var(URI_ENV))
+        .unwrap_or_else(|| DEFAULT_URI.to_owned());
+
+    let mut endpoint = Endpoint::from_env_tls(parse_host(&host, tls)?, var)?;
+    if tls && endpoint.tls.is_none() {
+        endpoint.tls = config_dir.map(|dir| TlsConfig::from_cert_path(dir, true)).transpose()?;
+    }
+
+    Ok(endpoint)
+}
+
+/// Looks the named context up in `contexts/meta/*/meta.json`, taking TLS
+/// material from the matching `contexts/tls/*/docker` directory
+fn load_context(config_dir: &Path, name: &str) -> Result<Endpoint> {
+    let contexts = config_dir.join("contexts");
+    let meta_dir = contexts.join("meta");
+
+    if !meta_dir.is_dir() {
+        return Err(ErrorKind::ContextNotFound(name.to_owned()).into());
+    }
+
+    for entry in fs::read_dir(&meta_dir)? {
+        let entry = entry?;
+        let meta_path = entry.path().join("meta.json");
+        if !meta_path.is_file() {
+            continue;
+        }
+
+        let meta: ContextMeta = de_from_reader(File::open(&meta_path)?)?;
+        if meta.Name != name {
+            continue;
+        }
+
+        let endpoint = meta.Endpoints
+            .get("docker")
+            .ok_or_else(|| Error::from(ErrorKind::NoHostString))?;
+        let host = endpoint.Host
+            .as_ref()
+            .ok_or_else(|| Error::from(ErrorKind::NoHostString))?;
+
+        let tls_dir = contexts.join("tls").join(entry.file_name()).join("docker");
+        let tls = if tls_dir.is_dir() {
+            Some(TlsConfig::from_cert_path(tls_dir, !endpoint.SkipTLSVerify)?)
+        } else {
+            None
+        };
+
+        return Ok(Endpoint {
+            host: parse_host(host, tls.is_some())?,
+            tls,
+            ssh: None,
+            timeouts: Timeouts::default(),
+        });
+    }
+
+    Err(ErrorKind::ContextNotFound(name.to_owned()).into())
+}
+
+/// Parses a docker host string, mapping `tcp://` onto http(s) and
+/// `unix:///path` onto the `unix://path` form the unix transport expects
+pub(crate) fn parse_host(host: &str, tls: bool) -> Result<Uri> {
+    let normalized = if let Some(path) = host.strip_prefix("unix:///") {
+        format!("unix://{}", path)
+    } else if let Some(address) = host.strip_prefix("tcp://") {
+        let scheme = if tls { "https" } else { "http" };
+        format!("{}://{}", scheme, address)
+    } else {
+        host.to_owned()
+    };
+
+    normalized.parse().map_err(Error::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_host, DockerBuilder};
+    use crate::communicate::tls::TlsConfig;
+    use crate::test_util::TempDir;
+    use std::collections::HashMap;
+
+    fn add_context(dir: &TempDir, id: &str, name: &str, host: &str, tls: bool) {
+        dir.write(&format!("contexts/meta/{}/meta.json", id), format!(
+            r#"{{"Name":"{}","Metadata":{{}},"Endpoints":{{"docker":{{"Host":"{}","SkipTLSVerify":false}}}}}}"#,
+            name, host));
+
+        if tls {
+            dir.write(&format!("contexts/tls/{}/docker/ca.pem", id), "-----BEGIN CERTIFICATE-----");
+        }
+    }
+
+    #[test]
+    fn host_string_normalization() {
+        let uri = |host, tls| parse_host(host, tls).expect("Valid host").to_string();
+
+        assert_eq!("unix://var/run/docker.sock", uri("unix:///var/run/docker.sock", false));
+        assert_eq!("http://10.0.0.1:2375/", uri("tcp://10.0.0.1:2375", false));
+        assert_eq!("https://10.0.0.1:2376/", uri("tcp://10.0.0.1:2376", true));
+    }
+
+    #[test]
+    fn explicit_tcp_host() {
+        let host = |builder: &mut DockerBuilder| builder
+            .host("tcp://10.0.0.1:2375".parse().expect("Uri"))
+            .resolve(&|_| None)
+            .expect("Resolved endpoint")
+            .host
+            .to_string();
+
+        assert_eq!("http://10.0.0.1:2375/", host(&mut DockerBuilder::new()));
+        assert_eq!("https://10.0.0.1:2375/",
+                   host(DockerBuilder::new().tls(TlsConfig::builder().build())));
+    }
+
+    #[test]
+    fn docker_host_wins_over_current_context() {
+        let dir = TempDir::new("docker-host");
+        dir.write("config.json", r#"{"currentContext":"remote"}"#);
+        add_context(&dir, "0abc", "remote", "tcp://remote:2376", true);
+
+        let mut env = HashMap::new();
+        env.insert("DOCKER_HOST", "tcp://local:2375".to_owned());
+        let endpoint = DockerBuilder::new()
+            .config_dir(dir.path())
+            .resolve(&|name| env.get(name).cloned())
+            .expect("Resolved endpoint");
+
+        assert_eq!("http://local:2375/", endpoint.host.to_string());
+        assert!(endpoint.tls.is_none());
+    }
+
+    #[test]
+    fn current_context_with_tls_material() {
+        let dir = TempDir::new("current-context");
+        dir.write("config.json", r#"{"currentContext":"remote"}"#);
+        add_context(&dir, "0abc", "other", "unix:///run/other.sock", false);
+        add_context(&dir, "1def", "remote", "tcp://remote:2376", true);
+
+        let endpoint = DockerBuilder::new()
+            .config_dir(dir.path())
+            .resolve(&|_| None)
+            .expect("Resolved endpoint");
+
+        assert_eq!("https://remote:2376/", endpoint.host.to_string());
+        let tls = endpoint.tls.expect("TLS material of the context");
+        assert!(tls.verify_hostname());
+        assert!(tls.ca().expect("CA of the context").is_some());
+        assert!(tls.identity().expect("Server authentication only").is_none());
+    }
+
+    #[test]
+    fn docker_context_env_and_missing_context() {
+        let dir = TempDir::new("context-env");
+        add_context(&dir, "0abc", "other", "unix:///run/other.sock", false);
+
+        let mut env = HashMap::new();
+        env.insert("DOCKER_CONTEXT", "other".to_owned());
+        let endpoint = DockerBuilder::new()
+            .config_dir(dir.path())
+            .resolve(&|name| env.get(name).cloned())
+            .expect("Resolved endpoint");
+        assert_eq!("unix://run/other.sock", endpoint.host.to_string());
+
+        assert!(DockerBuilder::new()
+            .config_dir(dir.path())
+            .context("missing")
+            .resolve(&|_| None)
+            .is_err());
+    }
+}
@@ -0,0 +1,652 @@
+//! Record and replay of the http exchanges with a docker daemon
+//!
+//! A `Recorder` wraps the transport of a real client and writes every
+//! request and response to a JSON cassette file. A `Replayer` serves the
+//! responses of such a cassette back without any daemon, keeping the chunk
+//! boundaries of streamed bodies such as logs, stats and events intact.
+//!
+//! Credentials never reach the cassette: the values of `X-Registry-Auth`,
+//! `X-Registry-Config`, `Authorization` and `Proxy-Authorization` are
+//! recorded as `<redacted>`.
+//!
+//! Only JSON request bodies are recorded in full. Other bodies, such as the
+//! tarballs of image loads and builds, are streamed through untouched and
+//! recorded as their length and FNV-1a digest, which is enough to match them
+//! on replay but not to read them back.
+//!
+//! ```no_run
+//! # extern crate async_docker;
+//! # use async_docker::{new_docker, new_docker_with_interact, DockerApi};
+//! # use async_docker::cassette::{Recorder, Replayer};
+//! # use std::sync::Arc;
+//! # fn main() -> async_docker::Result<()> {
+//! let docker = new_docker(None)?;
+//! let recording = new_docker_with_interact(
+//!     Arc::new(Recorder::new(docker.interact(), "ping.json")));
+//!
+//! let replaying = new_docker_with_interact(Arc::new(Replayer::load("ping.json")?));
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::{stream, Stream, TryStreamExt};
+use hyper::header::CONTENT_TYPE;
+use hyper::{HeaderMap, Method, Response};
+use serde_json::{from_reader as de_from_reader, to_writer_pretty};
+
+use crate::communicate::util::RequestArgs;
+use crate::communicate::version::ApiVersion;
+use crate::errors::{Error, ErrorKind, Result};
+use crate::transport::body::{into_box_error, Body};
+use crate::transport::interact::InteractApi;
+
+/// Recorded exchanges with a docker daemon, in the order they were made
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    /// Reads a cassette from a JSON file
+    pub fn load<P>(path: P) -> Result<Cassette>
+    where
+        P: AsRef<Path>,
+    {
+        de_from_reader(File::open(path)?).map_err(Error::from)
+    }
+
+    /// Writes the cassette to a JSON file, replacing its content
+    pub fn save<P>(&self, path: P) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        to_writer_pretty(File::create(path)?, self).map_err(Error::from)
+    }
+}
+
+/// Single request together with the response it got
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    /// Path without the API version prefix
+    pub path: String,
+    pub query: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<Data>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    /// Body chunks as they were received
+    pub chunks: Vec<Data>,
+}
+
+/// Body data, kept readable unless it is binary
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Data {
+    Text(String),
+    Base64(String),
+    /// Request body recorded without its content
+    Digest(Digest),
+}
+
+/// Length and 64-bit FNV-1a hash of a body
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Digest {
+    pub length: u64,
+    pub fnv1a: u64,
+}
+
+impl Default for Digest {
+    fn default() -> Self {
+        Digest {
+            length: 0,
+            fnv1a: 0xcbf2_9ce4_8422_2325,
+        }
+    }
+}
+
+impl Digest {
+    fn update(&mut self, bytes: &[u8]) {
+        self.length += bytes.len() as u64;
+        for byte in bytes {
+            self.fnv1a = (self.fnv1a ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn data(self) -> Option<Data> {
+        match self.length {
+            0 => None,
+            _ => Some(Data::Digest(self)),
+        }
+    }
+}
+
+impl Data {
+    pub fn from_bytes(bytes: &[u8]) -> Data {
+        match ::std::str::from_utf8(bytes) {
+            Ok(text) => Data::Text(text.to_owned()),
+            Err(_) => Data::Base64(base64::encode(bytes)),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        match *self {
+            Data::Text(ref text) => Ok(text.as_bytes().to_vec()),
+            Data::Base64(ref encoded) => base64::decode(encoded)
+                .map_err(|e| ErrorKind::InvalidCassette(e.to_string()).into()),
+            Data::Digest(_) => Err(ErrorKind::InvalidCassette(
+                "body was recorded as a digest only".to_owned()).into()),
+        }
+    }
+}
+
+/// Headers carrying credentials, recorded without their values
+const SECRET_HEADERS: [&str; 4] = [
+    "x-registry-auth",
+    "x-registry-config",
+    "authorization",
+    "proxy-authorization",
+];
+
+const REDACTED: &str = "<redacted>";
+
+fn headers(map: &HeaderMap) -> Vec<(String, String)> {
+    map.iter()
+        .map(|(name, value)| {
+            let value = if SECRET_HEADERS.contains(&name.as_str()) {
+                REDACTED.to_owned()
+            } else {
+                String::from_utf8_lossy(value.as_bytes()).into_owned()
+            };
+            (name.as_str().to_owned(), value)
+        })
+        .collect()
+}
+
+fn is_json(header: &HeaderMap) -> bool {
+    header.get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"))
+}
+
+fn recorded_request(opts: &RequestArgs, method: &Method) -> RecordedRequest {
+    RecordedRequest {
+        method: method.as_str().to_owned(),
+        path: opts.path.clone(),
+        query: opts.query.clone(),
+        headers: headers(&opts.header),
+        body: None,
+    }
+}
+
+/// `InteractApi` forwarding to another transport and recording every
+/// exchange to a cassette file
+///
+/// The file is rewritten whenever a response body has been read to its end
+/// or dropped, so interrupted streams are recorded up to that point.
+pub struct Recorder {
+    inner: Arc<dyn InteractApi>,
+    path: PathBuf,
+    cassette: Arc<Mutex<Cassette>>,
+}
+
+impl Recorder {
+    pub fn new<P>(inner: Arc<dyn InteractApi>, path: P) -> Recorder
+    where
+        P: Into<PathBuf>,
+    {
+        Recorder {
+            inner,
+            path: path.into(),
+            cassette: Arc::new(Mutex::new(Cassette::default())),
+        }
+    }
+
+    /// Interactions recorded so far
+    pub fn cassette(&self) -> Cassette {
+        self.cassette.lock().expect("Cassette lock poisoned").clone()
+    }
+}
+
+#[async_trait]
+impl InteractApi for Recorder {
+    async fn request(&self, mut opts: RequestArgs, method: Method) -> Result<Response<Body>> {
+        // the recorded headers are redacted, the daemon gets the real ones
+        let mut recorded = recorded_request(&opts, &method);
+        let body = ::std::mem::take(&mut opts.body);
+
+        let digest = if is_json(&opts.header) {
+            let body = body.bytes().await?;
+            if !body.is_empty() {
+                recorded.body = Some(Data::from_bytes(&body));
+            }
+            opts.body = Body::from(body);
+            None
+        } else {
+            let digest = Arc::new(Mutex::new(Digest::default()));
+            let sink = digest.clone();
+            opts.body = Body::wrap_stream(body
+                .inspect_ok(move |chunk| sink.lock().expect("Digest lock poisoned").update(chunk))
+                .map_err(into_box_error));
+            Some(digest)
+        };
+
+        let response = self.inner.request(opts, method).await?;
+
+        Ok(record(response, recorded, digest, self.cassette.clone(), self.path.clone()))
+    }
+
+    fn api_version(&self) -> Option<ApiVersion> {
+        self.inner.api_version()
+    }
+
+    fn set_api_version(&self, version: Option<ApiVersion>) {
+        self.inner.set_api_version(version)
+    }
+}
+
+fn record(response: Response<Body>, request: RecordedRequest, digest: Option<Arc<Mutex<Digest>>>,
+          cassette: Arc<Mutex<Cassette>>, path: PathBuf) -> Response<Body>
+{
+    let (parts, body) = response.into_parts();
+    let index = {
+        let mut cassette = cassette.lock().expect("Cassette lock poisoned");
+        cassette.interactions.push(Interaction {
+            request,
+            response: RecordedResponse {
+                status: parts.status.as_u16(),
+                headers: headers(&parts.headers),
+                chunks: Vec::new(),
+            },
+        });
+        cassette.interactions.len() - 1
+    };
+
+    let body = RecordingBody {
+        body,
+        digest,
+        cassette,
+        index,
+        path,
+        saved: false,
+    };
+
+    Response::from_parts(parts, Body::wrap_stream(body.map_err(into_box_error)))
+}
+
+/// Response body appending every chunk to the recorded interaction
+struct RecordingBody {
+    body: Body,
+    /// Digest of a streamed request body, complete once it has been sent
+    digest: Option<Arc<Mutex<Digest>>>,
+    cassette: Arc<Mutex<Cassette>>,
+    index: usize,
+    path: PathBuf,
+    saved: bool,
+}
+
+impl RecordingBody {
+    fn save(&mut self) {
+        if self.saved {
+            return;
+        }
+        self.saved = true;
+
+        let mut cassette = self.cassette.lock().expect("Cassette lock poisoned");
+        if let Some(ref digest) = self.digest {
+            cassette.interactions[self.index].request.body =
+                digest.lock().expect("Digest lock poisoned").data();
+        }
+
+        let result = cassette.save(&self.path);
+        if let Err(e) = result {
+            warn!("Failed to save cassette {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+impl Stream for RecordingBody {
+    type Item = Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Result<Bytes>>> {
+        match Pin::new(&mut self.body).poll_next(cx) {
+            Poll::Ready(Some(Ok(chunk))) => {
+                self.cassette.lock().expect("Cassette lock poisoned")
+                    .interactions[self.index].response.chunks
+                    .push(Data::from_bytes(&chunk));
+                Poll::Ready(Some(Ok(chunk)))
+            }
+            Poll::Ready(None) => {
+                self.save();
+                Poll::Ready(None)
+            }
+            other => other,
+        }
+    }
+}
+
+impl Drop for RecordingBody {
+    fn drop(&mut self) {
+        self.save();
+    }
+}
+
+/// `InteractApi` answering requests from a cassette
+///
+/// A request is served by the first interaction not yet replayed with the
+/// same method, path, query and body; headers are not compared, so the
+/// redacted credentials of a cassette match any credentials sent. Requests
+/// without such an interaction fail with `ErrorKind::UnmatchedRequest`.
+pub struct Replayer {
+    tape: Arc<Tape>,
+    version: RwLock<Option<ApiVersion>>,
+}
+
+/// Interactions of a cassette and which of them have been replayed
+struct Tape {
+    interactions: Vec<Interaction>,
+    replayed: Mutex<Vec<bool>>,
+}
+
+impl Replayer {
+    pub fn new(cassette: Cassette) -> Replayer {
+        let replayed = vec![false; cassette.interactions.len()];
+
+        Replayer {
+            tape: Arc::new(Tape {
+                interactions: cassette.interactions,
+                replayed: Mutex::new(replayed),
+            }),
+            version: RwLock::new(None),
+        }
+    }
+
+    /// Replays the cassette stored in a JSON file
+    pub fn load<P>(path: P) -> Result<Replayer>
+    where
+        P: AsRef<Path>,
+    {
+        Cassette::load(path).map(Replayer::new)
+    }
+
+    /// Whether every recorded interaction has been replayed
+    pub fn is_exhausted(&self) -> bool {
+        self.tape.replayed.lock().expect("Replay lock poisoned").iter().all(|r| *r)
+    }
+}
+
+impl Tape {
+    fn take(&self, request: &RecordedRequest) -> Option<&Interaction> {
+        let mut replayed = self.replayed.lock().expect("Replay lock poisoned");
+
+        let index = self.interactions.iter()
+            .enumerate()
+            .position(|(i, interaction)| {
+                let recorded = &interaction.request;
+                !replayed[i]
+                    && recorded.method == request.method
+                    && recorded.path == request.path
+                    && recorded.query == request.query
+                    && recorded.body == request.body
+            })?;
+
+        replayed[index] = true;
+        Some(&self.interactions[index])
+    }
+}
+
+fn replay(recorded: &RecordedResponse) -> Result<Response<Body>> {
+    let mut builder = Response::builder().status(recorded.status);
+    for (name, value) in &recorded.headers {
+        builder = builder.header(name.as_str(), value.as_str());
+    }
+
+    let chunks = recorded.chunks.iter()
+        .map(|chunk| chunk.to_bytes().map(Bytes::from).map_err(into_box_error))
+        .collect::<Vec<_>>();
+
+    builder.body(Body::wrap_stream(stream::iter(chunks))).map_err(Error::from)
+}
+
+#[async_trait]
+impl InteractApi for Replayer {
+    async fn request(&self, opts: RequestArgs, method: Method) -> Result<Response<Body>> {
+        let mut request = recorded_request(&opts, &method);
+        request.body = if is_json(&opts.header) {
+            let body = opts.body.bytes().await?;
+            if body.is_empty() { None } else { Some(Data::from_bytes(&body)) }
+        } else {
+            opts.body.try_fold(Digest::default(), |mut digest, chunk| async move {
+                digest.update(&chunk);
+                Ok(digest)
+            }).await?.data()
+        };
+
+        match self.tape.take(&request) {
+            Some(interaction) => replay(&interaction.response),
+            None => Err(ErrorKind::UnmatchedRequest(
+                format!("{} {}?{}", request.method, request.path, request.query)).into()),
+        }
+    }
+
+    fn api_version(&self) -> Option<ApiVersion> {
+        *self.version.read().expect("API version lock poisoned")
+    }
+
+    fn set_api_version(&self, version: Option<ApiVersion>) {
+        *self.version.write().expect("API version lock poisoned") = version;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cassette, Data, Interaction, RecordedRequest, RecordedResponse, Recorder,
+                Replayer};
+    use crate::build::{ImageLoadOptions, PullOptions, RegistryAuth};
+    use crate::communicate::util::X_REGISTRY_AUTH;
+    use crate::communicate::docker::new_docker_with_interact;
+    use crate::communicate::util::RequestArgs;
+    use crate::test_util::{Stub, TempDir};
+    use crate::transport::body::Body;
+    use crate::Result;
+    use bytes::Bytes;
+    use futures::{stream, TryStreamExt};
+    use hyper::header::{HeaderValue, CONTENT_TYPE};
+    use hyper::{HeaderMap, Method, Response};
+    use crate::transport::interact::InteractApi;
+    use tokio::runtime::Runtime;
+    use std::sync::Arc;
+
+    const EVENTS: [&str; 2] = [
+        "{\"status\":\"start\",\"id\":\"a\",\"time\":1,\"timeNano\":1}\n",
+        "{\"status\":\"die\",\"id\":\"a\",\"time\":2,\"timeNano\":2}\n",
+    ];
+
+    fn events_cassette() -> Cassette {
+        Cassette {
+            interactions: vec![Interaction {
+                request: RecordedRequest {
+                    method: "GET".to_owned(),
+                    path: "/events".to_owned(),
+                    query: String::new(),
+                    headers: Vec::new(),
+                    body: None,
+                },
+                response: RecordedResponse {
+                    status: 200,
+                    headers: vec![("content-type".to_owned(), "application/json".to_owned())],
+                    chunks: EVENTS.iter().map(|e| Data::Text(e.to_string())).collect(),
+                },
+            }],
+        }
+    }
+
+    fn chunks<F>(response: F) -> Vec<Vec<u8>>
+        where F: ::std::future::Future<Output=Result<Response<Body>>>
+    {
+        Runtime::new().expect("Runtime")
+            .block_on(async {
+                response.await?.into_body().try_collect::<Vec<_>>().await
+            })
+            .expect("Response body")
+            .iter()
+            .map(|chunk| chunk.to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn replay_keeps_chunks() {
+        let replayer = Replayer::new(events_cassette());
+        let args = RequestArgs {
+            path: "/events".to_owned(),
+            ..Default::default()
+        };
+
+        let chunks = chunks(replayer.request(args, Method::GET));
+
+        assert_eq!(EVENTS.len(), chunks.len());
+        assert_eq!(EVENTS[1].as_bytes(), &chunks[1][..]);
+        assert!(replayer.is_exhausted());
+    }
+
+    #[test]
+    fn replay_events_and_unmatched() {
+        let runtime = Runtime::new().expect("Runtime");
+        let docker = new_docker_with_interact(Arc::new(Replayer::new(events_cassette())));
+
+        let events = runtime.block_on(docker.events(&Default::default()).try_collect::<Vec<_>>())
+            .expect("Events");
+        assert_eq!(Some("die".to_owned()), events[1].status);
+
+        assert!(runtime.block_on(docker.events(&Default::default()).try_collect::<Vec<_>>())
+            .is_err());
+        assert!(runtime.block_on(docker.ping()).is_err());
+    }
+
+    /// Streams a text and a binary chunk for every request
+    async fn chunked(_opts: RequestArgs, _method: Method) -> Result<Response<Body>> {
+        let chunks = vec![Bytes::from("text"), Bytes::from(vec![1u8, 0xff])];
+        let body = Body::wrap_stream(stream::iter(chunks.into_iter().map(Ok::<_, ::std::io::Error>)));
+
+        Ok(Response::new(body))
+    }
+
+    #[test]
+    fn record_and_replay() {
+        let dir = TempDir::new("cassette");
+        let path = dir.path().join("cassette.json");
+        let recorder = Recorder::new(Arc::new(Stub(chunked)), &path);
+        let mut json = HeaderMap::new();
+        json.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        let args = RequestArgs {
+            path: "/containers/create".to_owned(),
+            query: "name=web".to_owned(),
+            body: Body::from("{}"),
+            header: json.clone(),
+            ..Default::default()
+        };
+
+        let recorded = chunks(recorder.request(args, Method::POST));
+
+        let cassette = Cassette::load(&path).expect("Saved cassette");
+        assert_eq!(recorder.cassette().interactions, cassette.interactions);
+        assert_eq!(Data::Base64("Af8=".to_owned()),
+                   cassette.interactions[0].response.chunks[1]);
+
+        let replayer = Replayer::new(cassette);
+        let args = RequestArgs {
+            path: "/containers/create".to_owned(),
+            query: "name=web".to_owned(),
+            body: Body::from("{}"),
+            header: json.clone(),
+            ..Default::default()
+        };
+
+        assert_eq!(recorded, chunks(replayer.request(args, Method::POST)));
+    }
+
+    /// Answers a pull, failing it unless credentials were sent
+    async fn registry(opts: RequestArgs, _method: Method) -> Result<Response<Body>> {
+        let status = match opts.header.get(X_REGISTRY_AUTH) {
+            Some(_) => r#"{"status":"Downloaded newer image for alpine:latest"}"#,
+            None => r#"{"error":"unauthorized"}"#,
+        };
+
+        Ok(Response::new(Body::from(status)))
+    }
+
+    #[test]
+    fn credentials_are_not_recorded() {
+        let dir = TempDir::new("cassette-credentials");
+        let path = dir.path().join("cassette.json");
+        let runtime = Runtime::new().expect("Runtime");
+        let auth = RegistryAuth::builder().username("jane").password("hunter2").build();
+        let opts = PullOptions::builder().image("alpine").auth(auth.clone()).build();
+
+        let recorder = new_docker_with_interact(Arc::new(Recorder::new(Arc::new(Stub(registry)), &path)));
+        runtime.block_on(recorder.images().pull(&opts).try_collect::<Vec<_>>())
+            .expect("Pulled with the real credentials");
+
+        let cassette = std::fs::read_to_string(&path).expect("Saved cassette");
+        assert!(cassette.contains("<redacted>"));
+        assert!(!cassette.contains(&auth.serialize()));
+        assert!(!cassette.contains("hunter2"));
+
+        let replayer = new_docker_with_interact(Arc::new(Replayer::load(&path).expect("Cassette")));
+        let other = PullOptions::builder().image("alpine").auth(RegistryAuth::token("t0k3n")).build();
+        assert!(runtime.block_on(replayer.images().pull(&other).try_collect::<Vec<_>>()).is_ok());
+    }
+
+    /// Answers an image load once the whole tarball has been received
+    async fn loader(opts: RequestArgs, _method: Method) -> Result<Response<Body>> {
+        let tarball = opts.body.bytes().await?;
+        let status = format!("{{\"stream\":\"Loaded {} bytes\"}}", tarball.len());
+
+        Ok(Response::new(Body::from(status)))
+    }
+
+    #[test]
+    fn streamed_bodies_are_recorded_as_digest() {
+        let dir = TempDir::new("cassette-digest");
+        let path = dir.path().join("cassette.json");
+        let runtime = Runtime::new().expect("Runtime");
+        let tarball = || Body::wrap_stream(stream::iter(vec![
+            Ok::<_, ::std::io::Error>(Bytes::from_static(b"first tar chunk ")),
+            Ok(Bytes::from_static(b"second tar chunk")),
+        ]));
+        let opts = ImageLoadOptions::builder().build();
+
+        let recorder = new_docker_with_interact(Arc::new(Recorder::new(Arc::new(Stub(loader)), &path)));
+        let events = runtime.block_on(recorder.images().load(tarball(), &opts).try_collect::<Vec<_>>())
+            .expect("Loaded through the recorder");
+        assert_eq!(events[0].stream.as_deref(), Some("Loaded 32 bytes"));
+
+        let cassette = std::fs::read_to_string(&path).expect("Saved cassette");
+        assert!(cassette.contains("\"digest\""));
+        assert!(!cassette.contains("tar chunk"));
+
+        let replayer = new_docker_with_interact(Arc::new(Replayer::load(&path).expect("Cassette")));
+        let other = Body::from("another tarball of 32 bytes.....");
+        assert!(runtime.block_on(replayer.images().load(other, &opts).try_collect::<Vec<_>>()).is_err());
+        assert!(runtime.block_on(replayer.images().load(tarball(), &opts).try_collect::<Vec<_>>()).is_ok());
+    }
+}
@@ -1,44 +1,47 @@
 use std::borrow::Cow;
-use futures::Future;
-use representation::rep::ContainerDetails;
-use Error;
-use Result;
-use futures::Stream;
-use build::LogsOptions;
-
-use util::build_simple_query;
-
-use transport::parse::parse_to_lines;
-use transport::parse::parse_to_trait;
-use transport::parse::parse_to_stream;
-use transport::parse::status_code;
-
-use representation::rep::Change;
-use representation::rep::Stats;
-use representation::rep::Top;
+use futures::{Stream, TryFutureExt};
+use crate::representation::rep::ContainerDetails;
+use crate::Error;
+use crate::Result;
+use crate::build::LogsOptions;
+use crate::build::StatsOptions;
+use crate::build::WaitOptions;
+
+use crate::util::build_simple_query;
+use crate::util::IntoRequestArgs;
+use crate::communicate::timeout::RequestTimeouts;
+
+use crate::transport::parse::parse_to_chunks;
+use crate::transport::parse::parse_to_lines;
+use crate::transport::parse::parse_to_trait;
+use crate::transport::parse::parse_to_stream;
+use crate::transport::parse::status_code;
+
+use crate::representation::rep::Change;
+use crate::representation::rep::Stats;
+use crate::representation::rep::Top;
 use http::StatusCode;
-use hyper::Body;
+use crate::transport::body::Body;
 use std::time::Duration;
-use representation::rep::Exit;
-use build::RmContainerOptions;
-use build::ExecContainerOptions;
+use crate::representation::rep::Exit;
+use crate::build::RmContainerOptions;
+use crate::build::ExecContainerOptions;
 use serde_json::Value;
-use errors::ErrorKind as EK;
-use transport::tty;
-use futures::future;
-use hyper::Chunk;
+use crate::errors::ErrorKind as EK;
+use crate::transport::tty;
+use bytes::Bytes;
 use std::sync::Arc;
-use build::ContainerArchivePutOptions;
-use tarball::tarball;
-use transport::interact::InteractApi;
-use transport::interact::InteractApiExt;
-use communicate::util::AsSlice;
+use crate::build::ContainerArchivePutOptions;
+use crate::tarball::body;
+use crate::tarball::tarball;
+use crate::transport::interact::InteractApi;
+use crate::transport::interact::InteractApiExt;
 
 
 /// Interface for accessing and manipulating a docker container
 pub struct Container
 {
-    interact: Arc<InteractApi>,
+    interact: Arc<dyn InteractApi>,
     id: Cow<'static, str>,
 }
 
@@ -52,10 +55,10 @@ impl Clone for Container
 impl Container
 {
     /// Exports an interface exposing operations against a container instance
-    pub(crate) fn new(interact: Arc<InteractApi>, id: Cow<'static, str>) -> Container {
+    pub(crate) fn new(interact: Arc<dyn InteractApi>, id: Cow<'static, str>) -> Container {
         Container {
             interact,
-            id: id.into(),
+            id,
         }
     }
 
@@ -65,219 +68,229 @@ impl Container
     }
 
     /// Inspects the current docker container instance's details
-    pub fn inspect(&self) -> impl Future<Item=ContainerDetails, Error=Error> + Send {
+    pub async fn inspect(&self) -> Result<ContainerDetails> {
         let path = format!("/containers/{}/json", self.id);
         let args = path.as_str();
 
-        parse_to_trait::<ContainerDetails>(self.interact.get(args))
+        parse_to_trait::<ContainerDetails, _>(self.interact.get(args)).await
     }
 
     /// Returns a `top` view of information about the container process
-    pub fn top(&self, psargs: Option<&str>) -> impl Future<Item=Top, Error=Error> + Send {
+    pub async fn top(&self, psargs: Option<&str>) -> Result<Top> {
         let path = format!("/containers/{}/top", self.id);
         let query = build_simple_query("ps_args", psargs);
-        let args = (path.as_ref(), query.as_slice());
+        let args = (path.as_ref(), query.as_deref());
 
-        parse_to_trait::<Top>(self.interact.get(args))
+        parse_to_trait::<Top, _>(self.interact.get(args)).await
     }
 
     /// Returns a stream of logs emitted but the container instance
-    pub fn logs(&self, opts: &LogsOptions) -> impl Stream<Item=String, Error=Error> + Send {
+    pub fn logs(&self, opts: &LogsOptions) -> impl Stream<Item=Result<String>> + Send {
         let path = format!("/containers/{}/logs", self.id);
         let query = opts.serialize();
-        let args = (path.as_str(), query.as_slice());
+        let timeouts = opts.timeouts();
+        let interact = self.interact.clone();
 
-        parse_to_lines(self.interact.get(args))
+        parse_to_lines(async move {
+            let mut args = (path.as_str(), query.as_deref()).into_request_args();
+            args.timeouts = timeouts;
+            interact.get(args).await
+        })
     }
 
     /// Returns a set of changes made to the container instance
-    pub fn changes(&self) -> impl Future<Item=Vec<Change>, Error=Error> + Send {
+    pub async fn changes(&self) -> Result<Vec<Change>> {
         let args = format!("/containers/{}/changes", self.id);
 
-        parse_to_trait::<Vec<Change>>(self.interact.get(args.as_str()))
+        parse_to_trait::<Vec<Change>, _>(self.interact.get(args.as_str())).await
     }
 
     /// Exports the current docker container into a tarball
-    pub fn export(&self) -> impl Stream<Item=Chunk, Error=Error> + Send {
+    pub fn export(&self) -> impl Stream<Item=Result<Bytes>> + Send {
         let path = format!("/containers/{}/export", self.id);
+        let interact = self.interact.clone();
 
-        self.interact.get(path.as_str())
-            .and_then(|a| a.map_err(Error::from))
-            .and_then(|a| Ok(a.into_body().map_err(Error::from)))
-            .flatten_stream()
+        parse_to_chunks(async move { interact.get(path.as_str()).await })
     }
 
     /// Returns a stream of stats specific to this container instance
-    pub fn stats(&self) -> impl Stream<Item=Result<Stats>, Error=Error> + Send {
-        let args = format!("/containers/{}/stats", self.id);
+    pub fn stats(&self, opts: &StatsOptions) -> impl Stream<Item=Result<Stats>> + Send {
+        let path = format!("/containers/{}/stats", self.id);
+        let timeouts = opts.timeouts();
+        let interact = self.interact.clone();
 
-        parse_to_stream::<Stats>(self.interact.get(args.as_str()))
+        parse_to_stream::<Stats, _>(async move {
+            let mut args = path.as_str().into_request_args();
+            args.timeouts = timeouts;
+            interact.get(args).await
+        })
     }
 
     /// Start the container instance
-    pub fn start(&self) ->  impl Future<Item=StatusCode, Error=Error> + Send {
+    pub async fn start(&self) -> Result<StatusCode> {
         let args = format!("/containers/{}/start", self.id);
 
-        status_code(self.interact.post(args.as_str()))
+        status_code(self.interact.post(args.as_str())).await
     }
 
     /// Stop the container instance
-    pub fn stop(&self, wait: Option<Duration>) -> impl Future<Item=StatusCode, Error=Error> + Send {
+    pub async fn stop(&self, wait: Option<Duration>) -> Result<StatusCode> {
         let path = format!("/containers/{}/stop", self.id);
         let query =
             build_simple_query("t", wait.map(|w| w.as_secs().to_string()));
-        let args = (path.as_str(), query.as_slice());
+        let args = (path.as_str(), query.as_deref());
 
-        status_code(self.interact.post(args))
+        status_code(self.interact.post(args)).await
     }
 
     /// Restart the container instance
-    pub fn restart(&self, wait: Option<Duration>) -> impl Future<Item=StatusCode, Error=Error> + Send {
+    pub async fn restart(&self, wait: Option<Duration>) -> Result<StatusCode> {
         let path = format!("/containers/{}/restart", self.id);
         let query =
             build_simple_query("t", wait.map(|w| w.as_secs().to_string()));
-        let args = (path.as_str(), query.as_slice());
+        let args = (path.as_str(), query.as_deref());
 
-        status_code(self.interact.post(args))
+        status_code(self.interact.post(args)).await
     }
 
     /// Kill the container instance
-    pub fn kill(&self, signal: Option<&str>) -> impl Future<Item=StatusCode, Error=Error> + Send {
+    pub async fn kill(&self, signal: Option<&str>) -> Result<StatusCode> {
         let path = format!("/containers/{}/kill", self.id);
-        let query = build_simple_query("signal", signal.map(|sig| sig));
-        let args = (path.as_str(), query.as_slice());
+        let query = build_simple_query("signal", signal);
+        let args = (path.as_str(), query.as_deref());
 
-        status_code(self.interact.post(args))
+        status_code(self.interact.post(args)).await
     }
 
     /// Rename the container instance
-    pub fn rename(&self, name: &str) -> impl Future<Item=StatusCode, Error=Error> + Send {
+    pub async fn rename(&self, name: &str) -> Result<StatusCode> {
         let path = format!("/containers/{}/rename", self.id);
         let query = build_simple_query("name", Some(name));
-        let args = (path.as_str(), query.as_slice());
+        let args = (path.as_str(), query.as_deref());
 
-        status_code(self.interact.post(args))
+        status_code(self.interact.post(args)).await
     }
 
     /// Pause the container instance
-    pub fn pause(&self) -> impl Future<Item=StatusCode, Error=Error> + Send {
+    pub async fn pause(&self) -> Result<StatusCode> {
         let args = format!("/containers/{}/pause", self.id);
 
-        status_code(self.interact.post(args.as_str()))
+        status_code(self.interact.post(args.as_str())).await
     }
 
     /// Unpause the container instance
-    pub fn unpause(&self) -> impl Future<Item=StatusCode, Error=Error> + Send {
+    pub async fn unpause(&self) -> Result<StatusCode> {
         let args = format!("/containers/{}/unpause", self.id);
 
-        status_code(self.interact.post(args.as_str()))
+        status_code(self.interact.post(args.as_str())).await
     }
 
     /// Wait until the container stops
-    pub fn wait(&self) -> impl Future<Item=Exit, Error=Error> + Send {
-        let args = format!("/containers/{}/wait", self.id);
-
-        parse_to_trait::<Exit>(self.interact.post(args.as_str()))
+    ///
+    /// The response body only arrives once the container stopped, so by
+    /// default it never idles out; an idle timeout in the options bounds how
+    /// long to wait for the container.
+    pub async fn wait(&self, opts: &WaitOptions) -> Result<Exit> {
+        let path = format!("/containers/{}/wait", self.id);
+        let mut args = path.as_str().into_request_args();
+        args.timeouts = opts.timeouts();
+
+        parse_to_trait::<Exit, _>(self.interact.post(args)).await
     }
 
     /// Delete the container instance
     ///
     /// Use remove instead to use the force/v options.
-    pub fn delete(&self) -> impl Future<Item=StatusCode, Error=Error> + Send {
+    pub async fn delete(&self) -> Result<StatusCode> {
         let args = format!("/containers/{}", self.id);
 
-        status_code(self.interact.delete(args.as_str()))
+        status_code(self.interact.delete(args.as_str())).await
     }
 
     /// Delete the container instance (todo: force/v)
-    pub fn remove(&self, opts: &RmContainerOptions) -> impl Future<Item=StatusCode, Error=Error> + Send {
+    pub async fn remove(&self, opts: &RmContainerOptions) -> Result<StatusCode> {
         let path = format!("/containers/{}", self.id);
         let query = opts.serialize();
-        let args = (path.as_str(), query.as_slice());
+        let args = (path.as_str(), query.as_deref());
 
-        status_code(self.interact.delete(args))
+        status_code(self.interact.delete(args)).await
     }
 
-    pub fn create_exec(&self, opts: &ExecContainerOptions)
-        -> impl Future<Item=String, Error=Error> + Send
-    {
+    pub async fn create_exec(&self, opts: &ExecContainerOptions) -> Result<String> {
+        self.create_exec_with(opts.serialize()).await
+    }
+
+    async fn create_exec_with(&self, body: Option<String>) -> Result<String> {
         let path = format!("/containers/{}/exec", self.id);
-        let body = opts.serialize().map(Body::from);
-        let args = (path.as_str(), body);
-
-        parse_to_trait::<Value>(self.interact.post_json(args))
-            .and_then(|val| {
-                debug!("{:?}", val);
-                match val {
-                    Value::Object(obj) => future::result(obj
-                        .get("Id")
-                        .ok_or(Error::from(EK::JsonFieldMissing("Id")))
-                        .and_then(|val| val.as_str()
-                            .ok_or(Error::from(EK::JsonTypeError("Id", "String"))))
-                        .map(|a| {
-                            debug!("String: {}", a.to_string());
-                            a.to_string()
-                        })),
-                    _ => future::err(Error::from(EK::JsonTypeError("<anonymous>", "Object")))
-                }
-            })
+        let args = (path.as_str(), body.map(Body::from));
+
+        let val = parse_to_trait::<Value, _>(self.interact.post_json(args)).await?;
+        debug!("{:?}", val);
+        match val {
+            Value::Object(obj) => obj
+                .get("Id")
+                .ok_or(Error::from(EK::JsonFieldMissing("Id")))
+                .and_then(|val| val.as_str()
+                    .ok_or(Error::from(EK::JsonTypeError("Id", "String"))))
+                .map(|a| {
+                    debug!("String: {}", a.to_string());
+                    a.to_string()
+                }),
+            _ => Err(Error::from(EK::JsonTypeError("<anonymous>", "Object")))
+        }
     }
 
-    pub fn start_exec(&self, id: String)
-        -> impl Stream<Item=(u32, Chunk), Error=Error>
+    pub fn start_exec(&self, id: String) -> impl Stream<Item=Result<(u32, Bytes)>> + Send
+    {
+        self.start_exec_with(id, RequestTimeouts::default().streaming())
+    }
+
+    fn start_exec_with(&self, id: String, timeouts: RequestTimeouts)
+        -> impl Stream<Item=Result<(u32, Bytes)>> + Send
     {
         let path = format!("/exec/{}/start", id);
-        let body = Some(Body::from("{}".to_string()));
-        let args = (path.as_str(), body);
-
-        let body_future = self.interact.post_json(args)
-            .and_then(|response| {
-                response.map_err(Error::from)
-            })
-            .and_then(|result| {
-                Ok(result.into_body().map_err(Error::from))
-            })
-            .flatten_stream();
-
-        tty::decode(body_future)
+        let interact = self.interact.clone();
+
+        tty::decode(parse_to_chunks(async move {
+            let body = Some(Body::from("{}".to_string()));
+            let mut args = (path.as_str(), body).into_request_args();
+            args.timeouts = timeouts;
+            interact.post_json(args).await
+        }))
     }
 
-    pub fn exec(&self, opts: &ExecContainerOptions)
-        -> impl Stream<Item=(u32, Chunk), Error=Error>
+    pub fn exec(&self, opts: &ExecContainerOptions) -> impl Stream<Item=Result<(u32, Bytes)>> + Send
     {
         let copy_self = self.clone();
-        self.create_exec(opts)
-            .and_then(move |id| Ok(copy_self.start_exec(id)))
-            .flatten_stream()
+        let body = opts.serialize();
+        let timeouts = opts.timeouts();
+
+        async move {
+            copy_self.create_exec_with(body).await
+                .map(|id| copy_self.start_exec_with(id, timeouts))
+        }.try_flatten_stream()
     }
 
-    pub fn archive_get(&self, pth: &str) -> impl Stream<Item=Chunk, Error=Error>
+    pub fn archive_get(&self, pth: &str) -> impl Stream<Item=Result<Bytes>> + Send
     {
         let path = format!("/containers/{}/archive", self.id);
         let query = build_simple_query("path", Some(pth));
-        let args = (path.as_str(), query.as_slice());
+        let interact = self.interact.clone();
 
-        self.interact.get(args)
-            .and_then(|a| a.map_err(Error::from))
-            .and_then(|a| Ok(a.into_body().map_err(Error::from)))
-            .flatten_stream()
+        parse_to_chunks(async move { interact.get((path.as_str(), query.as_deref())).await })
     }
 
-    pub fn archive_put(&self, opts: &ContainerArchivePutOptions)
-            -> impl Future<Item=StatusCode, Error=Error> + Send
+    pub async fn archive_put(&self, opts: &ContainerArchivePutOptions) -> Result<StatusCode>
     {
-        let mut bytes = vec![];
         let path = format!("/containers/{}/archive", self.id);
         let query = opts.serialize();
-        let interact = self.interact.clone();
+        let local_path = opts.local_path.clone();
 
-        future::result(tarball::dir(&mut bytes, &opts.local_path))
-            .and_then(move |_| {
-                let body = Some(Body::from(bytes));
-                let args = (path.as_str(), query.as_slice(), body);
-                status_code(interact.put(args))
-            })
+        let body = Some(body::streamed(move |w| tarball::dir(w, &local_path)));
+        let args = (path.as_str(), query.as_deref(), body);
+
+        status_code(self.interact.put(args)).await
     }
 
     // todo attach, attach/ws, copy
-}
\ No newline at end of file
+}
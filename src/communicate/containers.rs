@@ -1,49 +1,45 @@
-use transport::interact::InteractApi;
+use crate::transport::interact::InteractApi;
 use std::sync::Arc;
-use build::ContainerListOptions;
-use futures::Future;
-use rep::Container as ContainerRep;
-use transport::parse::parse_to_trait;
-use Error;
-use build::ContainerOptions;
-use representation::rep::ContainerCreateInfo;
-use communicate::util::build_simple_query;
-use hyper::Body;
-use transport::interact::InteractApiExt;
-use communicate::util::AsSlice;
+use crate::build::ContainerListOptions;
+use crate::rep::Container as ContainerRep;
+use crate::transport::parse::parse_to_trait;
+use crate::Result;
+use crate::build::ContainerOptions;
+use crate::representation::rep::ContainerCreateInfo;
+use crate::communicate::util::build_simple_query;
+use crate::transport::body::Body;
+use crate::transport::interact::InteractApiExt;
 
 /// Interface for docker containers
 pub struct Containers {
-    interact: Arc<InteractApi>,
+    interact: Arc<dyn InteractApi>,
 }
 
 impl Containers {
     /// Exports an interface for interacting with docker containers
-    pub(crate) fn new(interact: Arc<InteractApi>) -> Containers {
+    pub(crate) fn new(interact: Arc<dyn InteractApi>) -> Containers {
         Containers {
             interact
         }
     }
 
     /// Lists the container instances on the docker host
-    pub fn list(&self, opts: &ContainerListOptions)
-        -> impl Future<Item=Vec<ContainerRep>, Error=Error> {
+    pub async fn list(&self, opts: &ContainerListOptions) -> Result<Vec<ContainerRep>> {
         let path = "/containers/json";
         let query = opts.serialize();
-        let args = (path, query.as_slice());
+        let args = (path, query.as_deref());
 
-        parse_to_trait::<Vec<ContainerRep>>(self.interact.get(args))
+        parse_to_trait::<Vec<ContainerRep>, _>(self.interact.get(args)).await
     }
 
     /// Returns a builder interface for creating a new container instance
-    pub fn create(&self, opts: &ContainerOptions)
-                  -> impl Future<Item=ContainerCreateInfo, Error=Error> {
+    pub async fn create(&self, opts: &ContainerOptions) -> Result<ContainerCreateInfo> {
         let path = "/containers/create";
         let query = build_simple_query("name", opts.name.clone());
         let data = opts.serialize().expect("Error during serialization of ContainerOptions");
         let body = Some(Body::from(data));
-        let args = (path, query.as_slice(), body);
+        let args = (path, query.as_deref(), body);
 
-        parse_to_trait(self.interact.post_json(args))
+        parse_to_trait(self.interact.post_json(args)).await
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,225 @@
+//! Registry credentials stored by `docker login`
+//!
+//! Credentials are looked up like the docker CLI does: a registry specific
+//! `credHelpers` entry wins over the `credsStore` used for every registry,
+//! which wins over the `auths` of `config.json`. Helpers are the
+//! `docker-credential-<name>` executables found on the `PATH`.
+//!
+//! ```no_run
+//! # use async_docker::build::PullOptions;
+//! # use async_docker::credentials::CredentialStore;
+//! # fn main() -> async_docker::Result<()> {
+//! let store = CredentialStore::from_env()?;
+//! let mut options = PullOptions::builder();
+//! options.image("registry.example.com/team/app");
+//! if let Some(auth) = store.for_image("registry.example.com/team/app")? {
+//!     options.auth(auth);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::env;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use serde_json::from_slice as de_from_slice;
+
+use crate::build::RegistryAuth;
+use crate::communicate::builder::{config_dir, load_config, AuthEntry, CliConfig};
+use crate::errors::{ErrorKind, Result};
+
+/// Server address of Docker Hub, as `docker login` stores it
+pub const DOCKER_HUB: &str = "https://index.docker.io/v1/";
+
+/// Username a helper answers with if the secret is an identity token
+const TOKEN_USERNAME: &str = "<token>";
+
+/// Credentials of `~/.docker/config.json` and the helpers it names
+#[derive(Default)]
+pub struct CredentialStore {
+    config: CliConfig,
+}
+
+#[derive(Deserialize)]
+#[allow(non_snake_case)]
+struct HelperCredentials {
+    Username: String,
+    Secret: String,
+}
+
+impl CredentialStore {
+    /// Store of the config directory used by the docker CLI, `DOCKER_CONFIG`
+    /// or `~/.docker`. a missing config is an empty store
+    pub fn from_env() -> Result<CredentialStore> {
+        match config_dir(&|name| env::var(name).ok()) {
+            Some(dir) => CredentialStore::load(dir),
+            None => Ok(CredentialStore::default()),
+        }
+    }
+
+    /// Store of the `config.json` in the given directory
+    pub fn load<P>(config_dir: P) -> Result<CredentialStore>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(CredentialStore {
+            config: load_config(config_dir.as_ref())?,
+        })
+    }
+
+    /// Credentials of the registry, e.g. `registry.example.com:5000` or
+    /// `DOCKER_HUB`. Helpers are run synchronously
+    pub fn get(&self, registry: &str) -> Result<Option<RegistryAuth>> {
+        let key = normalize(registry);
+        let server = if key == normalize(DOCKER_HUB) { DOCKER_HUB } else { key };
+
+        let helper = self.config.credHelpers.iter()
+            .find(|&(registry, _)| normalize(registry) == key)
+            .map(|(_, helper)| helper)
+            .or(self.config.credsStore.as_ref());
+        if let Some(helper) = helper {
+            return run_helper(helper, server);
+        }
+
+        Ok(self.config.auths.iter()
+            .find(|&(registry, _)| normalize(registry) == key)
+            .and_then(|(_, entry)| from_entry(entry, server)))
+    }
+
+    /// Credentials of the registry the image is pulled from or pushed to
+    pub fn for_image(&self, image: &str) -> Result<Option<RegistryAuth>> {
+        self.get(registry_for_image(image))
+    }
+}
+
+/// Registry of an image reference, `DOCKER_HUB` unless the first path
+/// component is a host
+pub fn registry_for_image(image: &str) -> &str {
+    match image.find('/') {
+        Some(end) => {
+            let host = &image[..end];
+            if host.contains('.') || host.contains(':') || host == "localhost" {
+                host
+            } else {
+                DOCKER_HUB
+            }
+        }
+        None => DOCKER_HUB,
+    }
+}
+
+/// Host of a registry address, with the aliases of Docker Hub merged
+fn normalize(registry: &str) -> &str {
+    let host = registry.trim_start_matches("https://").trim_start_matches("http://");
+    let host = host.split('/').next().unwrap_or(host);
+
+    match host {
+        "docker.io" | "registry-1.docker.io" => "index.docker.io",
+        host => host,
+    }
+}
+
+fn from_entry(entry: &AuthEntry, server: &str) -> Option<RegistryAuth> {
+    let mut auth = RegistryAuth::builder();
+    auth.server_address(server);
+
+    if let Some(ref token) = entry.identitytoken {
+        auth.identity_token(token.as_str());
+    }
+    if let Some(ref email) = entry.email {
+        auth.email(email.as_str());
+    }
+
+    let decoded = entry.auth.as_ref()
+        .and_then(|auth| base64::decode(auth).ok())
+        .and_then(|auth| String::from_utf8(auth).ok());
+    match decoded.as_ref().and_then(|auth| auth.split_once(':')) {
+        Some((username, password)) => {
+            auth.username(username).password(password);
+        }
+        None => match (&entry.username, &entry.password) {
+            (Some(username), Some(password)) => {
+                auth.username(username.as_str()).password(password.as_str());
+            }
+            _ if entry.identitytoken.is_none() => return None,
+            _ => (),
+        },
+    }
+
+    Some(auth.build())
+}
+
+/// Asks `docker-credential-<helper>` for the credentials of `server`
+fn run_helper(helper: &str, server: &str) -> Result<Option<RegistryAuth>> {
+    let fail = |msg: String| ErrorKind::CredentialHelper(helper.to_string(), msg);
+
+    let mut child = Command::new(format!("docker-credential-{}", helper))
+        .arg("get")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| fail(err.to_string()))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(server.as_bytes()).map_err(|err| fail(err.to_string()))?;
+    }
+    let output = child.wait_with_output().map_err(|err| fail(err.to_string()))?;
+
+    if !output.status.success() {
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if stdout.contains("credentials not found") {
+            return Ok(None);
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(fail(format!("{} {}", stdout.trim(), stderr.trim()).trim().to_string()).into());
+    }
+
+    let credentials: HelperCredentials = de_from_slice(&output.stdout)
+        .map_err(|err| fail(err.to_string()))?;
+    let mut auth = RegistryAuth::builder();
+    auth.server_address(server);
+    if credentials.Username == TOKEN_USERNAME {
+        auth.identity_token(credentials.Secret);
+    } else {
+        auth.username(credentials.Username).password(credentials.Secret);
+    }
+
+    Ok(Some(auth.build()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{registry_for_image, CredentialStore, DOCKER_HUB};
+    use crate::build::RegistryAuth;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn auths_of_config_json() {
+        let dir = TempDir::new("credentials");
+        dir.write("config.json", r#"{"auths":{
+            "https://index.docker.io/v1/":{"auth":"amFuZTpodW50ZXIy"},
+            "registry.example.com:5000":{"identitytoken":"t0k3n"}}}"#);
+        let store = CredentialStore::load(dir.path()).expect("Store");
+
+        assert_eq!("registry.example.com:5000", registry_for_image("registry.example.com:5000/app"));
+        assert_eq!(DOCKER_HUB, registry_for_image("library/alpine"));
+
+        let hub = RegistryAuth::builder()
+            .username("jane")
+            .password("hunter2")
+            .server_address(DOCKER_HUB)
+            .build();
+        assert_eq!(Some(hub), store.for_image("alpine").expect("Hub"));
+        assert_eq!(Some(registry_token()), store.for_image("registry.example.com:5000/app").expect("Token"));
+        assert_eq!(None, store.get("quay.io").expect("Unknown"));
+    }
+
+    fn registry_token() -> RegistryAuth {
+        RegistryAuth::builder()
+            .identity_token("t0k3n")
+            .server_address("registry.example.com:5000")
+            .build()
+    }
+}
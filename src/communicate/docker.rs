@@ -1,58 +1,78 @@
-use hyper::client::connect::Connect;
-use futures::Future;
-use hyper::rt::Stream;
+use hyper_util::client::legacy::Client;
+use hyper_util::client::legacy::connect::Connect;
+use hyper_util::rt::TokioExecutor;
+use futures::stream::{BoxStream, StreamExt};
+use async_trait::async_trait;
 
-use errors::{Result, Error, ErrorKind};
+use crate::errors::{Result, Error, ErrorKind};
 
-use transport::status_code;
-use transport::parse_to_trait;
-use transport::parse_to_stream;
-use communicate::util::AsSlice;
+use crate::transport::status_code;
+use crate::transport::parse_to_trait;
+use crate::transport::parse_to_stream;
 
 use std::env;
 
-use build::{
+use crate::build::{
     EventsOptions,
 };
 
-use representation::rep::{
+use crate::representation::rep::{
     Info, Version, Event
 };
 
 use hyper::Uri;
 use hyper::StatusCode;
-use transport::interact::InteractApi;
-use transport::interact::InteractApiExt;
+use crate::transport::interact::{Interact, InteractApi};
+use crate::transport::interact::InteractApiExt;
 use std::sync::Arc;
 use std::borrow::Cow;
 use super::tcp_docker::TcpDocker;
+use super::ssh_docker::{SshConfig, SshDocker};
 #[cfg(target_os = "linux")]
 use super::unix_docker::UnixDocker;
 #[cfg(feature = "ssl")]
 use super::ssl_tcp_docker::TcpSSLDocker;
+#[cfg(all(feature = "rustls", not(feature = "ssl")))]
+use super::rustls_tcp_docker::TcpRustlsDocker;
 use std::marker::PhantomData;
-use communicate::Container;
-use communicate::image::Image;
-use communicate::Images;
-use communicate::containers::Containers;
-use communicate::networks::Networks;
-use communicate::Network;
+use crate::communicate::Container;
+use crate::communicate::image::Image;
+use crate::communicate::Images;
+use crate::communicate::containers::Containers;
+use crate::communicate::networks::Networks;
+use crate::communicate::Network;
+use crate::communicate::version::{ApiVersion, negotiate};
+use crate::communicate::builder::{Endpoint, parse_host};
+use crate::communicate::tls::TlsConfig;
+use crate::communicate::util::IntoRequestArgs;
+use crate::communicate::timeout::Timeouts;
 
 
 /// Entry point interface for communicating with docker daemon
-pub trait DockerApi
+#[async_trait]
+pub trait DockerApi: Send + Sync
 {
     /// Returns version information associated with the docker daemon
-    fn version(&self) -> Box<Future<Item=Version, Error=Error> + Send>;
+    async fn version(&self) -> Result<Version>;
 
     /// Returns information associated with the docker daemon
-    fn info(&self) -> Box<Future<Item=Info, Error=Error> + Send>;
+    async fn info(&self) -> Result<Info>;
 
     /// Returns a simple ping response indicating the docker daemon is accessible
-    fn ping(&self) -> Box<Future<Item=StatusCode, Error=Error> + Send>;
+    async fn ping(&self) -> Result<StatusCode>;
+
+    /// Returns the engine API version prefixed to request paths, if any
+    fn api_version(&self) -> Option<ApiVersion>;
+
+    /// Pins the engine API version, `None` sends unversioned requests
+    fn set_api_version(&self, version: Option<ApiVersion>);
+
+    /// Queries the daemon's supported API versions and pins the highest one
+    /// this client supports as well
+    async fn negotiate_version(&self) -> Result<ApiVersion>;
 
     /// Returns an iterator over streamed docker events
-    fn events(&self, opts: &EventsOptions) -> Box<Stream<Item=Result<Event>, Error=Error> + Send>;
+    fn events(&self, opts: &EventsOptions) -> BoxStream<'static, Result<Event>>;
 
     /// Exports an interface for interacting with docker container
     fn container(&self, id: Cow<'static, str>) -> Container;
@@ -71,19 +91,22 @@ pub trait DockerApi
 
     /// Exports an interface for interacting with networks
     fn networks(&self) -> Networks;
+
+    /// Returns the transport all requests of this client are sent through
+    fn interact(&self) -> Arc<dyn InteractApi>;
 }
 
 pub(crate) struct Docker<C>
-    where C: Connect + 'static
+    where C: Send + Sync + 'static
 {
-    interact: Arc<InteractApi>,
+    interact: Arc<dyn InteractApi>,
     phantom: PhantomData<C>,
 }
 
 impl <C> Docker<C>
-    where C: Connect + 'static
+    where C: Send + Sync + 'static
 {
-    pub(super) fn new_inner(interact: Arc<InteractApi>) -> Self
+    pub(super) fn new_inner(interact: Arc<dyn InteractApi>) -> Self
     {
         Self {
             interact,
@@ -92,32 +115,61 @@ impl <C> Docker<C>
     }
 }
 
+#[async_trait]
 impl <C> DockerApi for Docker<C>
-    where C: Connect + 'static
+    where C: Send + Sync + 'static
 {
-    fn version(&self) -> Box<Future<Item=Version, Error=Error> + Send> {
+    async fn version(&self) -> Result<Version> {
         let arg = "/version";
 
-        Box::new(parse_to_trait::<Version>(self.interact.get(arg)))
+        parse_to_trait::<Version, _>(self.interact.get(arg)).await
     }
 
-    fn info(&self) -> Box<Future<Item=Info, Error=Error> + Send> {
+    async fn info(&self) -> Result<Info> {
         let arg = "/info";
 
-        Box::new(parse_to_trait::<Info>(self.interact.get(arg)))
+        parse_to_trait::<Info, _>(self.interact.get(arg)).await
     }
 
-    fn ping(&self) -> Box<Future<Item=StatusCode, Error=Error> + Send> {
+    async fn ping(&self) -> Result<StatusCode> {
         let arg = "/_ping";
 
-        Box::new(status_code(self.interact.get(arg)))
+        status_code(self.interact.get(arg)).await
+    }
+
+    fn api_version(&self) -> Option<ApiVersion> {
+        self.interact.api_version()
+    }
+
+    fn set_api_version(&self, version: Option<ApiVersion>) {
+        self.interact.set_api_version(version)
     }
 
-    fn events(&self, opts: &EventsOptions) -> Box<Stream<Item=Result<Event>, Error=Error> + Send> {
+    async fn negotiate_version(&self) -> Result<ApiVersion> {
+        let version = self.version().await?;
+        let max = version.ApiVersion.parse::<ApiVersion>()?;
+        let min = match version.MinAPIVersion {
+            Some(ref min) => Some(min.parse::<ApiVersion>()?),
+            None => None,
+        };
+
+        let chosen = negotiate(max, min)?;
+        debug!("Negotiated API version {}", chosen);
+        self.interact.set_api_version(Some(chosen));
+
+        Ok(chosen)
+    }
+
+    fn events(&self, opts: &EventsOptions) -> BoxStream<'static, Result<Event>> {
         let query = opts.serialize();
-        let arg = ("/events",  query.as_slice());
+        let timeouts = opts.timeouts();
+        let interact = self.interact.clone();
 
-        Box::new(parse_to_stream::<Event>(self.interact.get(arg)))
+        parse_to_stream::<Event, _>(async move {
+            let mut args = ("/events", query.as_deref()).into_request_args();
+            args.timeouts = timeouts;
+            interact.get(args).await
+        }).boxed()
     }
 
     fn container(&self, id: Cow<'static, str>) -> Container
@@ -155,10 +207,15 @@ impl <C> DockerApi for Docker<C>
         let interact = self.interact.clone();
         Networks::new(interact)
     }
+
+    fn interact(&self) -> Arc<dyn InteractApi>
+    {
+        self.interact.clone()
+    }
 }
 
 fn default_uri(uri: Option<Uri>) -> Result<Uri> {
-    use communicate::util::{URI_ENV, DEFAULT_URI};
+    use crate::communicate::util::{URI_ENV, DEFAULT_URI};
     match uri {
         None => match env::var(URI_ENV) {
             Ok(var) => var.parse().map_err(Error::from),
@@ -169,19 +226,182 @@ fn default_uri(uri: Option<Uri>) -> Result<Uri> {
 }
 
 /// Creates the docker struct relevant to the provided Uri
-pub fn new_docker(host: Option<Uri>) -> Result<Box<DockerApi>>
+pub fn new_docker(host: Option<Uri>) -> Result<Box<dyn DockerApi>>
 {
     let host = default_uri(host)?;
-    let scheme = host.scheme_part().map(|a| a.as_str().to_string());
-    match scheme.as_slice() {
+    connect(Endpoint::from_env_tls(host, &|name| env::var(name).ok())?)
+}
+
+/// Creates a docker client talking https to the provided Uri, `tcp://`
+/// hosts are accepted as well
+pub fn new_docker_with_tls(host: Uri, tls: TlsConfig) -> Result<Box<dyn DockerApi>>
+{
+    let host = parse_host(&host.to_string(), true)?;
+    if host.scheme().map(|s| s.as_str()) != Some("https") {
+        return Err(ErrorKind::InvalidScheme.into());
+    }
+
+    connect(Endpoint { host, tls: Some(tls), ssh: None, timeouts: Timeouts::default() })
+}
+
+/// Creates a docker client tunneling to the `ssh://[user@]host[:port]` Uri
+/// through `docker system dial-stdio` on the remote host
+pub fn new_docker_with_ssh(host: Uri, ssh: SshConfig) -> Result<Box<dyn DockerApi>>
+{
+    if host.scheme().map(|s| s.as_str()) != Some("ssh") {
+        return Err(ErrorKind::InvalidScheme.into());
+    }
+
+    connect(Endpoint { host, tls: None, ssh: Some(ssh), timeouts: Timeouts::default() })
+}
+
+/// Creates a docker client speaking http through the given connector, the
+/// scheme and authority of `base_uri` are what the connector gets to see
+pub fn new_docker_with_connector<C>(connector: C, base_uri: Uri) -> Box<dyn DockerApi>
+    where C: Connect + Clone + Send + Sync + 'static
+{
+    new_docker_with_connector_and_timeouts(connector, base_uri, Timeouts::default())
+}
+
+/// Like `new_docker_with_connector`, with the given timeouts instead of the
+/// default ones. Connecting is bounded by the response timeout only, a
+/// connect timeout is up to the connector.
+pub fn new_docker_with_connector_and_timeouts<C>(connector: C, base_uri: Uri, timeouts: Timeouts)
+    -> Box<dyn DockerApi>
+    where C: Connect + Clone + Send + Sync + 'static
+{
+    let client = Client::builder(TokioExecutor::new()).build(connector);
+    let interact = Interact::new(client, base_uri, timeouts);
+
+    Box::new(Docker::<C>::new_inner(Arc::new(interact)))
+}
+
+/// Creates a docker client sending all requests through a custom transport
+pub fn new_docker_with_interact(interact: Arc<dyn InteractApi>) -> Box<dyn DockerApi>
+{
+    Box::new(Docker::<CustomInteract>::new_inner(interact))
+}
+
+/// Transport marker of clients built around a user supplied `InteractApi`
+pub(crate) enum CustomInteract {}
+
+/// Creates the docker struct relevant to the scheme of the endpoint's host
+pub(crate) fn connect(endpoint: Endpoint) -> Result<Box<dyn DockerApi>>
+{
+    let scheme = endpoint.host.scheme().map(|a| a.as_str().to_string());
+    match scheme.as_deref() {
         Some(scheme) => match scheme {
             #[cfg(target_os = "linux")]
-            "unix"  => UnixDocker::new(host),
+            "unix"  => UnixDocker::open(endpoint.host, endpoint.timeouts),
             #[cfg(feature = "ssl")]
-            "https" => TcpSSLDocker::new(host),
-            "http"  => TcpDocker::new(host),
+            "https" => TcpSSLDocker::open(endpoint.host, endpoint.tls
+                .unwrap_or_else(|| TlsConfig::builder().build()), endpoint.timeouts),
+            #[cfg(all(feature = "rustls", not(feature = "ssl")))]
+            "https" => TcpRustlsDocker::open(endpoint.host, endpoint.tls
+                .unwrap_or_else(|| TlsConfig::builder().build()), endpoint.timeouts),
+            "http"  => TcpDocker::open(endpoint.host, endpoint.timeouts),
+            "ssh"   => SshDocker::open(endpoint.host, endpoint.ssh.unwrap_or_default(),
+                                      endpoint.timeouts),
             _       => Err(ErrorKind::InvalidScheme.into()),
         }
         None => Err(ErrorKind::EmptyScheme.into())
     }
-}
\ No newline at end of file
+}
+
+/// Creates the docker struct relevant to the provided Uri and negotiates
+/// the engine API version with the daemon before handing it out
+pub async fn new_docker_negotiated(host: Option<Uri>) -> Result<Box<dyn DockerApi>>
+{
+    let docker = new_docker(host)?;
+    docker.negotiate_version().await?;
+
+    Ok(docker)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{new_docker_with_connector, new_docker_with_interact};
+    use crate::communicate::util::RequestArgs;
+    use crate::transport::body::Body;
+    use crate::test_util::Stub;
+    use crate::Result;
+    use futures::future;
+    use hyper::{Method, Response, StatusCode, Uri};
+    use hyper_util::client::legacy::connect::HttpConnector;
+    use tower_service::Service;
+    use tokio::runtime::Runtime;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll};
+    use std::thread;
+
+    /// Response with the given status
+    fn canned(status: StatusCode) -> future::Ready<Result<Response<Body>>> {
+        future::ok(Response::builder()
+            .status(status)
+            .body(Body::from("{\"message\":\"canned\"}"))
+            .expect("Response"))
+    }
+
+    #[test]
+    fn custom_interact() {
+        let runtime = Runtime::new().expect("Runtime");
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = requests.clone();
+        let docker = new_docker_with_interact(Arc::new(Stub(move |opts: RequestArgs, method: Method| {
+            seen.lock().unwrap().push(format!("{} {}", method, opts.path));
+            canned(StatusCode::OK)
+        })));
+
+        let status = runtime.block_on(docker.ping()).expect("Ping");
+
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(vec!["GET /_ping".to_owned()], *requests.lock().unwrap());
+
+        let docker = new_docker_with_interact(
+            Arc::new(Stub(|_: RequestArgs, _: Method| canned(StatusCode::NOT_FOUND))));
+        assert!(runtime.block_on(docker.ping()).is_err());
+    }
+
+    /// Plain http connector counting the connections it opens
+    #[derive(Clone)]
+    struct Counting(HttpConnector, Arc<AtomicUsize>);
+
+    impl Service<Uri> for Counting {
+        type Response = <HttpConnector as Service<Uri>>::Response;
+        type Error = <HttpConnector as Service<Uri>>::Error;
+        type Future = <HttpConnector as Service<Uri>>::Future;
+
+        fn poll_ready(&mut self, cx: &mut Context) -> Poll<::std::result::Result<(), Self::Error>> {
+            self.0.poll_ready(cx)
+        }
+
+        fn call(&mut self, dst: Uri) -> Self::Future {
+            self.1.fetch_add(1, Ordering::SeqCst);
+            self.0.call(dst)
+        }
+    }
+
+    #[test]
+    fn custom_connector() {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Bind");
+        let port = listener.local_addr().expect("Local address").port();
+        thread::spawn(move || {
+            let (mut tcp, _) = listener.accept().expect("Accept");
+            let _ = tcp.read(&mut [0u8; 1024]);
+            let _ = tcp.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nOK");
+        });
+
+        let connects = Arc::new(AtomicUsize::new(0));
+        let connector = Counting(HttpConnector::new(), connects.clone());
+        let host = format!("http://127.0.0.1:{}", port).parse().expect("Uri");
+        let docker = new_docker_with_connector(connector, host);
+
+        let status = Runtime::new().expect("Runtime").block_on(docker.ping());
+
+        assert_eq!(StatusCode::OK, status.expect("Ping"));
+        assert_eq!(1, connects.load(Ordering::SeqCst));
+    }
+}
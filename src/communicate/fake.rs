@@ -0,0 +1,1494 @@
+//! In-memory stand-in for a docker daemon
+//!
+//! `FakeDocker` implements `DockerApi` without any http: requests of the
+//! regular interfaces are answered from in-memory containers, images and
+//! networks, and every state change is published on `events()`. Responses
+//! and errors look like the ones of a real daemon, so code under test can
+//! not tell the difference for the supported operations:
+//!
+//! * ping, version, info and events
+//! * containers: list, create, inspect, start, stop, wait, logs and delete
+//! * exec: create, start and inspect, commands are run by `FakeDocker::on_exec`
+//! * images: list, tag and delete, images are added with `FakeDocker::add_image`
+//! * networks: list, create, inspect and delete
+//!
+//! Timestamps are logical: every event advances the clock by one second,
+//! starting at 1. Event filters are ignored.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io;
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::channel::{mpsc, oneshot};
+use futures::future::{self, BoxFuture};
+use futures::stream::{self, BoxStream};
+use futures::{FutureExt, Stream, StreamExt, TryFutureExt};
+use hyper::{Method, Response, StatusCode};
+use serde::Serialize;
+use serde_json::{self, json, Value};
+use tar::Archive;
+use url::form_urlencoded;
+
+use crate::build::EventsOptions;
+use crate::communicate::docker::{new_docker_with_interact, DockerApi};
+use crate::communicate::util::RequestArgs;
+use crate::communicate::version::{ApiVersion, MAX_API_VERSION, MIN_API_VERSION};
+use crate::communicate::{Container, Image, Images, Network};
+use crate::communicate::containers::Containers;
+use crate::communicate::networks::Networks;
+use crate::errors::{Error, ErrorKind, Result};
+use crate::representation::rep::{Config, Container as ContainerRep, ContainerCreateInfo,
+                          ContainerDetails, Event, EventActor, Exit, HostConfig, Image as ImageRep, Info,
+                          IPAM, NetworkCreateInfo, NetworkDetails, NetworkSettings, State,
+                          Version};
+use crate::transport::body::Body;
+use crate::transport::interact::InteractApi;
+
+const PREDEFINED_NETWORKS: [(&str, &str); 3] =
+    [("bridge", "bridge"), ("host", "host"), ("none", "null")];
+const ZERO_TIME: &str = "0001-01-01T00:00:00Z";
+
+/// `Type` of the emitted events
+const CONTAINER: &str = "container";
+const IMAGE: &str = "image";
+const NETWORK: &str = "network";
+
+/// Operations of the fake daemon that failures can be injected into
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Operation {
+    Ping,
+    Version,
+    Info,
+    Events,
+    ContainerList,
+    ContainerCreate,
+    ContainerInspect,
+    ContainerStart,
+    ContainerStop,
+    ContainerWait,
+    ContainerDelete,
+    ContainerLogs,
+    ExecCreate,
+    ExecStart,
+    ExecInspect,
+    ImageList,
+    ImageTag,
+    ImageLoad,
+    ImageDelete,
+    NetworkList,
+    NetworkCreate,
+    NetworkInspect,
+    NetworkDelete,
+}
+
+/// Output stream of a container process
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogStream {
+    Stdout = 1,
+    Stderr = 2,
+}
+
+/// Result of a command run through exec
+#[derive(Clone, Debug, Default)]
+pub struct ExecOutput {
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    pub exit_code: u64,
+}
+
+type ExecHandler = Arc<dyn Fn(&[String]) -> ExecOutput + Send + Sync>;
+
+/// Error response injected into an operation
+struct Failure {
+    status: StatusCode,
+    message: String,
+    remaining: Option<usize>,
+}
+
+struct FakeContainer {
+    id: String,
+    name: String,
+    image: String,
+    config: Value,
+    created: u64,
+    running: bool,
+    exit_code: u64,
+    waiters: Vec<oneshot::Sender<u64>>,
+    logs: Vec<(LogStream, Vec<u8>)>,
+    followers: Vec<(LogFilter, mpsc::UnboundedSender<Bytes>)>,
+}
+
+/// Streams requested by a logs call, and whether output is framed
+#[derive(Clone, Copy)]
+struct LogFilter {
+    stdout: bool,
+    stderr: bool,
+    tty: bool,
+}
+
+impl LogFilter {
+    fn frame(&self, stream: LogStream, data: &[u8]) -> Option<Bytes> {
+        let wanted = match stream {
+            LogStream::Stdout => self.stdout,
+            LogStream::Stderr => self.stderr,
+        };
+        if !wanted || data.is_empty() {
+            return None;
+        }
+
+        Some(Bytes::from(frame(stream, data, self.tty)))
+    }
+}
+
+struct FakeExec {
+    id: String,
+    container: String,
+    cmd: Vec<String>,
+    stdout: bool,
+    stderr: bool,
+    tty: bool,
+    exit_code: Option<u64>,
+}
+
+struct FakeImage {
+    id: String,
+    tags: Vec<String>,
+    created: u64,
+}
+
+struct FakeNetwork {
+    id: String,
+    name: String,
+    driver: String,
+    labels: HashMap<String, String>,
+    predefined: bool,
+}
+
+#[derive(Default)]
+struct FakeState {
+    clock: u64,
+    next_id: u64,
+    containers: Vec<FakeContainer>,
+    images: Vec<FakeImage>,
+    networks: Vec<FakeNetwork>,
+    execs: Vec<FakeExec>,
+    events: Vec<Event>,
+    subscribers: Vec<mpsc::UnboundedSender<Event>>,
+    failures: HashMap<Operation, Failure>,
+    exec_handler: Option<ExecHandler>,
+}
+
+/// In-memory docker daemon, see the module documentation
+#[derive(Clone)]
+pub struct FakeDocker {
+    interact: Arc<FakeInteract>,
+    docker: Arc<dyn DockerApi>,
+}
+
+impl Default for FakeDocker {
+    fn default() -> FakeDocker {
+        FakeDocker::new()
+    }
+}
+
+impl FakeDocker {
+    pub fn new() -> FakeDocker {
+        let mut state = FakeState::default();
+        for &(name, driver) in PREDEFINED_NETWORKS.iter() {
+            let id = state.new_id();
+            state.networks.push(FakeNetwork {
+                id,
+                name: name.to_owned(),
+                driver: driver.to_owned(),
+                labels: HashMap::new(),
+                predefined: true,
+            });
+        }
+
+        let interact = Arc::new(FakeInteract {
+            state: Arc::new(Mutex::new(state)),
+            version: RwLock::new(None),
+        });
+        let docker = new_docker_with_interact(interact.clone()).into();
+
+        FakeDocker {
+            interact,
+            docker,
+        }
+    }
+
+    /// Makes an image available under the given `repo:tag` and returns its id
+    pub fn add_image(&self, tag: &str) -> String {
+        let mut state = self.interact.lock();
+        let id = format!("sha256:{}", state.new_id());
+        let created = state.clock;
+
+        state.images.push(FakeImage {
+            id: id.clone(),
+            tags: vec![normalize_tag(tag)],
+            created,
+        });
+        state.emit(IMAGE, "pull", &normalize_tag(tag), None);
+
+        id
+    }
+
+    /// Lets the process of a running container exit on its own, waking up
+    /// everyone waiting for it
+    pub fn exit(&self, container: &str, code: u64) -> Result<()> {
+        let mut state = self.interact.lock();
+        let index = state.container(container)
+            .ok_or_else(|| Error::from(ErrorKind::Message(
+                format!("No such container: {}", container))))?;
+
+        state.exit(index, code);
+        Ok(())
+    }
+
+    /// Appends output of a container, sent to everyone following its logs
+    pub fn write_log(&self, container: &str, stream: LogStream, data: &[u8]) -> Result<()> {
+        let mut state = self.interact.lock();
+        let index = state.container(container)
+            .ok_or_else(|| Error::from(ErrorKind::Message(
+                format!("No such container: {}", container))))?;
+
+        let container = &mut state.containers[index];
+        container.followers.retain(|(filter, follower)| match filter.frame(stream, data) {
+            Some(chunk) => follower.unbounded_send(chunk).is_ok(),
+            None => true,
+        });
+        container.logs.push((stream, data.to_vec()));
+        Ok(())
+    }
+
+    /// Runs the commands of exec instances. Without a handler `echo` prints
+    /// its arguments, `true` and `false` exit accordingly and everything else
+    /// is not found. The handler is called with the fake locked and must not
+    /// use it
+    pub fn on_exec<F>(&self, handler: F)
+        where F: Fn(&[String]) -> ExecOutput + Send + Sync + 'static
+    {
+        self.interact.lock().exec_handler = Some(Arc::new(handler));
+    }
+
+    /// Answers every following call of the operation with an error response
+    pub fn fail(&self, operation: Operation, status: StatusCode, message: &str) {
+        self.inject(operation, status, message, None);
+    }
+
+    /// Answers only the next call of the operation with an error response
+    pub fn fail_once(&self, operation: Operation, status: StatusCode, message: &str) {
+        self.inject(operation, status, message, Some(1));
+    }
+
+    /// Removes all injected failures
+    pub fn clear_failures(&self) {
+        self.interact.lock().failures.clear();
+    }
+
+    /// Every event emitted so far
+    pub fn emitted_events(&self) -> Vec<Event> {
+        self.interact.lock().events.clone()
+    }
+
+    fn inject(&self, operation: Operation, status: StatusCode, message: &str,
+              remaining: Option<usize>) {
+        self.interact.lock().failures.insert(operation, Failure {
+            status,
+            message: message.to_owned(),
+            remaining,
+        });
+    }
+}
+
+#[async_trait]
+impl DockerApi for FakeDocker {
+    async fn version(&self) -> Result<Version> {
+        self.docker.version().await
+    }
+
+    async fn info(&self) -> Result<Info> {
+        self.docker.info().await
+    }
+
+    async fn ping(&self) -> Result<StatusCode> {
+        self.docker.ping().await
+    }
+
+    fn api_version(&self) -> Option<ApiVersion> {
+        self.docker.api_version()
+    }
+
+    fn set_api_version(&self, version: Option<ApiVersion>) {
+        self.docker.set_api_version(version)
+    }
+
+    async fn negotiate_version(&self) -> Result<ApiVersion> {
+        self.docker.negotiate_version().await
+    }
+
+    fn events(&self, opts: &EventsOptions) -> BoxStream<'static, Result<Event>> {
+        self.docker.events(opts)
+    }
+
+    fn container(&self, id: Cow<'static, str>) -> Container {
+        self.docker.container(id)
+    }
+
+    fn containers(&self) -> Containers {
+        self.docker.containers()
+    }
+
+    fn image<'a>(&self, id: Cow<'a, str>) -> Image<'a> {
+        self.docker.image(id)
+    }
+
+    fn images(&self) -> Images {
+        self.docker.images()
+    }
+
+    fn network<'a>(&self, id: Cow<'a, str>) -> Network<'a> {
+        self.docker.network(id)
+    }
+
+    fn networks(&self) -> Networks {
+        self.docker.networks()
+    }
+
+    fn interact(&self) -> Arc<dyn InteractApi> {
+        self.interact.clone()
+    }
+}
+
+/// `InteractApi` answering requests from the in-memory state
+struct FakeInteract {
+    state: Arc<Mutex<FakeState>>,
+    version: RwLock<Option<ApiVersion>>,
+}
+
+impl FakeInteract {
+    fn lock(&self) -> MutexGuard<'_, FakeState> {
+        self.state.lock().expect("Fake docker state poisoned")
+    }
+}
+
+#[async_trait]
+impl InteractApi for FakeInteract {
+    async fn request(&self, opts: RequestArgs, method: Method) -> Result<Response<Body>> {
+        let query = form_urlencoded::parse(opts.query.as_bytes()).into_iter().collect();
+        let body = opts.body.bytes().await?;
+
+        let reply = self.lock().handle(&method, &opts.path, &query, body);
+        reply.await
+    }
+
+    fn api_version(&self) -> Option<ApiVersion> {
+        *self.version.read().expect("API version lock poisoned")
+    }
+
+    fn set_api_version(&self, version: Option<ApiVersion>) {
+        *self.version.write().expect("API version lock poisoned") = version;
+    }
+}
+
+type Query = HashMap<String, String>;
+type Reply = BoxFuture<'static, Result<Response<Body>>>;
+
+fn operation(method: &Method, segments: &[&str]) -> Option<Operation> {
+    let operation = match (method.as_str(), segments) {
+        ("GET", ["_ping"]) => Operation::Ping,
+        ("GET", ["version"]) => Operation::Version,
+        ("GET", ["info"]) => Operation::Info,
+        ("GET", ["events"]) => Operation::Events,
+        ("GET", ["containers", "json"]) => Operation::ContainerList,
+        ("POST", ["containers", "create"]) => Operation::ContainerCreate,
+        ("GET", ["containers", _, "json"]) => Operation::ContainerInspect,
+        ("POST", ["containers", _, "start"]) => Operation::ContainerStart,
+        ("POST", ["containers", _, "stop"]) => Operation::ContainerStop,
+        ("POST", ["containers", _, "wait"]) => Operation::ContainerWait,
+        ("DELETE", ["containers", _]) => Operation::ContainerDelete,
+        ("GET", ["containers", _, "logs"]) => Operation::ContainerLogs,
+        ("POST", ["containers", _, "exec"]) => Operation::ExecCreate,
+        ("POST", ["exec", _, "start"]) => Operation::ExecStart,
+        ("GET", ["exec", _, "json"]) => Operation::ExecInspect,
+        ("GET", ["images", "json"]) => Operation::ImageList,
+        ("POST", ["images", "load"]) => Operation::ImageLoad,
+        ("POST", ["images", _, .., "tag"]) => Operation::ImageTag,
+        ("DELETE", ["images", _, ..]) => Operation::ImageDelete,
+        ("GET", ["networks"]) => Operation::NetworkList,
+        ("POST", ["networks", "create"]) => Operation::NetworkCreate,
+        ("GET", ["networks", _]) => Operation::NetworkInspect,
+        ("DELETE", ["networks", _]) => Operation::NetworkDelete,
+        _ => return None,
+    };
+
+    Some(operation)
+}
+
+fn respond<T>(status: StatusCode, value: &T) -> Reply
+    where T: Serialize
+{
+    let response = serde_json::to_vec(value)
+        .map_err(Error::from)
+        .and_then(|json| Response::builder()
+            .status(status)
+            .header("content-type", "application/json")
+            .body(Body::from(json))
+            .map_err(Error::from));
+
+    future::ready(response).boxed()
+}
+
+#[derive(Serialize)]
+struct ErrorMessage {
+    message: String,
+}
+
+fn error(status: StatusCode, message: String) -> Reply {
+    respond(status, &ErrorMessage { message })
+}
+
+fn empty(status: StatusCode) -> Reply {
+    future::ready(Response::builder()
+        .status(status)
+        .body(Body::empty())
+        .map_err(Error::from)).boxed()
+}
+
+/// `RepoTags` listed in the `manifest.json` of an image tarball
+fn manifest_tags(tarball: &[u8]) -> Option<Vec<String>> {
+    let mut archive = Archive::new(tarball);
+
+    for file in archive.files_mut().ok()? {
+        let mut file = file.ok()?;
+        if file.header().path().ok()?.to_str() != Some("manifest.json") {
+            continue;
+        }
+        let manifest: Vec<Value> = serde_json::from_reader(&mut file).ok()?;
+        return Some(manifest.iter()
+            .flat_map(|image| strings(&image["RepoTags"]).unwrap_or_default())
+            .collect());
+    }
+
+    None
+}
+
+/// Raw bytes for tty output, stdcopy framing with an 8 byte header otherwise
+fn frame(stream: LogStream, data: &[u8], tty: bool) -> Vec<u8> {
+    if tty {
+        return data.to_vec();
+    }
+
+    let size = data.len() as u32;
+    let mut framed = vec![stream as u8, 0, 0, 0,
+                          (size >> 24) as u8, (size >> 16) as u8, (size >> 8) as u8, size as u8];
+    framed.extend_from_slice(data);
+    framed
+}
+
+fn raw_stream<S>(chunks: S) -> Reply
+    where S: Stream<Item=Bytes> + Send + 'static
+{
+    future::ready(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/vnd.docker.raw-stream")
+        .body(Body::wrap_stream(chunks.map(Ok::<_, io::Error>)))
+        .map_err(Error::from)).boxed()
+}
+
+fn default_exec(cmd: &[String]) -> ExecOutput {
+    match cmd.first().map(String::as_str) {
+        Some("echo") => ExecOutput {
+            stdout: format!("{}\n", cmd[1..].join(" ")).into_bytes(),
+            ..ExecOutput::default()
+        },
+        Some("true") => ExecOutput::default(),
+        Some("false") => ExecOutput {
+            exit_code: 1,
+            ..ExecOutput::default()
+        },
+        command => ExecOutput {
+            stderr: format!("exec: \"{}\": executable file not found in $PATH\n",
+                            command.unwrap_or_default()).into_bytes(),
+            exit_code: 127,
+            ..ExecOutput::default()
+        },
+    }
+}
+
+#[derive(Serialize)]
+struct ExecCreated {
+    #[serde(rename = "Id")]
+    id: String,
+}
+
+#[derive(Serialize)]
+struct ExecDetails {
+    #[serde(rename = "ID")]
+    id: String,
+    #[serde(rename = "ContainerID")]
+    container: String,
+    #[serde(rename = "Running")]
+    running: bool,
+    #[serde(rename = "ExitCode")]
+    exit_code: Option<u64>,
+    #[serde(rename = "ProcessConfig")]
+    process: ProcessConfig,
+}
+
+#[derive(Serialize)]
+struct ProcessConfig {
+    entrypoint: String,
+    arguments: Vec<String>,
+    tty: bool,
+}
+
+fn is_true(query: &Query, name: &str) -> bool {
+    query.get(name).map(|v| v == "1" || v == "true").unwrap_or(false)
+}
+
+fn normalize_tag(tag: &str) -> String {
+    if tag.rsplit('/').next().map(|last| last.contains(':')).unwrap_or(false) {
+        tag.to_owned()
+    } else {
+        format!("{}:latest", tag)
+    }
+}
+
+fn strings(value: &Value) -> Option<Vec<String>> {
+    value.as_array().map(|values| values.iter()
+        .filter_map(|v| v.as_str().map(str::to_owned))
+        .collect())
+}
+
+fn labels(value: &Value) -> HashMap<String, String> {
+    value.as_object()
+        .map(|labels| labels.iter()
+            .filter_map(|(k, v)| v.as_str().map(|v| (k.clone(), v.to_owned())))
+            .collect())
+        .unwrap_or_default()
+}
+
+impl FakeState {
+    fn new_id(&mut self) -> String {
+        self.next_id += 1;
+        format!("{:064x}", self.next_id)
+    }
+
+    /// Records an event of a container or image. `from` is the image of a
+    /// container and the tag of an image
+    fn emit(&mut self, kind: &str, action: &str, id: &str, from: Option<&str>) {
+        let attribute = if kind == CONTAINER { "image" } else { "name" };
+        let attributes = from.map(|from| (attribute.to_owned(), from.to_owned()));
+
+        self.record(Event {
+            Type: Some(kind.to_owned()),
+            Action: Some(action.to_owned()),
+            Actor: Some(EventActor {
+                ID: id.to_owned(),
+                Attributes: attributes.into_iter().collect(),
+            }),
+            status: Some(action.to_owned()),
+            id: Some(id.to_owned()),
+            from: from.map(str::to_owned),
+            time: 0,
+            timeNano: 0,
+        });
+    }
+
+    /// Records an event of a network, which like on a daemon only carries
+    /// the typed fields
+    fn emit_network(&mut self, action: &str, network: &FakeNetwork) {
+        let attributes = vec![
+            ("name".to_owned(), network.name.clone()),
+            ("type".to_owned(), network.driver.clone()),
+        ];
+
+        self.record(Event {
+            Type: Some(NETWORK.to_owned()),
+            Action: Some(action.to_owned()),
+            Actor: Some(EventActor {
+                ID: network.id.clone(),
+                Attributes: attributes.into_iter().collect(),
+            }),
+            status: None,
+            id: None,
+            from: None,
+            time: 0,
+            timeNano: 0,
+        });
+    }
+
+    fn record(&mut self, mut event: Event) {
+        self.clock += 1;
+        event.time = self.clock;
+        event.timeNano = self.clock * 1_000_000_000;
+
+        self.subscribers.retain(|s| s.unbounded_send(event.clone()).is_ok());
+        self.events.push(event);
+    }
+
+    fn container(&self, id: &str) -> Option<usize> {
+        let name = id.trim_start_matches('/');
+
+        self.containers.iter().position(|c| c.id == id || c.name == name)
+            .or_else(|| {
+                let matches: Vec<usize> = self.containers.iter()
+                    .enumerate()
+                    .filter(|&(_, c)| !id.is_empty() && c.id.starts_with(id))
+                    .map(|(i, _)| i)
+                    .collect();
+                if matches.len() == 1 { Some(matches[0]) } else { None }
+            })
+    }
+
+    fn network(&self, id: &str) -> Option<usize> {
+        self.networks.iter().position(|n| n.id == id || n.name == id)
+            .or_else(|| self.networks.iter().position(|n| !id.is_empty() && n.id.starts_with(id)))
+    }
+
+    fn image(&self, reference: &str) -> Option<&FakeImage> {
+        self.image_index(reference).map(|index| &self.images[index])
+    }
+
+    fn image_index(&self, reference: &str) -> Option<usize> {
+        let tag = normalize_tag(reference);
+
+        self.images.iter().position(|i| i.id == reference || i.tags.contains(&tag))
+    }
+
+    fn exit(&mut self, index: usize, code: u64) {
+        if !self.containers[index].running {
+            return;
+        }
+
+        let (id, image) = {
+            let container = &mut self.containers[index];
+            container.running = false;
+            container.exit_code = code;
+            for waiter in container.waiters.drain(..) {
+                let _ = waiter.send(code);
+            }
+            container.followers.clear();
+            (container.id.clone(), container.image.clone())
+        };
+
+        self.emit(CONTAINER, "die", &id, Some(&image));
+    }
+
+    /// Consumes an injected failure of the operation, if any
+    fn failure(&mut self, operation: Operation) -> Option<(StatusCode, String)> {
+        let (status, message, exhausted) = match self.failures.get_mut(&operation) {
+            Some(failure) => {
+                if let Some(ref mut remaining) = failure.remaining {
+                    *remaining = remaining.saturating_sub(1);
+                }
+                (failure.status, failure.message.clone(), failure.remaining == Some(0))
+            }
+            None => return None,
+        };
+
+        if exhausted {
+            self.failures.remove(&operation);
+        }
+        Some((status, message))
+    }
+
+    fn handle(&mut self, method: &Method, path: &str, query: &Query, raw: Bytes) -> Reply {
+        let body = serde_json::from_slice(&raw).unwrap_or(Value::Null);
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+        let operation = match operation(method, &segments) {
+            Some(operation) => operation,
+            None => return error(StatusCode::NOT_FOUND, "page not found".to_owned()),
+        };
+
+        if let Some((status, message)) = self.failure(operation) {
+            return error(status, message);
+        }
+
+        let id = if segments.len() > 1 { segments[1] } else { "" };
+        match operation {
+            Operation::Ping => future::ok(Response::new(Body::from("OK"))).boxed(),
+            Operation::Version => self.version(),
+            Operation::Info => self.info(),
+            Operation::Events => self.events(query),
+            Operation::ContainerList => self.list_containers(query),
+            Operation::ContainerCreate => self.create_container(query, body),
+            Operation::ContainerInspect => self.inspect_container(id),
+            Operation::ContainerStart => self.start_container(id),
+            Operation::ContainerStop => self.stop_container(id),
+            Operation::ContainerWait => self.wait_container(id),
+            Operation::ContainerDelete => self.delete_container(id, query),
+            Operation::ContainerLogs => self.container_logs(id, query),
+            Operation::ExecCreate => self.create_exec(id, body),
+            Operation::ExecStart => self.start_exec(id),
+            Operation::ExecInspect => self.inspect_exec(id),
+            Operation::ImageList => self.list_images(),
+            // image names may contain slashes
+            Operation::ImageTag => self.tag_image(&segments[1..segments.len() - 1].join("/"), query),
+            Operation::ImageLoad => self.load_images(&raw),
+            Operation::ImageDelete => self.delete_image(&segments[1..].join("/"), query),
+            Operation::NetworkList => self.list_networks(),
+            Operation::NetworkCreate => self.create_network(body),
+            Operation::NetworkInspect => self.inspect_network(id),
+            Operation::NetworkDelete => self.delete_network(id),
+        }
+    }
+
+    fn version(&self) -> Reply {
+        respond(StatusCode::OK, &Version {
+            ApiVersion: MAX_API_VERSION.to_string(),
+            MinAPIVersion: Some(MIN_API_VERSION.to_string()),
+            Version: "fake".to_owned(),
+            GitCommit: "fake".to_owned(),
+            GoVersion: "fake".to_owned(),
+        })
+    }
+
+    fn info(&self) -> Reply {
+        respond(StatusCode::OK, &Info {
+            Containers: self.containers.len() as u64,
+            Images: self.images.len() as u64,
+            Driver: "fake".to_owned(),
+            DockerRootDir: "/var/lib/docker".to_owned(),
+            DriverStatus: Vec::new(),
+            ID: "FAKE".to_owned(),
+            KernelVersion: "fake".to_owned(),
+            MemTotal: 0,
+            MemoryLimit: false,
+            NCPU: 1,
+            NEventsListener: self.subscribers.len() as u64,
+            NGoroutines: 0,
+            Name: "fake".to_owned(),
+            OperatingSystem: "fake".to_owned(),
+            SwapLimit: false,
+            SystemTime: None,
+        })
+    }
+
+    /// Past events requested by `since`, followed by live ones unless the
+    /// stream is bounded by `until`
+    fn events(&mut self, query: &Query) -> Reply {
+        let since = query.get("since").and_then(|s| s.parse::<u64>().ok());
+        let until = query.get("until").and_then(|s| s.parse::<u64>().ok());
+
+        let past: Vec<Event> = match since {
+            Some(since) => self.events.iter()
+                .filter(|e| e.time >= since && until.map(|u| e.time <= u).unwrap_or(true))
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let events = match until {
+            Some(_) => stream::iter(past).boxed(),
+            None => {
+                let (sender, receiver) = mpsc::unbounded();
+                self.subscribers.push(sender);
+                stream::iter(past).chain(receiver).boxed()
+            }
+        };
+
+        let body = events.map(|event| {
+            let mut line = serde_json::to_vec(&event).expect("Event serialization");
+            line.push(b'\n');
+            Ok::<_, io::Error>(Bytes::from(line))
+        });
+
+        future::ok(Response::new(Body::wrap_stream(body))).boxed()
+    }
+
+    fn list_containers(&self, query: &Query) -> Reply {
+        let all = is_true(query, "all");
+
+        let containers: Vec<ContainerRep> = self.containers.iter()
+            .rev()
+            .filter(|c| all || c.running)
+            .map(|c| ContainerRep {
+                Created: c.created,
+                Command: strings(&c.config["Cmd"]).unwrap_or_default().join(" "),
+                Id: c.id.clone(),
+                Image: c.image.clone(),
+                Labels: labels(&c.config["Labels"]),
+                Names: vec![format!("/{}", c.name)],
+                Ports: Vec::new(),
+                Status: if c.running {
+                    "Up".to_owned()
+                } else {
+                    format!("Exited ({})", c.exit_code)
+                },
+                SizeRw: None,
+                SizeRootFs: None,
+            })
+            .collect();
+
+        respond(StatusCode::OK, &containers)
+    }
+
+    fn create_container(&mut self, query: &Query, config: Value) -> Reply {
+        let image = match config["Image"].as_str() {
+            Some(image) => image.to_owned(),
+            None => return error(StatusCode::BAD_REQUEST, "No image specified".to_owned()),
+        };
+        if self.image(&image).is_none() {
+            return error(StatusCode::NOT_FOUND, format!("No such image: {}", image));
+        }
+
+        let id = self.new_id();
+        let name = match query.get("name") {
+            Some(name) => name.trim_start_matches('/').to_owned(),
+            None => format!("fake_{}", self.next_id),
+        };
+        if self.containers.iter().any(|c| c.name == name) {
+            return error(StatusCode::CONFLICT, format!(
+                "Conflict. The container name \"/{}\" is already in use", name));
+        }
+
+        self.containers.push(FakeContainer {
+            id: id.clone(),
+            name,
+            image: image.clone(),
+            config,
+            created: self.clock,
+            running: false,
+            exit_code: 0,
+            waiters: Vec::new(),
+            logs: Vec::new(),
+            followers: Vec::new(),
+        });
+        self.emit(CONTAINER, "create", &id, Some(&image));
+
+        respond(StatusCode::CREATED, &ContainerCreateInfo {
+            Id: id,
+            Warnings: None,
+        })
+    }
+
+    fn inspect_container(&self, id: &str) -> Reply {
+        let c = match self.container(id) {
+            Some(index) => &self.containers[index],
+            None => return error(StatusCode::NOT_FOUND, format!("No such container: {}", id)),
+        };
+
+        let config = &c.config;
+        respond(StatusCode::OK, &ContainerDetails {
+            AppArmorProfile: String::new(),
+            Args: Vec::new(),
+            Config: Config {
+                AttachStderr: false,
+                AttachStdin: false,
+                AttachStdout: false,
+                Cmd: strings(&config["Cmd"]),
+                Domainname: String::new(),
+                Entrypoint: strings(&config["Entrypoint"]),
+                Env: strings(&config["Env"]),
+                Hostname: c.id[..12].to_owned(),
+                Image: c.image.clone(),
+                Labels: labels(&config["Labels"]),
+                OnBuild: None,
+                OpenStdin: false,
+                StdinOnce: false,
+                Tty: config["Tty"].as_bool().unwrap_or(false),
+                User: config["User"].as_str().unwrap_or_default().to_owned(),
+                WorkingDir: config["WorkingDir"].as_str().unwrap_or_default().to_owned(),
+            },
+            Created: ZERO_TIME.to_owned(),
+            Driver: "fake".to_owned(),
+            HostConfig: HostConfig {
+                CgroupParent: None,
+                ContainerIDFile: String::new(),
+                CpuShares: None,
+                CpusetCpus: None,
+                Memory: None,
+                MemorySwap: None,
+                NetworkMode: "default".to_owned(),
+                PidMode: None,
+                Privileged: false,
+                PublishAllPorts: false,
+                ReadonlyRootfs: None,
+            },
+            HostnamePath: String::new(),
+            HostsPath: String::new(),
+            LogPath: String::new(),
+            Id: c.id.clone(),
+            Image: c.image.clone(),
+            MountLabel: String::new(),
+            NetworkSettings: NetworkSettings {
+                Bridge: String::new(),
+                Gateway: String::new(),
+                IPAddress: String::new(),
+                IPPrefixLen: 0,
+                MacAddress: String::new(),
+            },
+            Path: String::new(),
+            ProcessLabel: String::new(),
+            ResolvConfPath: String::new(),
+            RestartCount: 0,
+            State: State {
+                Error: String::new(),
+                ExitCode: c.exit_code,
+                FinishedAt: ZERO_TIME.to_owned(),
+                OOMKilled: false,
+                Paused: false,
+                Pid: if c.running { 1 } else { 0 },
+                Restarting: false,
+                Running: c.running,
+                StartedAt: ZERO_TIME.to_owned(),
+            },
+            Mounts: Vec::new(),
+        })
+    }
+
+    fn start_container(&mut self, id: &str) -> Reply {
+        let index = match self.container(id) {
+            Some(index) => index,
+            None => return error(StatusCode::NOT_FOUND, format!("No such container: {}", id)),
+        };
+        if self.containers[index].running {
+            return empty(StatusCode::NOT_MODIFIED);
+        }
+
+        self.containers[index].running = true;
+        let (id, image) = (self.containers[index].id.clone(), self.containers[index].image.clone());
+        self.emit(CONTAINER, "start", &id, Some(&image));
+
+        empty(StatusCode::NO_CONTENT)
+    }
+
+    fn stop_container(&mut self, id: &str) -> Reply {
+        let index = match self.container(id) {
+            Some(index) => index,
+            None => return error(StatusCode::NOT_FOUND, format!("No such container: {}", id)),
+        };
+        if !self.containers[index].running {
+            return empty(StatusCode::NOT_MODIFIED);
+        }
+
+        self.exit(index, 0);
+        let (id, image) = (self.containers[index].id.clone(), self.containers[index].image.clone());
+        self.emit(CONTAINER, "stop", &id, Some(&image));
+
+        empty(StatusCode::NO_CONTENT)
+    }
+
+    /// Resolves once the container is not running anymore
+    fn wait_container(&mut self, id: &str) -> Reply {
+        let container = match self.container(id) {
+            Some(index) => &mut self.containers[index],
+            None => return error(StatusCode::NOT_FOUND, format!("No such container: {}", id)),
+        };
+        if !container.running {
+            return respond(StatusCode::OK, &Exit { StatusCode: container.exit_code });
+        }
+
+        let (sender, receiver) = oneshot::channel();
+        container.waiters.push(sender);
+
+        receiver
+            .map_err(|_| Error::from(ErrorKind::Eof))
+            .and_then(|code| respond(StatusCode::OK, &Exit { StatusCode: code }))
+            .boxed()
+    }
+
+    fn delete_container(&mut self, id: &str, query: &Query) -> Reply {
+        let index = match self.container(id) {
+            Some(index) => index,
+            None => return error(StatusCode::NOT_FOUND, format!("No such container: {}", id)),
+        };
+        if self.containers[index].running {
+            if !is_true(query, "force") {
+                return error(StatusCode::CONFLICT, format!(
+                    "You cannot remove a running container {}. Stop the container before \
+                     attempting removal or force remove", self.containers[index].id));
+            }
+            self.exit(index, 137);
+        }
+
+        let container = self.containers.remove(index);
+        self.emit(CONTAINER, "destroy", &container.id, Some(&container.image));
+
+        empty(StatusCode::NO_CONTENT)
+    }
+
+    /// Output written so far, followed by live output until the container
+    /// exits when `follow` is set
+    fn container_logs(&mut self, id: &str, query: &Query) -> Reply {
+        let index = match self.container(id) {
+            Some(index) => index,
+            None => return error(StatusCode::NOT_FOUND, format!("No such container: {}", id)),
+        };
+        let container = &mut self.containers[index];
+        let filter = LogFilter {
+            stdout: is_true(query, "stdout"),
+            stderr: is_true(query, "stderr"),
+            tty: container.config["Tty"].as_bool().unwrap_or(false),
+        };
+        if !filter.stdout && !filter.stderr {
+            return error(StatusCode::BAD_REQUEST,
+                         "Bad parameters: you must choose at least one stream".to_owned());
+        }
+
+        let mut past: Vec<Bytes> = container.logs.iter()
+            .filter_map(|&(stream, ref data)| filter.frame(stream, data))
+            .collect();
+        if let Some(tail) = query.get("tail").and_then(|t| t.parse::<usize>().ok()) {
+            let skip = past.len().saturating_sub(tail);
+            past.drain(..skip);
+        }
+
+        if is_true(query, "follow") && container.running {
+            let (sender, receiver) = mpsc::unbounded();
+            container.followers.push((filter, sender));
+            raw_stream(stream::iter(past).chain(receiver))
+        } else {
+            raw_stream(stream::iter(past))
+        }
+    }
+
+    fn create_exec(&mut self, id: &str, body: Value) -> Reply {
+        let (container, image) = match self.container(id) {
+            Some(index) if self.containers[index].running =>
+                (self.containers[index].id.clone(), self.containers[index].image.clone()),
+            Some(index) => return error(StatusCode::CONFLICT, format!(
+                "Container {} is not running", self.containers[index].id)),
+            None => return error(StatusCode::NOT_FOUND, format!("No such container: {}", id)),
+        };
+        let cmd = match strings(&body["Cmd"]) {
+            Some(ref cmd) if !cmd.is_empty() => cmd.clone(),
+            _ => return error(StatusCode::BAD_REQUEST, "No exec command specified".to_owned()),
+        };
+
+        let exec = FakeExec {
+            id: self.new_id(),
+            container: container.clone(),
+            stdout: body["AttachStdout"].as_bool().unwrap_or(false),
+            stderr: body["AttachStderr"].as_bool().unwrap_or(false),
+            tty: body["Tty"].as_bool().unwrap_or(false),
+            cmd,
+            exit_code: None,
+        };
+        let id = exec.id.clone();
+        let status = format!("exec_create: {}", exec.cmd.join(" "));
+        self.execs.push(exec);
+        self.emit(CONTAINER, &status, &container, Some(&image));
+
+        respond(StatusCode::CREATED, &ExecCreated { id })
+    }
+
+    /// Runs the command to completion and answers with its attached output
+    fn start_exec(&mut self, id: &str) -> Reply {
+        let index = match self.execs.iter().position(|e| e.id == id) {
+            Some(index) => index,
+            None => return error(StatusCode::NOT_FOUND, format!("No such exec instance: {}", id)),
+        };
+        if self.execs[index].exit_code.is_some() {
+            return error(StatusCode::CONFLICT, format!("Exec {} has already run", id));
+        }
+        let running = self.container(&self.execs[index].container)
+            .map(|c| self.containers[c].running)
+            .unwrap_or(false);
+        if !running {
+            return error(StatusCode::CONFLICT, format!(
+                "Container {} is not running", self.execs[index].container));
+        }
+
+        let output = match self.exec_handler {
+            Some(ref handler) => handler(&self.execs[index].cmd),
+            None => default_exec(&self.execs[index].cmd),
+        };
+
+        let (container, command, chunks) = {
+            let exec = &mut self.execs[index];
+            exec.exit_code = Some(output.exit_code);
+
+            let filter = LogFilter { stdout: exec.stdout, stderr: exec.stderr, tty: exec.tty };
+            let chunks: Vec<Bytes> = vec![
+                filter.frame(LogStream::Stdout, &output.stdout),
+                filter.frame(LogStream::Stderr, &output.stderr),
+            ].into_iter()
+                .flatten()
+                .collect();
+
+            (exec.container.clone(), exec.cmd.join(" "), chunks)
+        };
+        self.emit(CONTAINER, &format!("exec_start: {}", command), &container, None);
+        self.emit(CONTAINER, "exec_die", &container, None);
+
+        raw_stream(stream::iter(chunks))
+    }
+
+    fn inspect_exec(&self, id: &str) -> Reply {
+        match self.execs.iter().find(|e| e.id == id) {
+            Some(exec) => respond(StatusCode::OK, &ExecDetails {
+                id: exec.id.clone(),
+                container: exec.container.clone(),
+                running: false,
+                exit_code: exec.exit_code,
+                process: ProcessConfig {
+                    entrypoint: exec.cmd[0].clone(),
+                    arguments: exec.cmd[1..].to_vec(),
+                    tty: exec.tty,
+                },
+            }),
+            None => error(StatusCode::NOT_FOUND, format!("No such exec instance: {}", id)),
+        }
+    }
+
+    fn list_images(&self) -> Reply {
+        let images: Vec<ImageRep> = self.images.iter()
+            .rev()
+            .map(|i| ImageRep {
+                Created: i.created,
+                Id: i.id.clone(),
+                ParentId: String::new(),
+                Labels: None,
+                RepoTags: i.tags.clone(),
+                RepoDigests: None,
+                VirtualSize: 0,
+            })
+            .collect();
+
+        respond(StatusCode::OK, &images)
+    }
+
+    fn tag_image(&mut self, name: &str, query: &Query) -> Reply {
+        let index = match self.image_index(name) {
+            Some(index) => index,
+            None => return error(StatusCode::NOT_FOUND, format!("No such image: {}", name)),
+        };
+        let repo = match query.get("repo") {
+            Some(repo) if !repo.is_empty() => repo,
+            _ => return error(StatusCode::BAD_REQUEST,
+                              "repository name must have at least one component".to_owned()),
+        };
+        let tag = format!("{}:{}", repo, query.get("tag").map(String::as_str).unwrap_or("latest"));
+
+        for image in self.images.iter_mut() {
+            image.tags.retain(|t| *t != tag);
+        }
+        self.images[index].tags.push(tag.clone());
+        let id = self.images[index].id.clone();
+        self.emit(IMAGE, "tag", &id, Some(&tag));
+
+        empty(StatusCode::CREATED)
+    }
+
+    /// Adds every image named in the `manifest.json` of an image tarball
+    fn load_images(&mut self, tarball: &[u8]) -> Reply {
+        let tags = match manifest_tags(tarball) {
+            Some(tags) => tags,
+            None => return error(StatusCode::INTERNAL_SERVER_ERROR,
+                                 "open manifest.json: no such file or directory".to_owned()),
+        };
+
+        let mut output = Vec::new();
+        for tag in tags.iter().map(|tag| normalize_tag(tag)) {
+            for image in self.images.iter_mut() {
+                image.tags.retain(|t| *t != tag);
+            }
+            let id = format!("sha256:{}", self.new_id());
+            self.images.push(FakeImage {
+                id: id.clone(),
+                tags: vec![tag.clone()],
+                created: self.clock,
+            });
+            self.emit(IMAGE, "load", &id, Some(&tag));
+
+            let line = json!({ "stream": format!("Loaded image: {}\n", tag) });
+            output.extend(serde_json::to_vec(&line).expect("Progress serialization"));
+            output.push(b'\n');
+        }
+
+        future::ready(Response::builder()
+            .header("content-type", "application/json")
+            .body(Body::from(output))
+            .map_err(Error::from)).boxed()
+    }
+
+    fn delete_image(&mut self, name: &str, query: &Query) -> Reply {
+        let index = match self.image_index(name) {
+            Some(index) => index,
+            None => return error(StatusCode::NOT_FOUND, format!("No such image: {}", name)),
+        };
+        let force = is_true(query, "force");
+        let id = self.images[index].id.clone();
+        let tag = normalize_tag(name);
+
+        // one of several tags only loses the reference
+        if self.images[index].tags.len() > 1 && self.images[index].tags.contains(&tag) {
+            self.images[index].tags.retain(|t| *t != tag);
+            self.emit(IMAGE, "untag", &id, Some(&tag));
+            return respond(StatusCode::OK, &vec![json!({ "Untagged": tag })]);
+        }
+
+        if !force {
+            let user = self.containers.iter()
+                .find(|c| self.image(&c.image).map(|i| i.id == id).unwrap_or(false));
+            if let Some(container) = user {
+                return error(StatusCode::CONFLICT, format!(
+                    "conflict: unable to remove repository reference \"{}\" (must force) - \
+                     container {} is using its referenced image {}", name, container.id, id));
+            }
+            if self.images[index].tags.len() > 1 {
+                return error(StatusCode::CONFLICT, format!(
+                    "conflict: unable to delete {} (must be forced) - image is referenced in \
+                     multiple repositories", id));
+            }
+        }
+
+        let image = self.images.remove(index);
+        let mut statuses = Vec::new();
+        for tag in image.tags {
+            self.emit(IMAGE, "untag", &id, Some(&tag));
+            statuses.push(json!({ "Untagged": tag }));
+        }
+        self.emit(IMAGE, "delete", &id, None);
+        statuses.push(json!({ "Deleted": id }));
+
+        respond(StatusCode::OK, &statuses)
+    }
+
+    fn network_details(network: &FakeNetwork) -> NetworkDetails {
+        NetworkDetails {
+            Name: network.name.clone(),
+            Id: network.id.clone(),
+            Scope: "local".to_owned(),
+            Driver: network.driver.clone(),
+            EnableIPv6: false,
+            IPAM: IPAM {
+                Driver: "default".to_owned(),
+                Config: Vec::new(),
+                Options: None,
+            },
+            Internal: false,
+            Attachable: false,
+            Containers: HashMap::new(),
+            Options: None,
+            Labels: Some(network.labels.clone()),
+        }
+    }
+
+    fn list_networks(&self) -> Reply {
+        let networks: Vec<NetworkDetails> = self.networks.iter()
+            .map(FakeState::network_details)
+            .collect();
+
+        respond(StatusCode::OK, &networks)
+    }
+
+    fn create_network(&mut self, body: Value) -> Reply {
+        let name = match body["Name"].as_str() {
+            Some(name) => name.to_owned(),
+            None => return error(StatusCode::BAD_REQUEST, "No network name specified".to_owned()),
+        };
+        if self.networks.iter().any(|n| n.name == name) {
+            return error(StatusCode::CONFLICT,
+                         format!("network with name {} already exists", name));
+        }
+
+        let id = self.new_id();
+        let network = FakeNetwork {
+            id: id.clone(),
+            name,
+            driver: body["Driver"].as_str().unwrap_or("bridge").to_owned(),
+            labels: labels(&body["Labels"]),
+            predefined: false,
+        };
+        self.emit_network("create", &network);
+        self.networks.push(network);
+
+        respond(StatusCode::CREATED, &NetworkCreateInfo {
+            Id: id,
+            Warning: String::new(),
+        })
+    }
+
+    fn inspect_network(&self, id: &str) -> Reply {
+        match self.network(id) {
+            Some(index) => respond(StatusCode::OK,
+                                   &FakeState::network_details(&self.networks[index])),
+            None => error(StatusCode::NOT_FOUND, format!("network {} not found", id)),
+        }
+    }
+
+    fn delete_network(&mut self, id: &str) -> Reply {
+        let index = match self.network(id) {
+            Some(index) => index,
+            None => return error(StatusCode::NOT_FOUND, format!("network {} not found", id)),
+        };
+        if self.networks[index].predefined {
+            return error(StatusCode::FORBIDDEN, format!(
+                "{} is a pre-defined network and cannot be removed", self.networks[index].name));
+        }
+
+        let network = self.networks.remove(index);
+        self.emit_network("destroy", &network);
+
+        empty(StatusCode::NO_CONTENT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FakeDocker, Operation};
+    use crate::build::{ContainerListOptions, ContainerOptions, EventsOptions, ImageRemoveOptions,
+                       NetworkCreateOptions};
+    use crate::communicate::docker::DockerApi;
+    use crate::representation::rep::Status;
+    use crate::errors::ErrorKind;
+    use futures::executor::block_on;
+    use futures::{StreamExt, TryStreamExt};
+    use hyper::StatusCode;
+    use std::borrow::Cow;
+    use std::thread;
+
+    fn create(docker: &FakeDocker, name: &str) -> String {
+        let mut opts = ContainerOptions::builder("alpine");
+        opts.name(name);
+
+        block_on(docker.containers().create(&opts.build())).expect("Created").Id
+    }
+
+    #[test]
+    fn container_lifecycle() {
+        let docker = FakeDocker::new();
+        docker.add_image("alpine");
+        let id = create(&docker, "web");
+        let container = docker.container(Cow::Borrowed("web"));
+
+        block_on(container.start()).expect("Started");
+        assert!(block_on(container.inspect()).expect("Inspected").State.Running);
+        assert_eq!(1, block_on(docker.containers().list(&Default::default())).expect("List").len());
+
+        block_on(container.stop(None)).expect("Stopped");
+        assert_eq!(0, block_on(container.wait(&Default::default())).expect("Exited").StatusCode);
+        assert!(block_on(docker.containers().list(&Default::default())).expect("List").is_empty());
+        assert_eq!(1, block_on(docker.containers()
+            .list(&ContainerListOptions::builder().all().build())).expect("List all").len());
+
+        block_on(container.delete()).expect("Deleted");
+        let statuses: Vec<Option<String>> = docker.emitted_events().into_iter()
+            .filter(|e| e.id.as_ref() == Some(&id))
+            .map(|e| e.status)
+            .collect();
+        assert_eq!(vec![Some("create".to_owned()), Some("start".to_owned()),
+                        Some("die".to_owned()), Some("stop".to_owned()),
+                        Some("destroy".to_owned())], statuses);
+    }
+
+    #[test]
+    fn retag_and_untag() {
+        let docker = FakeDocker::new();
+        let id = docker.add_image("app:sha-123");
+        let image = docker.image(Cow::Borrowed("app:sha-123"));
+
+        assert_eq!(StatusCode::CREATED, block_on(image.tag("app", "prod")).expect("Tagged"));
+        let tags = |docker: &FakeDocker| block_on(docker.images().list(&Default::default()))
+            .expect("List")
+            .into_iter()
+            .flat_map(|i| i.RepoTags)
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["app:sha-123", "app:prod"], tags(&docker));
+
+        let untagged = block_on(image.remove(&ImageRemoveOptions::builder().noprune(true).build()))
+            .expect("Untagged");
+        match untagged.as_slice() {
+            [Status::Untagged(tag)] => assert_eq!("app:sha-123", tag),
+            other => panic!("Unexpected statuses {:?}", other),
+        }
+        assert_eq!(vec!["app:prod"], tags(&docker));
+
+        let deleted = block_on(docker.image(Cow::Borrowed("app:prod")).delete()).expect("Deleted");
+        match deleted.as_slice() {
+            [Status::Untagged(_), Status::Deleted(deleted)] => assert_eq!(&id, deleted),
+            other => panic!("Unexpected statuses {:?}", other),
+        }
+    }
+
+    #[test]
+    fn errors_like_a_daemon() {
+        let docker = FakeDocker::new();
+
+        let missing = block_on(docker.containers()
+            .create(&ContainerOptions::builder("alpine").build()));
+        match missing.map_err(|e| e.0) {
+            Err(ErrorKind::NotFound(_, _, message)) => assert_eq!("No such image: alpine", message),
+            other => panic!("Unexpected result {:?}", other.map(|c| c.Id)),
+        }
+
+        docker.add_image("alpine:latest");
+        create(&docker, "web");
+        block_on(docker.container(Cow::Borrowed("web")).start()).expect("Started");
+        match block_on(docker.container(Cow::Borrowed("web")).delete()).map_err(|e| e.0) {
+            Err(ErrorKind::Conflict(..)) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn injected_failures() {
+        let docker = FakeDocker::new();
+        docker.fail_once(Operation::Ping, StatusCode::INTERNAL_SERVER_ERROR, "restarting");
+
+        assert!(block_on(docker.ping()).is_err());
+        assert_eq!(StatusCode::OK, block_on(docker.ping()).expect("Ping"));
+
+        docker.fail(Operation::NetworkCreate, StatusCode::FORBIDDEN, "denied");
+        let create = || block_on(docker.networks()
+            .create(&NetworkCreateOptions::builder("backend").build()));
+        assert!(create().is_err());
+        assert!(create().is_err());
+
+        docker.clear_failures();
+        let id = create().expect("Network").Id;
+        assert_eq!("backend", block_on(docker.network(Cow::Owned(id)).inspect())
+            .expect("Inspected").Name);
+        assert_eq!(4, block_on(docker.networks().list(&Default::default())).expect("List").len());
+    }
+
+    #[test]
+    fn wait_and_events() {
+        let docker = FakeDocker::new();
+        docker.add_image("alpine");
+        let id = create(&docker, "job");
+        block_on(docker.container(Cow::Borrowed("job")).start()).expect("Started");
+
+        let events = docker.events(&EventsOptions::builder().since(&0).build());
+        let waiting = docker.container(Cow::Borrowed("job"));
+        let waiter = thread::spawn(move || block_on(waiting.wait(&Default::default())).expect("Exited").StatusCode);
+
+        docker.exit(&id[..6], 3).expect("Exit");
+
+        assert_eq!(3, waiter.join().expect("Waiter"));
+        let statuses: Vec<String> = block_on(events.take(4).try_collect::<Vec<_>>())
+            .expect("Events")
+            .into_iter()
+            .map(|e| e.status.expect("Status"))
+            .collect();
+        assert_eq!(vec!["pull", "create", "start", "die"], statuses);
+    }
+
+    #[test]
+    fn network_events_are_typed() {
+        let docker = FakeDocker::new();
+        docker.add_image("alpine");
+        create(&docker, "web");
+        let id = block_on(docker.networks()
+            .create(&NetworkCreateOptions::builder("backend").build()))
+            .expect("Network").Id;
+        block_on(docker.network(Cow::Owned(id.clone())).delete()).expect("Deleted");
+
+        let events = docker.emitted_events();
+        let types: Vec<&str> = events.iter().map(|e| e.Type.as_deref().expect("Type")).collect();
+        assert_eq!(vec!["image", "container", "network", "network"], types);
+
+        let actor = events[2].Actor.as_ref().expect("Actor");
+        assert_eq!((id.as_str(), Some("create")), (actor.ID.as_str(), events[2].Action.as_deref()));
+        assert_eq!("backend", actor.Attributes["name"]);
+        assert!(events[3].status.is_none() && events[3].id.is_none());
+    }
+}
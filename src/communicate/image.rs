@@ -1,32 +1,41 @@
-use representation::rep::ImageDetails;
-use representation::rep::History;
-use Error;
-use Result;
-use representation::rep::Status;
+use crate::representation::rep::ImageDetails;
+use crate::representation::rep::History;
+use crate::Error;
+use crate::Result;
+use crate::representation::rep::Status;
+use crate::representation::rep::PushEvent;
+use crate::build::ImageRemoveOptions;
+use crate::build::PushOptions;
+use crate::build::RegistryAuth;
+use crate::communicate::util::{IntoRequestArgs, X_REGISTRY_AUTH};
 use serde_json::Value;
 use std::borrow::Cow;
-use errors::ErrorKind as EK;
+use crate::errors::ErrorKind as EK;
 use futures::Stream;
-use transport::parse::parse_to_trait;
-use futures::Future;
-use futures::future;
+use http::header::HeaderValue;
+use crate::transport::parse::parse_to_trait;
+use crate::transport::parse::parse_to_chunks;
+use crate::transport::parse::parse_to_progress;
 use std::sync::Arc;
-use transport::interact::InteractApi;
-use transport::interact::InteractApiExt;
-use hyper::Chunk;
+use crate::transport::interact::InteractApi;
+use crate::transport::interact::InteractApiExt;
+use bytes::Bytes;
+use hyper::StatusCode;
+use url::form_urlencoded;
+use crate::transport::parse::status_code;
 
 
 /// Interface for accessing and manipulating a named docker image
 pub struct Image<'b>
 {
-    interact: Arc<InteractApi>,
+    interact: Arc<dyn InteractApi>,
     name: Cow<'b, str>,
 }
 
 impl<'b> Image<'b>
 {
     /// Exports an interface for operations that may be performed against a named image
-    pub(crate) fn new<S>(interact: Arc<InteractApi>, name: S) -> Image<'b>
+    pub(crate) fn new<S>(interact: Arc<dyn InteractApi>, name: S) -> Image<'b>
     where
         S: Into<Cow<'b, str>>,
     {
@@ -37,21 +46,38 @@ impl<'b> Image<'b>
     }
 
     /// Inspects a named image's details
-    pub fn inspect(&self) -> impl Future<Item=ImageDetails, Error=Error> + Send {
+    pub async fn inspect(&self) -> Result<ImageDetails> {
         let args = format!("/images/{}/json", self.name);
 
-        parse_to_trait::<ImageDetails>(self.interact.get(args.as_str()))
+        parse_to_trait::<ImageDetails, _>(self.interact.get(args.as_str())).await
     }
 
     /// Lists the history of the images set of changes
-    pub fn history(&self) -> impl Future<Item=History, Error=Error> + Send {
+    pub async fn history(&self) -> Result<History> {
         let args = format!("/images/{}/history", self.name);
 
-        parse_to_trait::<History>(self.interact.get(args.as_str()))
+        parse_to_trait::<History, _>(self.interact.get(args.as_str())).await
+    }
+
+    /// Tags the image as `repo:tag`, e.g. `app` and `prod`
+    pub async fn tag(&self, repo: &str, tag: &str) -> Result<StatusCode> {
+        let path = format!("/images/{}/tag", self.name);
+        let query = form_urlencoded::serialize(vec![("repo", repo), ("tag", tag)]);
+        let args = (path.as_str(), Some(query.as_str()));
+
+        status_code(self.interact.post(args)).await
     }
 
     /// Deletes an image
-    pub fn delete(&self) -> impl Future<Item=Vec<Status>, Error=Error> + Send {
+    ///
+    /// Use remove instead to use the force/noprune options.
+    pub async fn delete(&self) -> Result<Vec<Status>> {
+        self.remove(&ImageRemoveOptions::default()).await
+    }
+
+    /// Removes the image, or only untags it if the name is one of several
+    /// tags of the image
+    pub async fn remove(&self, opts: &ImageRemoveOptions) -> Result<Vec<Status>> {
 
         fn parse_array(xs: Vec<Value>) -> Result<Vec<Status>> {
             xs
@@ -79,24 +105,95 @@ impl<'b> Image<'b>
                 .collect()
         }
 
-        let args = format!("/images/{}", self.name);
+        let path = format!("/images/{}", self.name);
+        let query = opts.serialize();
+        let args = (path.as_str(), query.as_deref());
 
-        parse_to_trait::<Value>(self.interact.delete(args.as_str()))
-            .and_then(|val|
-                match val {
-                    Value::Array(xs) => future::result(parse_array(xs)),
-                    _ => unreachable!(),
-                }
-            )
+        match parse_to_trait::<Value, _>(self.interact.delete(args)).await? {
+            Value::Array(xs) => parse_array(xs),
+            _ => Err(EK::JsonTypeError("<root>", "Array").into()),
+        }
+    }
+
+    /// Pushes the image to its registry, reporting the progress of every
+    /// layer. An error reported by the daemon fails the stream
+    pub fn push(&self, opts: &PushOptions) -> impl Stream<Item=Result<PushEvent>> + Send {
+        let path = format!("/images/{}/push", self.name);
+        let query = opts.serialize();
+        // the daemon insists on the header, even without credentials
+        let auth = opts.auth().cloned().unwrap_or_else(RegistryAuth::default);
+        let timeouts = opts.timeouts();
+        let interact = self.interact.clone();
+        let endpoint = path.clone();
+
+        parse_to_progress(endpoint, async move {
+            let mut args = (path.as_str(), query.as_deref()).into_request_args();
+            args.set_header(X_REGISTRY_AUTH, HeaderValue::from_str(&auth.serialize())?);
+            args.timeouts = timeouts;
+
+            interact.post(args).await
+        })
     }
 
     /// Export this image to a tarball
-    pub fn export(&self) -> impl Stream<Item=Chunk, Error=Error> + Send {
+    pub fn export(&self) -> impl Stream<Item=Result<Bytes>> + Send {
         let path = format!("/images/{}/get", self.name);
+        let interact = self.interact.clone();
+
+        parse_to_chunks(async move { interact.get(path.as_str()).await })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::build::{PushOptions, RegistryAuth};
+    use crate::communicate::util::{RequestArgs, X_REGISTRY_AUTH};
+    use crate::docker::new_docker_with_interact;
+    use crate::errors::{ErrorKind, Result};
+    use crate::test_util::Stub;
+    use crate::transport::body::Body;
+    use futures::StreamExt;
+    use hyper::{Method, Response};
+    use std::borrow::Cow;
+    use std::sync::Arc;
+    use tokio::runtime::Runtime;
 
-        self.interact.get(path.as_str())
-            .and_then(|a| a.map_err(Error::from))
-            .and_then(|a| Ok(a.into_body().map_err(Error::from)))
-            .flatten_stream()
+    /// Answers a push with one layer, rejecting it unless credentials were sent
+    async fn registry(opts: RequestArgs, _method: Method) -> Result<Response<Body>> {
+        let authorized = opts.header.get(X_REGISTRY_AUTH)
+            .map(|auth| auth.as_bytes() != b"e30=")
+            .unwrap_or(false);
+        let mut body = String::from(concat!(
+            r#"{"status":"The push refers to repository [registry.example.com/app]"}"#, "\n",
+            r#"{"status":"Pushing","id":"4b8d","progressDetail":{"current":512,"total":1024}}"#,
+            "\n"));
+        if !authorized {
+            body.push_str(r#"{"errorDetail":{"message":"unauthorized"},"error":"unauthorized"}"#);
+        }
+
+        Ok(Response::new(Body::from(body)))
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn push_fails_on_reported_errors() {
+        let docker = new_docker_with_interact(Arc::new(Stub(registry)));
+        let image = docker.image(Cow::Borrowed("registry.example.com/app"));
+        let runtime = Runtime::new().expect("Runtime");
+
+        let events = runtime.block_on(image.push(&PushOptions::default()).collect::<Vec<_>>());
+        assert_eq!(3, events.len());
+        let progress = events[1].as_ref().expect("Progress").progressDetail.clone().expect("Detail");
+        assert_eq!((Some(512), Some(1024)), (progress.current, progress.total));
+        match events[2] {
+            Err(ref err) => match *err.kind() {
+                ErrorKind::StreamError(_, ref message) => assert_eq!("unauthorized", message),
+                ref other => panic!("Unexpected error {:?}", other),
+            },
+            Ok(ref event) => panic!("Unexpected event {:?}", event),
+        }
+
+        let opts = PushOptions::builder().auth(RegistryAuth::token("t0k3n")).build();
+        let events = runtime.block_on(image.push(&opts).collect::<Vec<_>>());
+        assert!(events.iter().all(|event| event.is_ok()));
+    }
+}
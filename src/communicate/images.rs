@@ -1,104 +1,303 @@
 use std::sync::Arc;
-use transport::interact::InteractApi;
-use build::BuildOptions;
-use futures::Stream;
-use representation::rep::Top;
-use Error;
-use tarball::tarball;
-use build::ImageListOptions;
-use representation::rep::SearchResult;
-use serde_json::Value;
+use crate::transport::interact::InteractApi;
+use crate::build::BuildOptions;
+use futures::{Stream, TryStreamExt};
+use crate::representation::rep::BuildEvent;
+use crate::errors::ErrorKind;
+use crate::Result;
+use crate::tarball::body;
+use crate::tarball::tarball;
+use crate::build::ImageListOptions;
+use crate::representation::rep::SearchResult;
 use url::form_urlencoded;
-use build::PullOptions;
-use hyper::Body;
-use transport::parse::parse_to_trait;
-use communicate::util::AsSlice;
-use transport::interact::InteractApiExt;
-use futures::future;
-use futures::Future;
-use rep::Image as ImageRep;
-use communicate::util::build_simple_query;
-use transport::parse::parse_to_lines;
+use crate::build::PullOptions;
+use crate::build::ImageLoadOptions;
+use crate::representation::rep::LoadEvent;
+use crate::transport::body::Body;
+use crate::transport::parse::parse_to_trait;
+use crate::transport::interact::InteractApiExt;
+use crate::rep::Image as ImageRep;
+use crate::communicate::util::build_simple_query;
+use crate::communicate::util::IntoRequestArgs;
+use crate::communicate::util::{X_REGISTRY_AUTH, X_REGISTRY_CONFIG};
+use http::header::{HeaderValue, CONTENT_TYPE};
+use crate::transport::parse::{parse_to_chunks, parse_to_file};
+use bytes::Bytes;
+use std::path::Path;
+use crate::transport::parse::parse_to_progress;
+use crate::representation::rep::PullEvent;
+use crate::communicate::progress::PullProgress;
 
 /// Interface for docker images
 pub struct Images
 {
-    interact: Arc<InteractApi>,
+    interact: Arc<dyn InteractApi>,
 }
 
 impl Images {
     /// Exports an interface for interacting with docker images
-    pub(crate) fn new(interact: Arc<InteractApi>) -> Images
+    pub(crate) fn new(interact: Arc<dyn InteractApi>) -> Images
     {
         Images {
             interact,
         }
     }
 
-    /// Builds a new image build by reading a Dockerfile in a target directory
-    pub fn build(&self, opts: &BuildOptions) -> impl Future<Item=Vec<Top>, Error=Error> + Send {
-        let mut bytes = vec![];
-        let interact = self.interact.clone();
-
+    /// Builds a new image build by reading a Dockerfile in a target directory,
+    /// streaming the output of the build. An error reported by the daemon
+    /// fails the stream
+    pub fn build(&self, opts: &BuildOptions) -> impl Stream<Item=Result<BuildEvent>> + Send {
         let path = "/build";
         let query = opts.serialize();
+        let context = opts.path.clone();
+        let dockerfile = opts.dockerfile().to_owned();
+        let config = opts.registry_config();
+        // steps may run for long without any output
+        let timeouts = opts.timeouts().streaming();
+        let interact = self.interact.clone();
+
+        parse_to_progress(path.to_owned(), async move {
+            let body = body::streamed(move |w| tarball::build_context(w, &context, &dockerfile));
+
+            let mut args = (path, query.as_deref(), Some(body)).into_request_args();
+            args.set_header(CONTENT_TYPE, HeaderValue::from_static(TAR));
+            if let Some(config) = config {
+                args.set_header(X_REGISTRY_CONFIG, HeaderValue::from_str(&config)?);
+            }
+            args.timeouts = timeouts;
+
+            interact.post(args).await
+        })
+    }
 
-        future::result(tarball::dir(&mut bytes, &opts.path[..]))
-            .and_then(move |_| {
-                let body = Some(Body::from(bytes));
+    /// Builds an image, calling `on_event` with every output message.
+    /// resolves to the id of the built image
+    pub async fn build_with_output<F>(&self, opts: &BuildOptions, mut on_event: F) -> Result<String>
+    where
+        F: FnMut(&BuildEvent),
+    {
+        let mut image_id = None;
+        let mut events = Box::pin(self.build(opts));
+
+        while let Some(event) = events.try_next().await? {
+            if let Some(id) = event.image_id() {
+                image_id = Some(id.to_owned());
+            }
+            on_event(&event);
+        }
 
-                let args = (path, query.as_slice(), body);
-                parse_to_trait::<Vec<Top>>(interact.get(args))
-            })
+        image_id.ok_or_else(|| ErrorKind::Message("The build reported no image id".to_owned()).into())
     }
 
     /// Lists the docker images on the current docker host
-    pub fn list(&self, opts: &ImageListOptions) -> impl Future<Item=Vec<ImageRep>, Error=Error> + Send {
+    pub async fn list(&self, opts: &ImageListOptions) -> Result<Vec<ImageRep>> {
         let path = "/images/json";
         let query = opts.serialize();
 
-        let args = (path, query.as_slice());
+        let args = (path, query.as_deref());
 
-        parse_to_trait::<Vec<ImageRep>>(self.interact.get(args))
+        parse_to_trait::<Vec<ImageRep>, _>(self.interact.get(args)).await
     }
 
 
     /// Search for docker images by term
-    pub fn search(&self, term: &str) -> impl Future<Item=Vec<SearchResult>, Error=Error> + Send {
+    pub async fn search(&self, term: &str) -> Result<Vec<SearchResult>> {
         let path = "/images/search";
         let query = build_simple_query("term", Some(term));
 
-        let args = (path, query.as_slice());
+        let args = (path, query.as_deref());
 
-        parse_to_trait::<Vec<SearchResult>>(self.interact.get(args))
+        parse_to_trait::<Vec<SearchResult>, _>(self.interact.get(args)).await
     }
 
-    /// Pull and create a new docker images from an existing image
-    pub fn pull(&self, opts: &PullOptions) -> impl Future<Item=Value, Error=Error> + Send {
+    /// Pull and create a new docker images from an existing image, reporting
+    /// the progress of every layer. An error reported by the daemon fails the
+    /// stream
+    pub fn pull(&self, opts: &PullOptions) -> impl Stream<Item=Result<PullEvent>> + Send {
         let path = "/images/create";
         let query = opts.serialize();
+        let auth = opts.auth().map(|auth| auth.serialize());
+        let timeouts = opts.timeouts();
+        let interact = self.interact.clone();
+
+        parse_to_progress(path.to_owned(), async move {
+            let mut args = (path, query.as_deref()).into_request_args();
+            if let Some(auth) = auth {
+                args.set_header(X_REGISTRY_AUTH, HeaderValue::from_str(&auth)?);
+            }
+            args.timeouts = timeouts;
+
+            interact.post(args).await
+        })
+    }
 
-        let args = (path, query.as_slice());
+    /// Pulls an image, calling `on_progress` with the bytes downloaded so far
+    /// after every message. resolves to the digest of the pulled image, if the
+    /// daemon reported one
+    pub async fn pull_with_progress<F>(&self, opts: &PullOptions, mut on_progress: F)
+        -> Result<Option<String>>
+    where
+        F: FnMut(&PullProgress),
+    {
+        let mut progress = PullProgress::new();
+        let mut events = Box::pin(self.pull(opts));
+
+        while let Some(event) = events.try_next().await? {
+            progress.update(&event);
+            on_progress(&progress);
+        }
 
-        parse_to_trait::<Value>(self.interact.post(args))
+        Ok(progress.digest().map(str::to_owned))
     }
 
     /// exports a collection of named images,
     /// either by name, name:tag, or image id, into a tarball
-    pub fn export(&self, names: Vec<&str>) -> impl Stream<Item=String, Error=Error> + Send {
-        let params = names
-            .iter()
-            .map(|n| ("names", *n))
-            .collect::<Vec<(&str, &str)>>();
+    pub fn export(&self, names: Vec<&str>) -> impl Stream<Item=Result<Bytes>> + Send {
+        let query = export_query(names);
+        let interact = self.interact.clone();
+
+        parse_to_chunks(async move { interact.get(("/images/get", query.as_deref())).await })
+    }
+
+    /// exports a collection of named images into a tarball at `filepath`,
+    /// gzipped if asked to. The file is replaced once the export completes.
+    /// resolves to the bytes written
+    pub async fn export_to_path<P>(&self, names: Vec<&str>, filepath: P, gzip: bool) -> Result<u64>
+    where
+        P: AsRef<Path>,
+    {
+        let query = export_query(names);
+        let interact = self.interact.clone();
+
+        parse_to_file(async move { interact.get(("/images/get", query.as_deref())).await },
+                      filepath.as_ref(), gzip).await
+    }
+
+    /// Loads the images of a tarball as produced by `export`. The tarball is
+    /// streamed to the daemon, e.g. `Body::wrap_stream` of a file
+    pub fn load<B>(&self, tarball: B, opts: &ImageLoadOptions)
+        -> impl Stream<Item=Result<LoadEvent>> + Send
+    where
+        B: Into<Body>,
+    {
+        let path = "/images/load";
+        let query = opts.serialize();
+        let body = tarball.into();
+        let timeouts = opts.timeouts();
+        let interact = self.interact.clone();
+
+        parse_to_progress(path.to_owned(), async move {
+            let mut args = (path, query.as_deref(), Some(body)).into_request_args();
+            args.set_header(CONTENT_TYPE, HeaderValue::from_static(TAR));
+            args.timeouts = timeouts;
+
+            interact.post(args).await
+        })
+    }
+
+    /// Creates an image `repo:tag` from the root filesystem in `source`, a
+    /// plain or compressed tarball. `changes` are Dockerfile instructions
+    /// applied to the image, e.g. `CMD ["/app"]`
+    pub fn import<B>(&self, source: B, repo: &str, tag: &str, changes: &[&str])
+        -> impl Stream<Item=Result<PullEvent>> + Send
+    where
+        B: Into<Body>,
+    {
+        let path = "/images/create";
+        let mut params = vec![("fromSrc", "-"), ("repo", repo), ("tag", tag)];
+        params.extend(changes.iter().map(|change| ("changes", *change)));
+        let query = form_urlencoded::serialize(params);
+        let body = source.into();
+        let interact = self.interact.clone();
+
+        parse_to_progress(path.to_owned(), async move {
+            let mut args = (path, Some(query.as_str()), Some(body)).into_request_args();
+            args.set_header(CONTENT_TYPE, HeaderValue::from_static(TAR));
+
+            interact.post(args).await
+        })
+    }
+}
+
+const TAR: &str = "application/x-tar";
+
+fn export_query(names: Vec<&str>) -> Option<String> {
+    let params = names
+        .iter()
+        .map(|n| ("names", *n))
+        .collect::<Vec<(&str, &str)>>();
 
-        let path = "/images/get";
-        let query = Some(form_urlencoded::serialize(params));
-        let args = (path, query.as_slice());
+    Some(form_urlencoded::serialize(params))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::build::{BuildOptions, ImageLoadOptions};
+    use crate::communicate::util::RequestArgs;
+    use crate::docker::new_docker_with_interact;
+    use crate::errors::Result;
+    use crate::test_util::{Stub, TempDir};
+    use crate::transport::body::Body;
+    use bytes::Bytes;
+    use futures::{stream, TryStreamExt};
+    use hyper::{Method, Response};
+    use std::io;
+    use std::sync::Arc;
+    use tokio::runtime::Runtime;
+
+    /// Loads the tarball by counting its bytes
+    async fn loader(opts: RequestArgs, _method: Method) -> Result<Response<Body>> {
+        let tarball = opts.body.bytes().await?;
+        let message = format!(r#"{{"stream":"Loaded {} bytes ({})\n"}}"#, tarball.len(), opts.query);
+
+        Ok(Response::new(Body::from(message)))
+    }
+
+    #[test]
+    fn load_streams_the_tarball() {
+        let docker = new_docker_with_interact(Arc::new(Stub(loader)));
+        let chunks = stream::iter((0..4).map(|_| Ok::<_, io::Error>(Bytes::from(vec![0; 512]))));
+        let opts = ImageLoadOptions::builder().quiet(true).build();
+
+        let events = Runtime::new().expect("Runtime")
+            .block_on(docker.images().load(Body::wrap_stream(chunks), &opts).try_collect::<Vec<_>>())
+            .expect("Loaded");
+        assert_eq!(Some("Loaded 2048 bytes (quiet=true)\n"), events[0].stream.as_deref());
+    }
+
+    /// Builds by echoing the output of a build, failing it for `fail` tags
+    async fn builder(opts: RequestArgs, method: Method) -> Result<Response<Body>> {
+        assert_eq!(Method::POST, method);
+        let mut output = String::from(concat!(
+            r#"{"stream":"Step 1/1 : FROM alpine\n"}"#, "\n",
+            r#"{"status":"Pulling from library/alpine","id":"latest"}"#, "\n"));
+        if opts.query.contains("fail") {
+            output.push_str(r#"{"errorDetail":{"code":1,"message":"exit code 1"},"error":"exit code 1"}"#);
+        } else {
+            output.push_str(r#"{"aux":{"ID":"sha256:b1d"}}"#);
+        }
 
-        parse_to_lines(self.interact.get(args))
+        Ok(Response::new(Body::from(output)))
     }
 
-    // pub fn import(self, tarball: Read>) -> Result<()> {
-    //  self.interact.post
-    // }
-}
\ No newline at end of file
+    #[test]
+    fn build_resolves_to_the_image_id() {
+        let dir = TempDir::new("build");
+        dir.write("Dockerfile", "FROM alpine\n");
+        let docker = new_docker_with_interact(Arc::new(Stub(builder)));
+        let runtime = Runtime::new().expect("Runtime");
+        let context = dir.path().to_str().expect("Path");
+
+        let mut lines = 0;
+        let opts = BuildOptions::builder(context).tag("app").build();
+        let id = runtime.block_on(docker.images().build_with_output(&opts, |event| {
+            lines += event.stream.iter().count();
+        }));
+        let failed = BuildOptions::builder(context).tag("fail").build();
+        let failed = runtime.block_on(docker.images().build(&failed).try_collect::<Vec<_>>());
+
+        assert_eq!("sha256:b1d", id.expect("Built"));
+        assert_eq!(1, lines);
+        assert!(failed.is_err());
+    }
+}
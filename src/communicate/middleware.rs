@@ -0,0 +1,279 @@
+//! Middleware around the transport of a client
+//!
+//! A `Layer` sees every request before it is sent, including the headers set
+//! by the docker interfaces, and the response it resolves to. Layers are
+//! stacked on top of a transport with `Layered`, the first added layer is
+//! the outermost one.
+//!
+//! ```no_run
+//! # use async_docker::{new_docker, new_docker_with_interact};
+//! # use async_docker::middleware::{HeaderLayer, Layered, LoggingLayer};
+//! # use hyper::header::{HeaderValue, USER_AGENT};
+//! # use std::sync::Arc;
+//! # fn main() -> async_docker::Result<()> {
+//! let mut headers = HeaderLayer::new();
+//! headers.insert(USER_AGENT, HeaderValue::from_static("deployer/1.0"));
+//!
+//! let docker = new_docker(None)?;
+//! let layered = Layered::builder(docker.interact())
+//!     .layer(LoggingLayer::new())
+//!     .layer(headers)
+//!     .build();
+//! let docker = new_docker_with_interact(Arc::new(layered));
+//! # Ok(())
+//! # }
+//! ```
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use async_trait::async_trait;
+use hyper::header::{HeaderName, HeaderValue};
+use hyper::{Method, Response};
+
+use crate::communicate::util::RequestArgs;
+use crate::communicate::version::ApiVersion;
+use crate::errors::Result;
+use crate::transport::body::Body;
+use crate::transport::interact::InteractApi;
+
+/// Hook around `InteractApi::request`
+///
+/// Implementations may change the request before handing it to `next`, and
+/// inspect or replace the result `next` resolves to. Not calling `next`
+/// short-circuits the request.
+#[async_trait]
+pub trait Layer: Send + Sync
+{
+    async fn handle(&self, opts: RequestArgs, method: Method, next: Next<'_>)
+        -> Result<Response<Body>>;
+}
+
+/// The layers below the current one, followed by the transport
+pub struct Next<'a> {
+    layers: &'a [Arc<dyn Layer>],
+    inner: &'a dyn InteractApi,
+}
+
+impl<'a> Next<'a> {
+    /// Sends the request through the remaining layers
+    pub async fn run(self, opts: RequestArgs, method: Method) -> Result<Response<Body>> {
+        match self.layers.split_first() {
+            Some((layer, layers)) => {
+                let next = Next {
+                    layers,
+                    inner: self.inner,
+                };
+                layer.handle(opts, method, next).await
+            }
+            None => self.inner.request(opts, method).await,
+        }
+    }
+}
+
+/// Transport sending every request through a stack of layers
+pub struct Layered {
+    inner: Arc<dyn InteractApi>,
+    layers: Vec<Arc<dyn Layer>>,
+}
+
+impl Layered {
+    /// return a new instance of a builder stacking layers on `inner`
+    pub fn builder(inner: Arc<dyn InteractApi>) -> LayeredBuilder {
+        LayeredBuilder::new(inner)
+    }
+}
+
+#[async_trait]
+impl InteractApi for Layered {
+    async fn request(&self, opts: RequestArgs, method: Method) -> Result<Response<Body>> {
+        let next = Next {
+            layers: &self.layers,
+            inner: &*self.inner,
+        };
+
+        next.run(opts, method).await
+    }
+
+    fn api_version(&self) -> Option<ApiVersion> {
+        self.inner.api_version()
+    }
+
+    fn set_api_version(&self, version: Option<ApiVersion>) {
+        self.inner.set_api_version(version)
+    }
+}
+
+/// Builder interface for `Layered`
+pub struct LayeredBuilder {
+    inner: Arc<dyn InteractApi>,
+    layers: Vec<Arc<dyn Layer>>,
+}
+
+impl LayeredBuilder {
+    pub fn new(inner: Arc<dyn InteractApi>) -> LayeredBuilder {
+        LayeredBuilder {
+            inner,
+            layers: Vec::new(),
+        }
+    }
+
+    /// Adds a layer below the ones added before
+    pub fn layer<L>(&mut self, layer: L) -> &mut LayeredBuilder
+    where
+        L: Layer + 'static,
+    {
+        self.layers.push(Arc::new(layer));
+        self
+    }
+
+    /// Adds a layer shared with other stacks
+    pub fn shared_layer(&mut self, layer: Arc<dyn Layer>) -> &mut LayeredBuilder {
+        self.layers.push(layer);
+        self
+    }
+
+    pub fn build(&self) -> Layered {
+        Layered {
+            inner: self.inner.clone(),
+            layers: self.layers.clone(),
+        }
+    }
+}
+
+/// Logs every request with its outcome and latency
+#[derive(Clone, Debug, Default)]
+pub struct LoggingLayer;
+
+impl LoggingLayer {
+    pub fn new() -> LoggingLayer {
+        LoggingLayer
+    }
+}
+
+#[async_trait]
+impl Layer for LoggingLayer {
+    async fn handle(&self, opts: RequestArgs, method: Method, next: Next<'_>)
+        -> Result<Response<Body>>
+    {
+        let endpoint = format!("{} {}", method, opts.path);
+        let started = Instant::now();
+
+        let result = next.run(opts, method).await;
+        match result {
+            Ok(ref response) => info!("{} -> {} in {:?}", endpoint, response.status(),
+                                      started.elapsed()),
+            Err(ref err) => warn!("{} failed in {:?}: {}", endpoint, started.elapsed(), err),
+        }
+
+        result
+    }
+}
+
+type HeaderFn = Arc<dyn Fn() -> HeaderValue + Send + Sync>;
+
+/// Sets headers on every request, replacing the ones already set
+#[derive(Clone, Default)]
+pub struct HeaderLayer {
+    headers: Vec<(HeaderName, HeaderFn)>,
+}
+
+impl HeaderLayer {
+    pub fn new() -> HeaderLayer {
+        HeaderLayer {
+            headers: Vec::new(),
+        }
+    }
+
+    /// Sets the header to a fixed value
+    pub fn insert<K>(&mut self, key: K, value: HeaderValue) -> &mut HeaderLayer
+    where
+        K: Into<HeaderName>,
+    {
+        self.headers.push((key.into(), Arc::new(move || value.clone())));
+        self
+    }
+
+    /// Sets the header to a value computed for every request, e.g. a tracing id
+    pub fn insert_with<K, F>(&mut self, key: K, value: F) -> &mut HeaderLayer
+    where
+        K: Into<HeaderName>,
+        F: Fn() -> HeaderValue + Send + Sync + 'static,
+    {
+        self.headers.push((key.into(), Arc::new(value)));
+        self
+    }
+}
+
+#[async_trait]
+impl Layer for HeaderLayer {
+    async fn handle(&self, mut opts: RequestArgs, method: Method, next: Next<'_>)
+        -> Result<Response<Body>>
+    {
+        for (key, value) in &self.headers {
+            opts.set_header(key.clone(), value());
+        }
+
+        next.run(opts, method).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HeaderLayer, Layer, Layered, Next};
+    use crate::communicate::util::RequestArgs;
+    use crate::docker::new_docker_with_interact;
+    use crate::errors::Result;
+    use crate::test_util::Stub;
+    use crate::transport::body::Body;
+    use crate::transport::interact::InteractApi;
+    use async_trait::async_trait;
+    use hyper::header::{HeaderValue, USER_AGENT};
+    use hyper::{Method, Response, StatusCode};
+    use std::sync::{Arc, Mutex};
+    use tokio::runtime::Runtime;
+
+    /// Answers every request with the user agent it was sent with
+    async fn echo(opts: RequestArgs, _method: Method) -> Result<Response<Body>> {
+        let agent = opts.header.get(USER_AGENT).map(|v| v.as_bytes().to_vec());
+
+        Ok(Response::new(Body::from(agent.unwrap_or_default())))
+    }
+
+    /// Records the method and status of every call
+    #[derive(Default)]
+    struct Calls(Mutex<Vec<String>>);
+
+    #[async_trait]
+    impl Layer for Arc<Calls> {
+        async fn handle(&self, opts: RequestArgs, method: Method, next: Next<'_>)
+            -> Result<Response<Body>>
+        {
+            let call = format!("{} {}", method, opts.path);
+            let response = next.run(opts, method).await?;
+            self.0.lock().unwrap().push(format!("{} {}", call, response.status()));
+
+            Ok(response)
+        }
+    }
+
+    #[test]
+    fn layers_see_requests_and_responses() {
+        let calls = Arc::new(Calls::default());
+        let mut headers = HeaderLayer::new();
+        headers.insert(USER_AGENT, HeaderValue::from_static("deployer/1.0"));
+        let layered = Layered::builder(Arc::new(Stub(echo)))
+            .layer(calls.clone())
+            .layer(headers)
+            .build();
+        let docker = new_docker_with_interact(Arc::new(layered));
+        let runtime = Runtime::new().expect("Runtime");
+
+        assert_eq!(StatusCode::OK, runtime.block_on(docker.ping()).expect("Ping"));
+        let body = runtime.block_on(docker.interact().request(RequestArgs::default(), Method::GET))
+            .expect("Response")
+            .into_body();
+        assert_eq!(&b"deployer/1.0"[..], &runtime.block_on(body.bytes()).expect("Body")[..]);
+        assert_eq!(vec!["GET /_ping 200 OK", "GET  200 OK"], *calls.0.lock().unwrap());
+    }
+}
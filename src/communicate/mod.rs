@@ -1,7 +1,10 @@
+pub mod builder;
 pub mod docker;
 mod ssl_tcp_docker;
+mod rustls_tcp_docker;
 mod tcp_docker;
 mod unix_docker;
+mod ssh_docker;
 pub mod util;
 pub mod container;
 pub mod image;
@@ -9,10 +12,31 @@ pub mod images;
 pub mod containers;
 pub mod network;
 pub mod networks;
+pub mod version;
+pub mod tls;
+pub mod timeout;
+pub mod retry;
+pub mod middleware;
+pub mod credentials;
+pub mod progress;
+pub mod cassette;
+pub mod fake;
 
 
-pub use container::Container;
-pub use image::Image;
-pub use images::Images;
-pub use network::Network;
-pub use docker::{DockerApi, new_docker};
\ No newline at end of file
+pub use crate::container::Container;
+pub use crate::image::Image;
+pub use crate::images::Images;
+pub use crate::network::Network;
+pub use crate::docker::{DockerApi, new_docker, new_docker_negotiated, new_docker_with_tls,
+                 new_docker_with_ssh, new_docker_with_connector,
+                 new_docker_with_connector_and_timeouts, new_docker_with_interact};
+pub use crate::version::ApiVersion;
+pub use crate::util::RequestArgs;
+pub use crate::transport::interact::InteractApi;
+pub use crate::transport::body::Body;
+pub use crate::builder::DockerBuilder;
+pub use crate::credentials::CredentialStore;
+pub use crate::progress::PullProgress;
+pub use crate::tls::{TlsConfig, TlsConfigBuilder};
+pub use crate::timeout::{RequestTimeouts, Timeout, Timeouts, TimeoutsBuilder};
+pub use self::ssh_docker::{SshConfig, SshConfigBuilder};
\ No newline at end of file
@@ -1,25 +1,23 @@
 use std::sync::Arc;
 use std::borrow::Cow;
-use transport::interact::InteractApi;
-use futures::Future;
-use Error;
-use transport::parse::status_code;
-use transport::parse::parse_to_trait;
+use crate::transport::interact::InteractApi;
+use crate::Result;
+use crate::transport::parse::status_code;
+use crate::transport::parse::parse_to_trait;
 use http::StatusCode;
-use representation::rep::NetworkDetails;
-use transport::interact::InteractApiExt;
-use build::ContainerConnectionOptions;
-use communicate::util::AsSlice;
+use crate::representation::rep::NetworkDetails;
+use crate::transport::interact::InteractApiExt;
+use crate::build::ContainerConnectionOptions;
 
 /// Interface for accessing and manipulating a docker network
 pub struct Network<'b> {
-    interact: Arc<InteractApi>,
+    interact: Arc<dyn InteractApi>,
     id: Cow<'b, str>,
 }
 
 impl<'b> Network<'b> {
     /// Exports an interface exposing operations against a network instance
-    pub(crate) fn new<S>(interact: Arc<InteractApi>, id: S) -> Network<'b>
+    pub(crate) fn new<S>(interact: Arc<dyn InteractApi>, id: S) -> Network<'b>
         where
             S: Into<Cow<'b, str>>,
     {
@@ -36,35 +34,34 @@ impl<'b> Network<'b> {
 
 
     /// Inspects the current docker network instance's details
-    pub fn inspect(&self) -> impl Future<Item=NetworkDetails, Error=Error> {
+    pub async fn inspect(&self) -> Result<NetworkDetails> {
         let path = format!("/networks/{}", self.id);
 
-        parse_to_trait::<NetworkDetails>(self.interact.get(path.as_str()))
+        parse_to_trait::<NetworkDetails, _>(self.interact.get(path.as_str())).await
     }
 
     /// Delete the network instance
-    pub fn delete(&self) -> impl Future<Item=StatusCode, Error=Error> {
+    pub async fn delete(&self) -> Result<StatusCode> {
         let path = format!("/networks/{}", self.id);
 
-        status_code(self.interact.delete(path.as_str()))
+        status_code(self.interact.delete(path.as_str())).await
     }
 
     /// Connect container to network
-    pub fn connect(&self, opts: &ContainerConnectionOptions) -> impl Future<Item=StatusCode, Error=Error> {
+    pub async fn connect(&self, opts: &ContainerConnectionOptions) -> Result<StatusCode> {
         let path = format!("/networks/{}/connect", self.id);
         let query = opts.serialize();
-        let args = (path.as_str(), query.as_slice());
+        let args = (path.as_str(), query.as_deref());
 
-        status_code(self.interact.post(args))
+        status_code(self.interact.post(args)).await
     }
 
     /// Disconnect container to network
-    pub fn disconnect(&self, opts: &ContainerConnectionOptions)
-                      -> impl Future<Item=StatusCode, Error=Error> {
+    pub async fn disconnect(&self, opts: &ContainerConnectionOptions) -> Result<StatusCode> {
         let path = format!("/networks/{}/disconnect", self.id);
         let query = opts.serialize();
-        let args = (path.as_str(), query.as_slice());
+        let args = (path.as_str(), query.as_deref());
 
-        status_code(self.interact.post(args))
+        status_code(self.interact.post(args)).await
     }
 }
@@ -1,46 +1,42 @@
 use std::sync::Arc;
-use transport::interact::InteractApi;
-use build::NetworkListOptions;
-use Error;
-use futures::Future;
-use representation::rep::NetworkDetails;
-use transport::parse::parse_to_trait;
-use communicate::util::AsSlice;
-use transport::interact::InteractApiExt;
-use representation::rep::NetworkCreateInfo;
-use build::NetworkCreateOptions;
-use hyper::Body;
+use crate::transport::interact::InteractApi;
+use crate::build::NetworkListOptions;
+use crate::Result;
+use crate::representation::rep::NetworkDetails;
+use crate::transport::parse::parse_to_trait;
+use crate::transport::interact::InteractApiExt;
+use crate::representation::rep::NetworkCreateInfo;
+use crate::build::NetworkCreateOptions;
+use crate::transport::body::Body;
 
 /// Interface for docker networks
 pub struct Networks {
-    interact: Arc<InteractApi>,
+    interact: Arc<dyn InteractApi>,
 }
 
 impl Networks {
     /// Exports an interface for interacting with docker Networks
-    pub(crate) fn new(interact: Arc<InteractApi>) -> Networks {
+    pub(crate) fn new(interact: Arc<dyn InteractApi>) -> Networks {
         Networks {
             interact
         }
     }
 
     /// List the docker networks on the current docker host
-    pub fn list(&self, opts: &NetworkListOptions)
-        -> impl Future<Item=Vec<NetworkDetails>, Error=Error> {
+    pub async fn list(&self, opts: &NetworkListOptions) -> Result<Vec<NetworkDetails>> {
         let path = "/networks";
         let query = opts.serialize();
-        let args = (path, query.as_slice());
+        let args = (path, query.as_deref());
 
-        parse_to_trait::<Vec<NetworkDetails>>(self.interact.get(args))
+        parse_to_trait::<Vec<NetworkDetails>, _>(self.interact.get(args)).await
     }
 
-    pub fn create(&self, opts: &NetworkCreateOptions)
-            -> impl Future<Item=NetworkCreateInfo, Error=Error> {
+    pub async fn create(&self, opts: &NetworkCreateOptions) -> Result<NetworkCreateInfo> {
         let path = "/networks/create";
         let bytes = opts.serialize().expect("Error during serialization");
         let body = Some(Body::from(bytes));
         let args = (path, body);
 
-        parse_to_trait::<NetworkCreateInfo>(self.interact.post_json(args))
+        parse_to_trait::<NetworkCreateInfo, _>(self.interact.post_json(args)).await
     }
 }
\ No newline at end of file
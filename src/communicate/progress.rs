@@ -0,0 +1,126 @@
+//! Overall progress of an image pull
+//!
+//! The daemon reports progress per layer. `PullProgress` folds those
+//! messages into the bytes downloaded across all layers and picks up the
+//! digest of the pulled image.
+
+use std::collections::HashMap;
+
+use crate::representation::rep::PullEvent;
+
+const DIGEST_PREFIX: &str = "Digest: ";
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Layer {
+    current: u64,
+    total: u64,
+    done: bool,
+}
+
+/// Bytes downloaded across all layers of a pull
+#[derive(Clone, Debug, Default)]
+pub struct PullProgress {
+    layers: HashMap<String, Layer>,
+    digest: Option<String>,
+}
+
+impl PullProgress {
+    pub fn new() -> PullProgress {
+        PullProgress {
+            ..Default::default()
+        }
+    }
+
+    /// Accounts for a message of the pull
+    pub fn update(&mut self, event: &PullEvent) {
+        let status = match event.status {
+            Some(ref status) => status.as_str(),
+            None => return,
+        };
+
+        if let Some(digest) = status.strip_prefix(DIGEST_PREFIX) {
+            self.digest = Some(digest.trim().to_owned());
+            return;
+        }
+
+        let id = match event.id {
+            Some(ref id) => id,
+            None => return,
+        };
+        match status {
+            "Pulling fs layer" | "Waiting" => {
+                self.layers.entry(id.clone()).or_default();
+            }
+            "Downloading" => {
+                let layer = self.layers.entry(id.clone()).or_default();
+                if let Some(ref detail) = event.progressDetail {
+                    layer.current = detail.current.unwrap_or(layer.current);
+                    layer.total = detail.total.unwrap_or(layer.total);
+                }
+            }
+            // extraction reports its own bytes, the download is complete
+            "Verifying Checksum" | "Download complete" | "Extracting" | "Pull complete"
+            | "Already exists" => {
+                let layer = self.layers.entry(id.clone()).or_default();
+                layer.current = layer.total;
+                layer.done = true;
+            }
+            _ => (),
+        }
+    }
+
+    /// Bytes downloaded so far
+    pub fn current(&self) -> u64 {
+        self.layers.values().map(|layer| layer.current).sum()
+    }
+
+    /// Bytes of the layers whose size is known so far
+    pub fn total(&self) -> u64 {
+        self.layers.values().map(|layer| layer.total).sum()
+    }
+
+    /// Layers the pull consists of, as far as reported
+    pub fn layers(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Layers which are downloaded or were present already
+    pub fn completed(&self) -> usize {
+        self.layers.values().filter(|layer| layer.done).count()
+    }
+
+    /// Digest of the pulled image, reported at the end of the pull
+    pub fn digest(&self) -> Option<&str> {
+        self.digest.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PullProgress;
+    use crate::representation::rep::PullEvent;
+
+    fn event(json: &str) -> PullEvent {
+        serde_json::from_str(json).expect("Pull event")
+    }
+
+    #[test]
+    fn aggregates_layers_and_digest() {
+        let mut progress = PullProgress::new();
+        for json in &[
+            r#"{"status":"Pulling from library/alpine","id":"3.19"}"#,
+            r#"{"status":"Pulling fs layer","progressDetail":{},"id":"a1"}"#,
+            r#"{"status":"Already exists","progressDetail":{},"id":"b2"}"#,
+            r#"{"status":"Downloading","progressDetail":{"current":100,"total":400},"id":"a1"}"#,
+        ] {
+            progress.update(&event(json));
+        }
+        assert_eq!((100, 400, 2, 1), (progress.current(), progress.total(),
+                                      progress.layers(), progress.completed()));
+
+        progress.update(&event(r#"{"status":"Download complete","progressDetail":{},"id":"a1"}"#));
+        progress.update(&event(r#"{"status":"Digest: sha256:c5b1"}"#));
+        assert_eq!((400, 400, 2), (progress.current(), progress.total(), progress.completed()));
+        assert_eq!(Some("sha256:c5b1"), progress.digest());
+    }
+}
@@ -0,0 +1,401 @@
+//! Retries of idempotent requests failing with transient transport errors
+//!
+//! While dockerd restarts, requests fail with refused or reset connections.
+//! `Retry` wraps the transport of a client and resends idempotent requests,
+//! GETs and HEADs without a body, with exponential backoff and jitter when
+//! the connection was refused, reset or timed out, or the daemon answered
+//! with a 5xx or 429 status. Any other request, such as creating a container
+//! or an exec, is never resent.
+//!
+//! ```no_run
+//! # use async_docker::{new_docker, new_docker_with_interact};
+//! # use async_docker::retry::{Retry, RetryPolicy};
+//! # use std::sync::Arc;
+//! # fn main() -> async_docker::Result<()> {
+//! let mut policy = RetryPolicy::builder();
+//! policy
+//!     .max_retries(5)
+//!     .on_retry(|retry| eprintln!("retrying {} {}: {}", retry.method, retry.path, retry.error));
+//!
+//! let docker = new_docker(None)?;
+//! let docker = new_docker_with_interact(Arc::new(Retry::new(docker.interact(), policy.build())));
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::hash_map::RandomState;
+use std::error::Error as StdError;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use http_body::Body as HttpBody;
+use hyper::{Method, Response, StatusCode};
+
+use crate::communicate::util::RequestArgs;
+use crate::communicate::version::ApiVersion;
+use crate::errors::{Error, ErrorKind, Result};
+use crate::transport::body::Body;
+use crate::transport::interact::InteractApi;
+use crate::transport::parse::check_status;
+
+/// A failed attempt which is about to be retried
+pub struct RetryEvent<'a> {
+    pub method: &'a Method,
+    /// Path without the API version prefix
+    pub path: &'a str,
+    /// Number of the retry, starting at 1
+    pub attempt: u32,
+    /// Time waited before the retry is sent
+    pub delay: Duration,
+    pub error: &'a Error,
+}
+
+type RetryCallback = Arc<dyn Fn(&RetryEvent) + Send + Sync>;
+
+/// How often and how fast idempotent requests are retried
+#[derive(Clone)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: f64,
+    jitter: f64,
+    on_retry: Option<RetryCallback>,
+}
+
+impl RetryPolicy {
+    /// return a new instance of a builder for the retry policy
+    pub fn builder() -> RetryPolicyBuilder {
+        RetryPolicyBuilder::new()
+    }
+
+    /// Backoff before the given retry, starting at 1, without jitter
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let backoff = self.initial_backoff.as_nanos() as f64 * self.multiplier.powi(exponent);
+
+        Duration::from_nanos(backoff.min(self.max_backoff.as_nanos() as f64).round() as u64)
+    }
+
+    /// Backoff before the given retry, shortened by a random part of up to
+    /// `jitter` of it
+    fn delay(&self, attempt: u32) -> Duration {
+        let backoff = self.backoff(attempt);
+
+        backoff.mul_f64(1.0 - self.jitter * random())
+    }
+}
+
+/// Uniformly distributed number between 0 and 1.
+///
+/// Jitter only has to keep clients from retrying in lockstep, which does not
+/// warrant a dependency on an RNG crate. The std keys each thread's first
+/// `RandomState` from the OS random source and increments the key for every
+/// later one, so the SipHash of no input differs between processes and
+/// between calls, which is all the jitter needs.
+fn random() -> f64 {
+    RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy::builder().build()
+    }
+}
+
+/// Builder interface for `RetryPolicy`
+pub struct RetryPolicyBuilder {
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: f64,
+    jitter: f64,
+    on_retry: Option<RetryCallback>,
+}
+
+impl Default for RetryPolicyBuilder {
+    fn default() -> RetryPolicyBuilder {
+        RetryPolicyBuilder::new()
+    }
+}
+
+impl RetryPolicyBuilder {
+    pub fn new() -> RetryPolicyBuilder {
+        RetryPolicyBuilder {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+            multiplier: 2.0,
+            jitter: 0.5,
+            on_retry: None,
+        }
+    }
+
+    /// Retries after the first attempt. defaults to 3
+    pub fn max_retries(&mut self, retries: u32) -> &mut RetryPolicyBuilder {
+        self.max_retries = retries;
+        self
+    }
+
+    /// Backoff before the first retry. defaults to 100 milliseconds
+    pub fn initial_backoff(&mut self, backoff: Duration) -> &mut RetryPolicyBuilder {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// Upper bound of the backoff. defaults to 5 seconds
+    pub fn max_backoff(&mut self, backoff: Duration) -> &mut RetryPolicyBuilder {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// Factor the backoff grows by with every retry. defaults to 2
+    pub fn multiplier(&mut self, multiplier: f64) -> &mut RetryPolicyBuilder {
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    /// Share of the backoff which is randomly cut off, between 0 and 1.
+    /// defaults to 0.5
+    pub fn jitter(&mut self, jitter: f64) -> &mut RetryPolicyBuilder {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Called before every retry, in addition to the `warn!` log line
+    pub fn on_retry<F>(&mut self, callback: F) -> &mut RetryPolicyBuilder
+    where
+        F: Fn(&RetryEvent) + Send + Sync + 'static,
+    {
+        self.on_retry = Some(Arc::new(callback));
+        self
+    }
+
+    pub fn build(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_retries: self.max_retries,
+            initial_backoff: self.initial_backoff,
+            max_backoff: self.max_backoff,
+            multiplier: self.multiplier,
+            jitter: self.jitter,
+            on_retry: self.on_retry.clone(),
+        }
+    }
+}
+
+/// Transport retrying the idempotent requests of the wrapped one
+pub struct Retry {
+    inner: Arc<dyn InteractApi>,
+    policy: RetryPolicy,
+}
+
+impl Retry {
+    pub fn new(inner: Arc<dyn InteractApi>, policy: RetryPolicy) -> Retry {
+        Retry {
+            inner,
+            policy,
+        }
+    }
+}
+
+#[async_trait]
+impl InteractApi for Retry {
+    async fn request(&self, opts: RequestArgs, method: Method) -> Result<Response<Body>> {
+        if !is_idempotent(&method, &opts) {
+            return self.inner.request(opts, method).await;
+        }
+
+        let mut attempt = 0;
+        loop {
+            let retries_left = attempt < self.policy.max_retries;
+            let error = match self.inner.request(resend(&opts), method.clone()).await {
+                Err(error) if retries_left && is_transient(&error) => error,
+                Ok(response) if retries_left && is_transient_status(response.status()) =>
+                    match check_status(opts.path.clone(), Ok(response)).await {
+                        Err(error) => error,
+                        ok => return ok,
+                    },
+                result => return result,
+            };
+
+            attempt += 1;
+            let delay = self.policy.delay(attempt);
+            warn!("Retrying {} {} in {:?} ({}/{}): {}", method, opts.path, delay, attempt,
+                  self.policy.max_retries, error);
+            if let Some(ref callback) = self.policy.on_retry {
+                callback(&RetryEvent {
+                    method: &method,
+                    path: &opts.path,
+                    attempt,
+                    delay,
+                    error: &error,
+                });
+            }
+            drop(error);
+
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    fn api_version(&self) -> Option<ApiVersion> {
+        self.inner.api_version()
+    }
+
+    fn set_api_version(&self, version: Option<ApiVersion>) {
+        self.inner.set_api_version(version)
+    }
+}
+
+/// Only requests which can be sent again unchanged are retried
+fn is_idempotent(method: &Method, opts: &RequestArgs) -> bool {
+    (*method == Method::GET || *method == Method::HEAD) && opts.body.is_end_stream()
+}
+
+/// Copy of a request without a body
+fn resend(opts: &RequestArgs) -> RequestArgs {
+    RequestArgs {
+        path: opts.path.clone(),
+        query: opts.query.clone(),
+        body: Body::empty(),
+        header: opts.header.clone(),
+        timeouts: opts.timeouts,
+    }
+}
+
+/// Whether the daemon refused or dropped the connection or did not answer in
+/// time. TLS, DNS and certificate failures won't go away by retrying
+fn is_transient(error: &Error) -> bool {
+    match *error.kind() {
+        ErrorKind::Timeout(_) => true,
+        ErrorKind::Io(ref err) => is_transient_io(err),
+        ErrorKind::HyperClient(ref err) => has_transient_io(err),
+        ErrorKind::Hyper(ref err) => err.is_incomplete_message() || has_transient_io(err),
+        _ => false,
+    }
+}
+
+/// Whether the daemon failed or asked to slow down
+fn is_transient_status(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+fn has_transient_io(err: &(dyn StdError + 'static)) -> bool {
+    let mut source = err.source();
+    while let Some(err) = source {
+        if let Some(err) = err.downcast_ref::<io::Error>() {
+            return is_transient_io(err);
+        }
+        source = err.source();
+    }
+
+    false
+}
+
+fn is_transient_io(err: &io::Error) -> bool {
+    matches!(err.kind(), io::ErrorKind::ConnectionRefused
+        | io::ErrorKind::ConnectionReset
+        | io::ErrorKind::ConnectionAborted
+        | io::ErrorKind::TimedOut)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Retry, RetryPolicy};
+    use crate::communicate::util::RequestArgs;
+    use crate::docker::new_docker_with_interact;
+    use crate::test_util::Stub;
+    use crate::transport::body::Body;
+    use crate::transport::interact::InteractApi;
+    use crate::errors::{Error, ErrorKind, Result};
+    use crate::build::ContainerOptions;
+    use futures::future;
+    use hyper::{Method, Response, StatusCode};
+    use std::io;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::runtime::Runtime;
+
+    /// Fails the first `failures` requests with `failure`, answers the rest
+    /// with 200. `requests` counts them all
+    fn restarting(failures: usize, failure: fn() -> Result<Response<Body>>,
+                  requests: Arc<AtomicUsize>) -> Arc<dyn InteractApi> {
+        Arc::new(Stub(move |_: RequestArgs, _: Method| {
+            future::ready(if requests.fetch_add(1, Ordering::SeqCst) < failures {
+                failure()
+            } else {
+                Ok(Response::new(Body::from("OK")))
+            })
+        }))
+    }
+
+    #[test]
+    fn retries_idempotent_requests_only() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let daemon = restarting(2, refused, requests.clone());
+        let retries = Arc::new(AtomicUsize::new(0));
+        let counted = retries.clone();
+        let policy = RetryPolicy::builder()
+            .initial_backoff(Duration::from_millis(1))
+            .on_retry(move |_| { counted.fetch_add(1, Ordering::SeqCst); })
+            .build();
+        let docker = new_docker_with_interact(Arc::new(Retry::new(daemon, policy)));
+        let runtime = Runtime::new().expect("Runtime");
+
+        assert_eq!(StatusCode::OK, runtime.block_on(docker.ping()).expect("Ping"));
+        assert_eq!(2, retries.load(Ordering::SeqCst));
+        assert_eq!(3, requests.load(Ordering::SeqCst));
+
+        requests.store(0, Ordering::SeqCst);
+        let opts = ContainerOptions::builder("alpine").build();
+        assert!(runtime.block_on(docker.containers().create(&opts)).is_err());
+        assert_eq!(1, requests.load(Ordering::SeqCst));
+    }
+
+    fn refused() -> Result<Response<Body>> {
+        Err(Error::from(io::Error::from(io::ErrorKind::ConnectionRefused)))
+    }
+
+    fn status(status: StatusCode) -> Result<Response<Body>> {
+        Response::builder().status(status).body(Body::empty()).map_err(Error::from)
+    }
+
+    /// Requests sent for a ping whose first attempt fails with `failure`
+    fn attempts(failure: fn() -> Result<Response<Body>>) -> usize {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let daemon = restarting(1, failure, requests.clone());
+        let policy = RetryPolicy::builder().initial_backoff(Duration::from_millis(1)).build();
+        let docker = new_docker_with_interact(Arc::new(Retry::new(daemon, policy)));
+        let _ = Runtime::new().expect("Runtime").block_on(docker.ping());
+
+        requests.load(Ordering::SeqCst)
+    }
+
+    #[test]
+    fn retries_transient_failures_only() {
+        assert_eq!(2, attempts(|| Err(io::Error::from(io::ErrorKind::ConnectionReset).into())));
+        assert_eq!(2, attempts(|| Err(ErrorKind::Timeout("/_ping".to_owned()).into())));
+        assert_eq!(2, attempts(|| status(StatusCode::SERVICE_UNAVAILABLE)));
+        assert_eq!(2, attempts(|| status(StatusCode::TOO_MANY_REQUESTS)));
+        assert_eq!(1, attempts(|| Err(io::Error::from(io::ErrorKind::NotFound).into())));
+        assert_eq!(1, attempts(|| Err(ErrorKind::InvalidTlsConfig("bad".to_owned()).into())));
+        assert_eq!(1, attempts(|| status(StatusCode::NOT_FOUND)));
+    }
+
+    #[test]
+    fn backoff_grows_up_to_the_limit() {
+        let policy = RetryPolicy::builder()
+            .initial_backoff(Duration::from_millis(100))
+            .max_backoff(Duration::from_millis(300))
+            .build();
+
+        assert_eq!(Duration::from_millis(100), policy.backoff(1));
+        assert_eq!(Duration::from_millis(200), policy.backoff(2));
+        assert_eq!(Duration::from_millis(300), policy.backoff(3));
+        assert!(policy.delay(2) >= Duration::from_millis(100));
+        assert!(policy.delay(2) <= Duration::from_millis(200));
+    }
+}
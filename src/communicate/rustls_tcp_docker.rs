@@ -0,0 +1,372 @@
+#![cfg(all(feature = "rustls", not(feature = "ssl")))]
+
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::WebPkiServerVerifier;
+use rustls::crypto::{self, CryptoProvider};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use rustls::{CertificateError, ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme};
+use tokio_rustls::TlsConnector;
+
+use hyper::Uri;
+use hyper_util::client::legacy::Client;
+use hyper_util::client::legacy::connect::{Connected, Connection, HttpConnector};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+use tower_service::Service;
+use crate::errors::{ErrorKind, Result};
+use crate::Error;
+use crate::communicate::docker::Docker;
+use crate::communicate::docker::DockerApi;
+use crate::communicate::tls::TlsConfig;
+use crate::transport::interact::Interact;
+use crate::transport::timeout::TimeoutConnector;
+use crate::communicate::timeout::Timeouts;
+use std::convert::TryFrom;
+use std::future::Future;
+use std::io::{self, BufReader};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+pub type TcpRustlsDocker = Docker<RustlsConnector>;
+
+impl Docker<RustlsConnector> {
+    pub(crate) fn open(host: Uri, tls: TlsConfig, timeouts: Timeouts) -> Result<Box<dyn DockerApi>> {
+        let mut http = HttpConnector::new();
+        http.enforce_http(false);
+
+        let connector = RustlsConnector {
+            http,
+            tls: TlsConnector::from(Arc::new(client_config(&tls)?)),
+        };
+
+        let connector = TimeoutConnector::new(connector, timeouts.connect());
+        let client = Client::builder(TokioExecutor::new()).build(connector);
+
+        let docker = Self::new_inner(Arc::new(Interact::new(client, host, timeouts)));
+        Ok(Box::new(docker))
+    }
+}
+
+fn provider() -> Arc<CryptoProvider> {
+    Arc::new(crypto::ring::default_provider())
+}
+
+/// Loads the CA and client identity of the configuration into a rustls
+/// client configuration
+fn client_config(tls: &TlsConfig) -> Result<ClientConfig> {
+    let mut roots = RootCertStore::empty();
+
+    match tls.ca()? {
+        Some(ca) => {
+            let certs = rustls_pemfile::certs(&mut BufReader::new(&ca[..]))
+                .collect::<io::Result<Vec<_>>>()
+                .map_err(|_| invalid("Cannot parse CA PEM"))?;
+            let (added, _) = roots.add_parsable_certificates(certs);
+            if added == 0 {
+                return Err(invalid("No certificate found in CA PEM"));
+            }
+        }
+        None => roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned()),
+    }
+    let roots = Arc::new(roots);
+
+    let builder = ClientConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| invalid(&e.to_string()))?;
+
+    let builder = if tls.skip_verify() {
+        builder.dangerous().with_custom_certificate_verifier(Arc::new(NoVerification))
+    } else if !tls.verify_hostname() {
+        let verifier = WebPkiServerVerifier::builder_with_provider(roots, provider())
+            .build()
+            .map_err(|e| invalid(&e.to_string()))?;
+        builder.dangerous().with_custom_certificate_verifier(Arc::new(NoHostnameVerification(verifier)))
+    } else {
+        builder.with_root_certificates(roots)
+    };
+
+    match tls.identity()? {
+        Some(identity) => {
+            let chain = rustls_pemfile::certs(&mut BufReader::new(&identity.cert[..]))
+                .collect::<io::Result<Vec<_>>>()
+                .map_err(|_| invalid("Cannot parse client certificate PEM"))?;
+            if chain.is_empty() {
+                return Err(invalid("No certificate found in client certificate PEM"));
+            }
+
+            let key = private_key(&identity.key)?;
+            builder.with_client_auth_cert(chain, key)
+                .map_err(|_| invalid("Unsupported client key type"))
+        }
+        None => Ok(builder.with_no_client_auth()),
+    }
+}
+
+/// Reads a PKCS#8, PKCS#1 (`BEGIN RSA PRIVATE KEY`, as generated by docker)
+/// or SEC1 private key
+fn private_key(pem: &[u8]) -> Result<PrivateKeyDer<'static>> {
+    rustls_pemfile::private_key(&mut BufReader::new(pem))
+        .map_err(|_| invalid("Cannot parse client key PEM"))?
+        .ok_or_else(|| invalid("No private key found in client key PEM"))
+}
+
+fn invalid(msg: &str) -> Error {
+    ErrorKind::InvalidTlsConfig(msg.to_owned()).into()
+}
+
+/// Accepts any daemon certificate, the handshake signatures are still checked
+#[derive(Debug)]
+struct NoVerification;
+
+impl ServerCertVerifier for NoVerification {
+    fn verify_server_cert(&self,
+                          _end_entity: &CertificateDer,
+                          _intermediates: &[CertificateDer],
+                          _server_name: &ServerName,
+                          _ocsp_response: &[u8],
+                          _now: UnixTime) -> ::std::result::Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(&self, message: &[u8], cert: &CertificateDer, dss: &DigitallySignedStruct)
+        -> ::std::result::Result<HandshakeSignatureValid, rustls::Error>
+    {
+        crypto::verify_tls12_signature(message, cert, dss,
+                                       &provider().signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(&self, message: &[u8], cert: &CertificateDer, dss: &DigitallySignedStruct)
+        -> ::std::result::Result<HandshakeSignatureValid, rustls::Error>
+    {
+        crypto::verify_tls13_signature(message, cert, dss,
+                                       &provider().signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        provider().signature_verification_algorithms.supported_schemes()
+    }
+}
+
+/// Verifies the daemon certificate chain, but not the name it is issued for
+#[derive(Debug)]
+struct NoHostnameVerification(Arc<WebPkiServerVerifier>);
+
+impl ServerCertVerifier for NoHostnameVerification {
+    fn verify_server_cert(&self,
+                          end_entity: &CertificateDer,
+                          intermediates: &[CertificateDer],
+                          server_name: &ServerName,
+                          ocsp_response: &[u8],
+                          now: UnixTime) -> ::std::result::Result<ServerCertVerified, rustls::Error> {
+        match self.0.verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now) {
+            Err(rustls::Error::InvalidCertificate(CertificateError::NotValidForName))
+            | Err(rustls::Error::InvalidCertificate(CertificateError::NotValidForNameContext { .. })) =>
+                Ok(ServerCertVerified::assertion()),
+            other => other,
+        }
+    }
+
+    fn verify_tls12_signature(&self, message: &[u8], cert: &CertificateDer, dss: &DigitallySignedStruct)
+        -> ::std::result::Result<HandshakeSignatureValid, rustls::Error>
+    {
+        self.0.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(&self, message: &[u8], cert: &CertificateDer, dss: &DigitallySignedStruct)
+        -> ::std::result::Result<HandshakeSignatureValid, rustls::Error>
+    {
+        self.0.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.supported_verify_schemes()
+    }
+}
+
+/// Connector establishing a rustls session on top of a plain TCP connection
+#[derive(Clone)]
+pub struct RustlsConnector {
+    http: HttpConnector,
+    tls: TlsConnector,
+}
+
+impl Service<Uri> for RustlsConnector {
+    type Response = TokioIo<TlsStream>;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output=io::Result<TokioIo<TlsStream>>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, dst: Uri) -> Self::Future {
+        let mut http = self.http.clone();
+        let tls = self.tls.clone();
+
+        Box::pin(async move {
+            if dst.scheme_str() != Some("https") {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                    format!("Unsupported scheme {:?}", dst.scheme_str())));
+            }
+
+            let host = dst.host().unwrap_or_default()
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .to_owned();
+            let name = ServerName::try_from(host)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+            let tcp = http.call(dst).await
+                .map_err(io::Error::other)?
+                .into_inner();
+            let stream = tls.connect(name, tcp).await?;
+
+            Ok(TokioIo::new(TlsStream(stream)))
+        })
+    }
+}
+
+/// Client side of a rustls session over a TCP connection
+pub struct TlsStream(tokio_rustls::client::TlsStream<TcpStream>);
+
+impl AsyncRead for TlsStream {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context, buf: &mut ReadBuf)
+        -> Poll<io::Result<()>>
+    {
+        Pin::new(&mut self.0).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for TlsStream {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context, buf: &[u8])
+        -> Poll<io::Result<usize>>
+    {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_shutdown(cx)
+    }
+}
+
+impl Connection for TlsStream {
+    fn connected(&self) -> Connected {
+        self.0.get_ref().0.connected()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustls::server::WebPkiClientVerifier;
+    use rustls::{RootCertStore, ServerConfig, ServerConnection, Stream};
+    use super::{provider, TcpRustlsDocker};
+    use crate::communicate::tls::TlsConfig;
+    use crate::communicate::timeout::Timeouts;
+    use hyper::StatusCode;
+    use tokio::runtime::Runtime;
+    use std::io::{BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::Arc;
+    use std::thread;
+
+    const CA: &[u8] = include_bytes!("../../tests/fixtures/tls/ca.pem");
+    const SERVER_CERT: &[u8] = include_bytes!("../../tests/fixtures/tls/server.pem");
+    const SERVER_KEY: &[u8] = include_bytes!("../../tests/fixtures/tls/server-key.pem");
+    const CLIENT_CERT: &[u8] = include_bytes!("../../tests/fixtures/tls/cert.pem");
+    const CLIENT_KEY: &[u8] = include_bytes!("../../tests/fixtures/tls/key.pem");
+
+    /// Stand-in daemon answering a single request over TLS, requiring a
+    /// client certificate signed by the test CA. Returns its port.
+    fn serve_once() -> u16 {
+        let mut roots = RootCertStore::empty();
+        for cert in rustls_pemfile::certs(&mut BufReader::new(CA)) {
+            roots.add(cert.expect("CA")).expect("CA");
+        }
+        let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider())
+            .build()
+            .expect("Client verifier");
+        let certs = rustls_pemfile::certs(&mut BufReader::new(SERVER_CERT))
+            .collect::<Result<Vec<_>, _>>()
+            .expect("Server cert");
+        let key = rustls_pemfile::private_key(&mut BufReader::new(SERVER_KEY))
+            .expect("Server key")
+            .expect("Server key");
+        let config = ServerConfig::builder_with_provider(provider())
+            .with_safe_default_protocol_versions()
+            .expect("Protocol versions")
+            .with_client_cert_verifier(verifier)
+            .with_single_cert(certs, key)
+            .expect("Server identity");
+        let config = Arc::new(config);
+
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Bind");
+        let port = listener.local_addr().expect("Local address").port();
+
+        thread::spawn(move || {
+            let (mut tcp, _) = listener.accept().expect("Accept");
+            let mut session = ServerConnection::new(config).expect("Server session");
+            let mut tls = Stream::new(&mut session, &mut tcp);
+
+            let mut request = Vec::new();
+            let mut buf = [0u8; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                match tls.read(&mut buf) {
+                    Ok(0) | Err(_) => return,
+                    Ok(n) => request.extend_from_slice(&buf[..n]),
+                }
+            }
+
+            let _ = tls.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nOK");
+            let _ = tls.flush();
+        });
+
+        port
+    }
+
+    fn ping(tls: TlsConfig) -> crate::errors::Result<StatusCode> {
+        let port = serve_once();
+        let host = format!("https://localhost:{}", port).parse().expect("Uri");
+        let docker = TcpRustlsDocker::open(host, tls, Timeouts::default())?;
+
+        Runtime::new().expect("Runtime").block_on(docker.ping())
+    }
+
+    #[test]
+    fn ping_with_client_certificate() {
+        let tls = TlsConfig::builder()
+            .ca_pem(CA)
+            .cert_pem(CLIENT_CERT)
+            .key_pem(CLIENT_KEY)
+            .build();
+
+        assert_eq!(StatusCode::OK, ping(tls).expect("Ping over TLS"));
+    }
+
+    #[test]
+    fn missing_client_certificate_is_rejected() {
+        let tls = TlsConfig::builder()
+            .ca_pem(CA)
+            .build();
+
+        assert!(ping(tls).is_err());
+    }
+
+    #[test]
+    fn unknown_daemon_certificate_is_rejected() {
+        let tls = TlsConfig::builder()
+            .cert_pem(CLIENT_CERT)
+            .key_pem(CLIENT_KEY)
+            .build();
+
+        assert!(ping(tls).is_err());
+        assert!(TcpRustlsDocker::open("https://localhost".parse().expect("Uri"),
+            TlsConfig::builder().ca_pem("not a certificate").build(),
+            Timeouts::default()).is_err());
+    }
+}
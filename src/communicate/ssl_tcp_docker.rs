@@ -1,62 +1,86 @@
 #![cfg(feature = "ssl")]
 
-extern crate hyper_openssl;
-extern crate openssl;
-use self::hyper_openssl::HttpsConnector;
-use self::openssl::ssl::SslConnectorBuilder;
-use self::openssl::x509::X509Builder;
-use self::openssl::ssl::SslMethod;
+use hyper_openssl::client::legacy::HttpsConnector;
+use openssl::pkey::PKey;
+use openssl::ssl::{SslConnector, SslConnectorBuilder, SslMethod, SslVerifyMode};
+use openssl::x509::X509;
 
 use hyper::Uri;
-use errors::Result;
-use Error;
-use std::path::Path;
-use std::env;
-use hyper::Client;
-use communicate::docker::Docker;
+use crate::errors::{ErrorKind, Result};
+use crate::Error;
+use hyper_util::client::legacy::Client;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::rt::TokioExecutor;
+use crate::communicate::docker::Docker;
 use std::sync::Arc;
-use hyper::client::HttpConnector;
-use hyper::Body;
-use transport::interact::Interact;
-use communicate::docker::DockerApi;
-use self::openssl::ssl::SslContextBuilder;
+use crate::transport::interact::Interact;
+use crate::transport::timeout::TimeoutConnector;
+use crate::communicate::timeout::Timeouts;
+use crate::communicate::docker::DockerApi;
+use crate::communicate::tls::TlsConfig;
 
 pub type TcpSSLDocker = Docker<HttpsConnector<HttpConnector>>;
 
-const THREADS: usize = 1;
-
 impl Docker<HttpsConnector<HttpConnector>> {
-    pub(crate) fn new(host: Uri) -> Result<Box<DockerApi>> {
-        let certs = env::var("DOCKER_CERT_PATH").ok().expect("No SSL cert");
-
-        let cert = &format!("{}/cert.pem", certs);
-        let key = &format!("{}/key.pem", certs);
-
-        // https://github.com/hyperium/hyper/blob/master/src/net.rs#L427-L428
-        let context = SslContextBuilder::new(SslMethod::tls())
-            .expect("Error during ssl connection preparation");
-        let mut connector = SslConnectorBuilder{context}.build();
-
-        connector.set_cipher_list("DEFAULT")?;
-        connector.set_certificate_file(&Path::new(cert), X509Builder::new())?;
-        connector.set_private_key_file(&Path::new(key), X509Builder::new())?;
-
-        if let Some(_) = env::var("DOCKER_TLS_VERIFY").ok() {
-            let ca = &format!("{}/ca.pem", certs);
-            connector.set_ca_file(&Path::new(ca))?;
-        }
+    pub(crate) fn open(host: Uri, tls: TlsConfig, timeouts: Timeouts) -> Result<Box<dyn DockerApi>> {
+        let connector = ssl_connector(&tls)?;
 
-        let mut http = HttpConnector::new(THREADS);
+        let mut http = HttpConnector::new();
         http.enforce_http(false);
 
-        let connector = HttpsConnector::<HttpConnector>::with_connector(http, connector)
+        let mut connector = HttpsConnector::<HttpConnector>::with_connector(http, connector)
             .map_err(Error::from)?;
 
-        let client = Client::builder().build(connector);
+        if !tls.verify_hostname() {
+            connector.set_callback(|config, _| {
+                config.set_verify_hostname(false);
+                Ok(())
+            });
+        }
 
+        let connector = TimeoutConnector::new(connector, timeouts.connect());
+        let client = Client::builder(TokioExecutor::new()).build(connector);
 
-        let docker = Self::new_inner(Arc::new(Interact::new(client, host)));
+        let docker = Self::new_inner(Arc::new(Interact::new(client, host, timeouts)));
         Ok(Box::new(docker))
     }
 }
 
+/// Loads the CA and client identity of the configuration into an openssl
+/// connector
+fn ssl_connector(tls: &TlsConfig) -> Result<SslConnectorBuilder> {
+    let mut connector = SslConnector::builder(SslMethod::tls())?;
+    connector.set_cipher_list("DEFAULT")?;
+
+    if let Some(ca) = tls.ca()? {
+        let certs = X509::stack_from_pem(&ca)?;
+        if certs.is_empty() {
+            return Err(ErrorKind::InvalidTlsConfig(
+                "No certificate found in CA PEM".to_owned()).into());
+        }
+        for cert in certs {
+            connector.cert_store_mut().add_cert(cert)?;
+        }
+    }
+
+    if let Some(identity) = tls.identity()? {
+        let mut chain = X509::stack_from_pem(&identity.cert)?.into_iter();
+        let cert = chain.next()
+            .ok_or_else(|| Error::from(ErrorKind::InvalidTlsConfig(
+                "No certificate found in client certificate PEM".to_owned())))?;
+
+        connector.set_certificate(&cert)?;
+        for intermediate in chain {
+            connector.add_extra_chain_cert(intermediate)?;
+        }
+        let key = PKey::private_key_from_pem(&identity.key)?;
+        connector.set_private_key(&key)?;
+        connector.check_private_key()?;
+    }
+
+    if tls.skip_verify() {
+        connector.set_verify(SslVerifyMode::NONE);
+    }
+
+    Ok(connector)
+}
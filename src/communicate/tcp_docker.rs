@@ -1,20 +1,25 @@
-use hyper::client::HttpConnector;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
 use hyper::Uri;
-use hyper::Client;
 
-use errors::Result;
-use communicate::docker::Docker;
-use communicate::docker::DockerApi;
-use transport::interact::Interact;
+use crate::errors::Result;
+use crate::communicate::docker::Docker;
+use crate::communicate::docker::DockerApi;
+use crate::transport::interact::Interact;
+use crate::transport::timeout::TimeoutConnector;
+use crate::communicate::timeout::Timeouts;
 use std::sync::Arc;
 
 pub(super) type TcpDocker = Docker<HttpConnector>;
 
 impl Docker<HttpConnector> {
-    pub(crate) fn new(host: Uri) -> Result<Box<DockerApi>> {
-        let interact = Interact::new(Client::new(), host);
+    pub(crate) fn open(host: Uri, timeouts: Timeouts) -> Result<Box<dyn DockerApi>> {
+        let connector = TimeoutConnector::new(HttpConnector::new(), timeouts.connect());
+        let client = Client::builder(TokioExecutor::new()).build(connector);
+        let interact = Interact::new(client, host, timeouts);
         let docker = Self::new_inner(Arc::new(interact));
 
         Ok(Box::new(docker))
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,176 @@
+//! Timeouts of the requests sent to a docker daemon
+
+use std::time::Duration;
+
+/// Time allowed for establishing a connection to the daemon
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Time allowed from sending the request body until the response headers
+/// arrive
+pub const DEFAULT_RESPONSE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Time allowed between two chunks of a response body
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Client wide timeouts, `None` waits forever
+///
+/// The response timeout starts once the request body is sent, so uploads of
+/// any size are not cut short. For requests without a body it covers
+/// connecting as well. Long-lived streams such
+/// as events, followed logs, stats and exec output are not subject to the
+/// idle timeout unless their options ask for one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Timeouts {
+    connect: Option<Duration>,
+    response: Option<Duration>,
+    idle: Option<Duration>,
+}
+
+impl Timeouts {
+    /// return a new instance of a builder for the timeouts
+    pub fn builder() -> TimeoutsBuilder {
+        TimeoutsBuilder::new()
+    }
+
+    /// Timeouts disabling every deadline, the behaviour of earlier releases
+    pub fn none() -> Timeouts {
+        Timeouts {
+            connect: None,
+            response: None,
+            idle: None,
+        }
+    }
+
+    pub fn connect(&self) -> Option<Duration> {
+        self.connect
+    }
+
+    pub fn response(&self) -> Option<Duration> {
+        self.response
+    }
+
+    pub fn idle(&self) -> Option<Duration> {
+        self.idle
+    }
+}
+
+impl Default for Timeouts {
+    fn default() -> Timeouts {
+        Timeouts::builder().build()
+    }
+}
+
+/// Builder interface for `Timeouts`
+pub struct TimeoutsBuilder {
+    connect: Option<Duration>,
+    response: Option<Duration>,
+    idle: Option<Duration>,
+}
+
+impl Default for TimeoutsBuilder {
+    fn default() -> TimeoutsBuilder {
+        TimeoutsBuilder::new()
+    }
+}
+
+impl TimeoutsBuilder {
+    pub fn new() -> TimeoutsBuilder {
+        TimeoutsBuilder {
+            connect: Some(DEFAULT_CONNECT_TIMEOUT),
+            response: Some(DEFAULT_RESPONSE_TIMEOUT),
+            idle: Some(DEFAULT_IDLE_TIMEOUT),
+        }
+    }
+
+    /// Time allowed for establishing a connection. defaults to 30 seconds
+    pub fn connect(&mut self, timeout: Option<Duration>) -> &mut TimeoutsBuilder {
+        self.connect = timeout;
+        self
+    }
+
+    /// Time allowed after sending the request body until the response headers
+    /// arrive. defaults to 2 minutes
+    pub fn response(&mut self, timeout: Option<Duration>) -> &mut TimeoutsBuilder {
+        self.response = timeout;
+        self
+    }
+
+    /// Time allowed between two chunks of a response body. defaults to 2 minutes
+    pub fn idle(&mut self, timeout: Option<Duration>) -> &mut TimeoutsBuilder {
+        self.idle = timeout;
+        self
+    }
+
+    pub fn build(&self) -> Timeouts {
+        Timeouts {
+            connect: self.connect,
+            response: self.response,
+            idle: self.idle,
+        }
+    }
+}
+
+/// Per request override of one of the client's timeouts
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Timeout {
+    /// Use the timeout the client was configured with
+    #[default]
+    Default,
+    /// Wait forever
+    Never,
+    /// Give up after the given duration
+    After(Duration),
+}
+
+impl From<Duration> for Timeout {
+    fn from(duration: Duration) -> Timeout {
+        Timeout::After(duration)
+    }
+}
+
+impl Timeout {
+    /// This timeout, or `other` if this one defers to the client
+    pub fn or(self, other: Timeout) -> Timeout {
+        match self {
+            Timeout::Default => other,
+            timeout => timeout,
+        }
+    }
+
+    /// The effective deadline given the client's configured one
+    pub fn resolve(self, client: Option<Duration>) -> Option<Duration> {
+        match self {
+            Timeout::Default => client,
+            Timeout::Never => None,
+            Timeout::After(duration) => Some(duration),
+        }
+    }
+}
+
+/// Timeouts of a single request, overriding the ones of the client
+///
+/// The `timeout` and `idle_timeout` setters of the option builders fill in
+/// these two fields. Each takes a `Duration`, `Timeout::Never` to wait
+/// forever, or `Timeout::Default` to keep the client's `Timeouts`. Streams
+/// that live as long as their container, such as events, followed logs,
+/// stats, exec output and waits, never idle out unless an idle timeout is
+/// given.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RequestTimeouts {
+    /// Time allowed after sending the request body until the response
+    /// headers arrive
+    pub response: Timeout,
+    /// Time allowed between two chunks of the response body
+    pub idle: Timeout,
+}
+
+impl RequestTimeouts {
+    /// Timeouts of a long-lived stream, which never idles out unless asked to
+    pub(crate) fn streaming(self) -> RequestTimeouts {
+        RequestTimeouts {
+            response: self.response,
+            idle: self.idle.or(Timeout::Never),
+        }
+    }
+}
+
@@ -1,29 +1,29 @@
 #![cfg(target_os = "linux")]
 
-extern crate tokio_uds;
-extern crate unix_socket;
-use self::tokio_uds::UnixStream;
-
+use std::future::Future;
+use std::io;
 use std::path::PathBuf;
-use hyper::client::connect::Connect;
-use tokio::io;
-use tokio::prelude::Future;
-use hyper::client::connect::Connected;
-use hyper::client::connect::Destination;
-use tokio::prelude::future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use tokio::net::UnixStream;
+use tower_service::Service;
 use hyper::Uri;
-use hyper::Client;
 use http::uri::Authority;
 use http::uri::Scheme;
 use std::str::FromStr;
 
 
-use transport::interact::Interact;
-use docker::Docker;
-use errors::Result;
-use std::sync::Arc;
-use communicate::docker::DockerApi;
+use crate::transport::interact::Interact;
+use crate::transport::timeout::TimeoutConnector;
+use crate::communicate::timeout::Timeouts;
+use crate::docker::Docker;
+use crate::errors::Result;
+use crate::communicate::docker::DockerApi;
 
+#[derive(Clone)]
 pub struct UnixConnector
 {
     path: PathBuf
@@ -38,49 +38,53 @@ impl UnixConnector {
     }
 }
 
-impl Connect for UnixConnector {
-    type Transport = UnixStream;
+impl Service<Uri> for UnixConnector {
+    type Response = TokioIo<UnixStream>;
     type Error = io::Error;
-    type Future = Box<Future<Item=(UnixStream, Connected), Error=io::Error> + Send>;
+    type Future = Pin<Box<dyn Future<Output=io::Result<TokioIo<UnixStream>>> + Send>>;
 
-    fn connect(&self, dst: Destination) -> Self::Future {
-        if dst.scheme() != "http" {
-            return Box::new(future::err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                format!("Invalid uri {:?}", dst),
-            )));
-        }
+    fn poll_ready(&mut self, _cx: &mut Context) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
 
-        let connected = future::ok(Connected::new());
-        let unix = UnixStream::connect(&self.path.as_path());
+    fn call(&mut self, dst: Uri) -> Self::Future {
+        let path = self.path.clone();
 
-        let join = unix.join(connected);
+        Box::pin(async move {
+            if dst.scheme_str() != Some("http") {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Invalid uri {:?}", dst),
+                ));
+            }
 
-        Box::new(join)
+            UnixStream::connect(path).await.map(TokioIo::new)
+        })
     }
 }
 
 pub(crate) type UnixDocker = Docker<UnixConnector>;
 
 impl Docker<UnixConnector> {
-    pub(crate) fn new(host: Uri) -> Result<Box<DockerApi>>
+    pub(crate) fn open(host: Uri, timeouts: Timeouts) -> Result<Box<dyn DockerApi>>
     {
         let path = format!("/{}{}",
-                           host.authority_part().map(|a| a.as_str()).unwrap_or_default(),
+                           host.authority().map(|a| a.as_str()).unwrap_or_default(),
                            host.path()
         );
         let mut parts = host.into_parts();
-        parts.authority = Some(Authority::from_str("v1.37")
+        parts.authority = Some(Authority::from_str("localhost")
             .expect("Constant authority parsing error"));
         parts.scheme = Some(Scheme::from_str("http")
             .expect("Constant scheme parsing error"));
 
         let host = Uri::from_parts(parts)?;
+        let connector = TimeoutConnector::new(UnixConnector::new(PathBuf::from(path)),
+                                              timeouts.connect());
         let interact = Interact::new(
-            Client::builder().build(
-                UnixConnector::new(PathBuf::from(path))
-            ),
-            host
+            Client::builder(TokioExecutor::new()).build(connector),
+            host,
+            timeouts
         );
 
         let docker = Self::new_inner(Arc::new(interact));
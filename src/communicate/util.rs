@@ -1,20 +1,26 @@
-pub use build::*;
+pub use crate::build::*;
 
 pub use std::marker::Sized;
-pub use errors::Error;
+pub use crate::errors::Error;
 /// Represents the result of all docker operations
-pub use errors::Result;
+pub use crate::errors::Result;
 
-use hyper::Body;
+use crate::transport::body::Body;
 use std::str;
 use url::form_urlencoded;
-use hyper::HeaderMap;
+use http::HeaderMap;
 use http::header::HeaderValue;
 use http::header::HeaderName;
+use crate::communicate::timeout::RequestTimeouts;
 
 
-pub(crate) const URI_ENV: &'static str = "SHIPLIFT_URI";
-pub(crate) const DEFAULT_URI: &'static str = "unix://var/run/docker.sock";
+pub(crate) const URI_ENV: &str = "SHIPLIFT_URI";
+pub(crate) const DEFAULT_URI: &str = "unix://var/run/docker.sock";
+
+/// Header carrying the base64url encoded `RegistryAuth` of pulls and pushes
+pub(crate) const X_REGISTRY_AUTH: HeaderName = HeaderName::from_static("x-registry-auth");
+/// Header carrying the base64url encoded registry credentials of builds
+pub(crate) const X_REGISTRY_CONFIG: HeaderName = HeaderName::from_static("x-registry-config");
 
 pub(crate) fn build_simple_query<A>(name: &str, value: Option<A>) -> Option<String>
     where
@@ -29,29 +35,17 @@ pub(crate) fn build_simple_query<A>(name: &str, value: Option<A>) -> Option<Stri
     query
 }
 
-pub(crate) trait AsSlice {
-    fn as_slice(&self) -> Option<&str>;
-}
-
-impl AsSlice for Option<String>
-{
-    fn as_slice(&self) -> Option<&str> {
-        match self {
-            Some(ref x) => Some(x),
-            None => None,
-        }
-    }
-}
-
-#[derive(Default)]
-pub(crate) struct RequestArgs<'a,'b> {
-    pub path: &'a str,
-    pub query: &'b str,
+/// Path, query, body and headers of a request sent through `InteractApi`
+#[derive(Debug, Default)]
+pub struct RequestArgs {
+    pub path: String,
+    pub query: String,
     pub body: Body,
     pub header: HeaderMap,
+    pub timeouts: RequestTimeouts,
 }
 
-impl <'a,'b> RequestArgs<'a,'b>{
+impl RequestArgs {
     pub fn set_header<A,B>(&mut self, key: A, value: B)
         where
             A: Into<HeaderName>,
@@ -61,53 +55,58 @@ impl <'a,'b> RequestArgs<'a,'b>{
     }
 }
 
-pub(crate) trait IntoRequestArgs<'a,'b>
+pub(crate) trait IntoRequestArgs
 {
-    fn into_request_args(self) -> RequestArgs<'a,'b>;
+    fn into_request_args(self) -> RequestArgs;
 }
 
-impl <'a,'b> IntoRequestArgs<'a,'b> for &'a str
+impl IntoRequestArgs for RequestArgs
 {
-    fn into_request_args(self) -> RequestArgs<'a,'b> {
-        let mut args = RequestArgs::default();
-        args.path = self;
-
-        args
+    fn into_request_args(self) -> RequestArgs {
+        self
     }
 }
 
-impl <'a,'b> IntoRequestArgs<'a,'b> for (&'a str, Option<&'b str>)
+impl IntoRequestArgs for &str
 {
-    fn into_request_args(self) -> RequestArgs<'a,'b> {
-        let mut args = RequestArgs::default();
-
-        args.path = self.0;
-        args.query = self.1.unwrap_or_default();
-
-        args
+    fn into_request_args(self) -> RequestArgs {
+        RequestArgs {
+            path: self.to_owned(),
+            ..Default::default()
+        }
     }
 }
 
-impl <'a,'b> IntoRequestArgs<'a,'b> for (&'a str, Option<Body>)
+impl IntoRequestArgs for (&str, Option<&str>)
 {
-    fn into_request_args(self) -> RequestArgs<'a,'b> {
-        let mut args = RequestArgs::default();
-        args.path = self.0;
-        args.body = self.1.unwrap_or_default();
-
-        args
+    fn into_request_args(self) -> RequestArgs {
+        RequestArgs {
+            path: self.0.to_owned(),
+            query: self.1.unwrap_or_default().to_owned(),
+            ..Default::default()
+        }
     }
 }
 
-impl <'a,'b> IntoRequestArgs<'a,'b> for (&'a str, Option<&'b str>, Option<Body>)
+impl IntoRequestArgs for (&str, Option<Body>)
 {
-    fn into_request_args(self) -> RequestArgs<'a,'b> {
-        let mut args = RequestArgs::default();
-
-        args.path = self.0;
-        args.query = self.1.unwrap_or_default();
-        args.body = self.2.unwrap_or_default();
+    fn into_request_args(self) -> RequestArgs {
+        RequestArgs {
+            path: self.0.to_owned(),
+            body: self.1.unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+}
 
-        args
+impl IntoRequestArgs for (&str, Option<&str>, Option<Body>)
+{
+    fn into_request_args(self) -> RequestArgs {
+        RequestArgs {
+            path: self.0.to_owned(),
+            query: self.1.unwrap_or_default().to_owned(),
+            body: self.2.unwrap_or_default(),
+            ..Default::default()
+        }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,106 @@
+//! Docker Engine API versions and their negotiation
+
+use std::cmp;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::errors::{Error, ErrorKind, Result};
+
+/// Oldest engine API version this client is able to speak
+pub const MIN_API_VERSION: ApiVersion = ApiVersion { major: 1, minor: 25 };
+
+/// Newest engine API version this client is able to speak
+pub const MAX_API_VERSION: ApiVersion = ApiVersion { major: 1, minor: 43 };
+
+/// Version of the Docker Engine API, e.g. `1.37`
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct ApiVersion {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl ApiVersion {
+    pub fn new(major: u32, minor: u32) -> ApiVersion {
+        ApiVersion {
+            major,
+            minor,
+        }
+    }
+}
+
+impl fmt::Display for ApiVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+impl FromStr for ApiVersion {
+    type Err = Error;
+
+    /// Parses `1.37` as well as the path prefix form `v1.37`
+    fn from_str(s: &str) -> Result<ApiVersion> {
+        let invalid = || Error::from(ErrorKind::InvalidApiVersion(s.to_owned()));
+        let trimmed = s.trim();
+        let trimmed = trimmed.strip_prefix('v').unwrap_or(trimmed);
+
+        let mut parts = trimmed.splitn(2, '.');
+        let major = parts.next()
+            .and_then(|m| m.parse().ok())
+            .ok_or_else(invalid)?;
+        let minor = parts.next()
+            .and_then(|m| m.parse().ok())
+            .ok_or_else(invalid)?;
+
+        Ok(ApiVersion::new(major, minor))
+    }
+}
+
+/// Picks the highest API version supported by both this client and a daemon
+/// reporting `daemon_max` as its current and `daemon_min` as its oldest
+/// supported version
+pub fn negotiate(daemon_max: ApiVersion, daemon_min: Option<ApiVersion>) -> Result<ApiVersion> {
+    let chosen = cmp::min(daemon_max, MAX_API_VERSION);
+    let floor = cmp::max(daemon_min.unwrap_or(MIN_API_VERSION), MIN_API_VERSION);
+
+    if chosen < floor {
+        let min = daemon_min.unwrap_or(daemon_max);
+        Err(ErrorKind::UnsupportedApiVersion(min.to_string(), daemon_max.to_string()).into())
+    } else {
+        Ok(chosen)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{negotiate, ApiVersion, MAX_API_VERSION};
+
+    #[test]
+    fn parse_and_display() {
+        let version: ApiVersion = "v1.37".parse().expect("Valid version");
+
+        assert_eq!(ApiVersion::new(1, 37), version);
+        assert_eq!("1.37", version.to_string());
+        assert!("1".parse::<ApiVersion>().is_err());
+        assert!("latest".parse::<ApiVersion>().is_err());
+    }
+
+    #[test]
+    fn negotiate_older_daemon() {
+        let chosen = negotiate(ApiVersion::new(1, 37), Some(ApiVersion::new(1, 12)));
+
+        assert_eq!(ApiVersion::new(1, 37), chosen.expect("Common version"));
+    }
+
+    #[test]
+    fn negotiate_newer_daemon() {
+        let chosen = negotiate(ApiVersion::new(1, 99), Some(ApiVersion::new(1, 24)));
+
+        assert_eq!(MAX_API_VERSION, chosen.expect("Common version"));
+    }
+
+    #[test]
+    fn negotiate_without_common_version() {
+        assert!(negotiate(ApiVersion::new(1, 60), Some(ApiVersion::new(1, 50))).is_err());
+        assert!(negotiate(ApiVersion::new(1, 18), None).is_err());
+    }
+}
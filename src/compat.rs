@@ -0,0 +1,165 @@
+//! futures 0.1 flavoured client for code not yet ported to async/await
+//!
+//! `CompatDocker` wraps any `DockerApi` and hands out boxed futures 0.1
+//! futures and streams. The underlying requests are spawned on the tokio 1
+//! runtime behind the given `Handle`, so the results can be polled from any
+//! futures 0.1 executor, including `wait()` on a plain thread.
+//!
+//! ```no_run
+//! # use async_docker::compat::CompatDocker;
+//! # use async_docker::new_docker;
+//! use futures01::Future;
+//!
+//! # fn main() -> async_docker::Result<()> {
+//! let runtime = tokio::runtime::Runtime::new()?;
+//! let docker = CompatDocker::new(new_docker(None)?, runtime.handle().clone());
+//!
+//! let status = docker.ping().wait()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::future::Future;
+use std::sync::Arc;
+
+use futures::channel::mpsc;
+use futures::compat::Compat;
+use futures::{FutureExt, SinkExt, Stream, StreamExt};
+use hyper::StatusCode;
+use tokio::runtime::Handle;
+
+use crate::build::EventsOptions;
+use crate::communicate::docker::DockerApi;
+use crate::communicate::version::ApiVersion;
+use crate::errors::{Error, ErrorKind, Result};
+use crate::representation::rep::{Event, Info, Version};
+
+/// Boxed futures 0.1 future resolving to `T`
+pub type Future01<T> = Box<dyn futures01::Future<Item=T, Error=Error> + Send>;
+
+/// Boxed futures 0.1 stream of `T`
+pub type Stream01<T> = Box<dyn futures01::Stream<Item=T, Error=Error> + Send>;
+
+/// Number of stream items buffered ahead of the futures 0.1 consumer
+const STREAM_BUFFER: usize = 16;
+
+/// Docker client returning futures 0.1 futures and streams
+#[derive(Clone)]
+pub struct CompatDocker {
+    docker: Arc<dyn DockerApi>,
+    handle: Handle,
+}
+
+impl CompatDocker {
+    /// Wraps a client, its requests are driven on the runtime of `handle`
+    pub fn new(docker: Box<dyn DockerApi>, handle: Handle) -> CompatDocker {
+        CompatDocker {
+            docker: Arc::from(docker),
+            handle,
+        }
+    }
+
+    /// The wrapped client, for the interfaces not mirrored here
+    pub fn docker(&self) -> &dyn DockerApi {
+        &*self.docker
+    }
+
+    /// Returns version information associated with the docker daemon
+    pub fn version(&self) -> Future01<Version> {
+        let docker = self.docker.clone();
+        self.spawn(async move { docker.version().await })
+    }
+
+    /// Returns information associated with the docker daemon
+    pub fn info(&self) -> Future01<Info> {
+        let docker = self.docker.clone();
+        self.spawn(async move { docker.info().await })
+    }
+
+    /// Returns a simple ping response indicating the docker daemon is accessible
+    pub fn ping(&self) -> Future01<StatusCode> {
+        let docker = self.docker.clone();
+        self.spawn(async move { docker.ping().await })
+    }
+
+    /// Queries the daemon's supported API versions and pins the highest one
+    /// this client supports as well
+    pub fn negotiate_version(&self) -> Future01<ApiVersion> {
+        let docker = self.docker.clone();
+        self.spawn(async move { docker.negotiate_version().await })
+    }
+
+    /// Returns a stream of docker events
+    pub fn events(&self, opts: &EventsOptions) -> Stream01<Event> {
+        self.spawn_stream(self.docker.events(opts))
+    }
+
+    /// Runs any future of this crate on the runtime, e.g. one of a
+    /// container or image interface
+    pub fn spawn<F, T>(&self, future: F) -> Future01<T>
+        where F: Future<Output=Result<T>> + Send + 'static,
+              T: Send + 'static
+    {
+        let task = self.handle.spawn(future);
+
+        Box::new(Compat::new(async move {
+            task.await.map_err(|e| Error::from(ErrorKind::Message(e.to_string())))?
+        }.boxed()))
+    }
+
+    /// Runs any stream of this crate on the runtime, items are produced
+    /// until the returned stream is dropped
+    pub fn spawn_stream<S, T>(&self, stream: S) -> Stream01<T>
+        where S: Stream<Item=Result<T>> + Send + 'static,
+              T: Send + 'static
+    {
+        let (mut sender, receiver) = mpsc::channel(STREAM_BUFFER);
+
+        self.handle.spawn(async move {
+            let mut stream = Box::pin(stream);
+            while let Some(item) = stream.next().await {
+                if sender.send(item).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Box::new(Compat::new(receiver))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CompatDocker;
+    use crate::build::{ContainerOptions, EventsOptions};
+    use crate::communicate::fake::FakeDocker;
+    use futures01::{Future, Stream};
+    use hyper::StatusCode;
+    use std::borrow::Cow;
+    use tokio::runtime::Runtime;
+
+    #[test]
+    fn futures01_client() {
+        let runtime = Runtime::new().expect("Runtime");
+        let fake = FakeDocker::new();
+        fake.add_image("alpine");
+        let docker = CompatDocker::new(Box::new(fake.clone()), runtime.handle().clone());
+
+        assert_eq!(StatusCode::OK, docker.ping().wait().expect("Ping"));
+
+        let mut opts = ContainerOptions::builder("alpine");
+        opts.name("web");
+        let containers = docker.docker().containers();
+        docker.spawn(async move { containers.create(&opts.build()).await })
+            .wait().expect("Created");
+        let container = docker.docker().container(Cow::Borrowed("web"));
+        docker.spawn(async move { container.start().await }).wait().expect("Started");
+
+        let events = EventsOptions::builder().since(&0).until(&u64::MAX).build();
+        let statuses: Vec<String> = docker.events(&events).collect().wait().expect("Events")
+            .into_iter()
+            .map(|e| e.status.expect("Status"))
+            .collect();
+        assert_eq!(vec!["pull", "create", "start"], statuses);
+    }
+}
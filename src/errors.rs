@@ -12,7 +12,9 @@ error_chain! {
         EnvVar(::std::env::VarError);
         Io(::std::io::Error);
         Hyper(::hyper::Error);
+        HyperClient(::hyper_util::client::legacy::Error);
         OpenSSL(openssl::error::ErrorStack) #[cfg(feature = "ssl")];
+        Rustls(rustls::Error) #[cfg(feature = "rustls")];
         SerdeJsonError(::serde_json::error::Error);
         InvalidUri(::http::uri::InvalidUri);
         Http(::http::Error);
@@ -79,6 +81,94 @@ error_chain! {
             description("Invalid path - empty parent")
                 display("Invalid uri ")
         }
+
+        ContextNotFound(name: String) {
+            description("Docker context not found")
+                display("Docker context '{}' not found", name)
+        }
+
+        InvalidTlsConfig(msg: String) {
+            description("Invalid TLS configuration")
+                display("Invalid TLS configuration: {}", msg)
+        }
+
+        InvalidSshHost(msg: String) {
+            description("Invalid ssh host")
+                display("Invalid ssh host: {}", msg)
+        }
+
+        InvalidApiVersion(version: String) {
+            description("Invalid API version")
+                display("Invalid API version '{}'", version)
+        }
+
+        UnsupportedApiVersion(min: String, max: String) {
+            description("No API version supported by both client and daemon")
+                display("Daemon API versions {} to {} are not supported by this client", min, max)
+        }
+
+        UnmatchedRequest(request: String) {
+            description("No recorded interaction matches the request")
+                display("No recorded interaction matches {}", request)
+        }
+
+        InvalidCassette(msg: String) {
+            description("Invalid cassette")
+                display("Invalid cassette: {}", msg)
+        }
+
+        CredentialHelper(helper: String, msg: String) {
+            description("Credential helper failed")
+                display("docker-credential-{}: {}", helper, msg)
+        }
+
+        StreamError(endpoint: String, message: String) {
+            description("Docker API: error reported in the response stream")
+                display("{}: {}", endpoint, message)
+        }
+
+        Timeout(endpoint: String) {
+            description("Request timed out")
+                display("{}: request timed out", endpoint)
+        }
+
+        NotModified(endpoint: String, status: StatusCode, message: String) {
+            description("Docker API: not modified")
+                display("{} ({}): {}", endpoint, status, message)
+        }
+
+        BadParameter(endpoint: String, status: StatusCode, message: String) {
+            description("Docker API: bad parameter")
+                display("{} ({}): {}", endpoint, status, message)
+        }
+
+        NotFound(endpoint: String, status: StatusCode, message: String) {
+            description("Docker API: no such resource")
+                display("{} ({}): {}", endpoint, status, message)
+        }
+
+        Conflict(endpoint: String, status: StatusCode, message: String) {
+            description("Docker API: conflict")
+                display("{} ({}): {}", endpoint, status, message)
+        }
+
+        ServerError(endpoint: String, status: StatusCode, message: String) {
+            description("Docker API: server error")
+                display("{} ({}): {}", endpoint, status, message)
+        }
     }
 
 }
+
+impl ErrorKind {
+    /// Maps a non-2xx response of the docker daemon onto the matching error
+    pub(crate) fn from_status(endpoint: String, status: StatusCode, message: String) -> ErrorKind {
+        match status {
+            StatusCode::NOT_MODIFIED => ErrorKind::NotModified(endpoint, status, message),
+            StatusCode::NOT_FOUND => ErrorKind::NotFound(endpoint, status, message),
+            StatusCode::CONFLICT => ErrorKind::Conflict(endpoint, status, message),
+            s if s.is_client_error() => ErrorKind::BadParameter(endpoint, status, message),
+            _ => ErrorKind::ServerError(endpoint, status, message),
+        }
+    }
+}
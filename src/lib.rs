@@ -3,14 +3,17 @@
 //! # examples
 //!
 //! ```no_run
-//! extern crate async_docker;
+//! use async_docker::blocking::BlockingDocker;
 //!
-//! let docker = async_docker::Docker::new();
-//! let images = docker.images().list(&Default::default()).unwrap();
-//! debug!("docker images in stock");
+//! # fn main() -> async_docker::Result<()> {
+//! let docker = BlockingDocker::new(async_docker::new_docker(None)?)?;
+//! let images = docker.images().list(&Default::default())?;
+//! println!("docker images in stock");
 //! for i in images {
-//!   debug!("{:?}", i.RepoTags);
+//!   println!("{:?}", i.RepoTags);
 //! }
+//! # Ok(())
+//! # }
 //! ```
 
 #![recursion_limit = "256"]
@@ -32,20 +35,33 @@ extern crate hyper;
 extern crate http;
 extern crate url;
 extern crate bytes;
-extern crate tokio_codec;
+extern crate base64;
+#[cfg(feature = "ssl")]
+extern crate openssl;
+#[cfg(feature = "rustls")]
+extern crate rustls;
 
 pub mod representation;
 pub mod communicate;
 pub mod build;
+pub mod blocking;
+#[cfg(feature = "test-server")]
+pub mod test_server;
+#[cfg(feature = "compat")]
+pub mod compat;
 
 mod errors;
 mod tarball;
 mod transport;
+#[cfg(test)]
+mod test_util;
 
 
-pub use errors::Error;
-pub use errors::Result;
+pub use crate::errors::Error;
+pub use crate::errors::Result;
 
-pub use communicate::*;
-pub use build::*;
-pub use representation::*;
\ No newline at end of file
+pub use crate::communicate::*;
+pub use crate::build::*;
+pub use crate::representation::*;
+// the interfaces win over the representations of the same name
+pub use crate::communicate::{Container, Image, Network};
\ No newline at end of file
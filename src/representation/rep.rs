@@ -1,6 +1,7 @@
 //! Rust representations of docker json structures
 
 use std::collections::HashMap;
+use serde_json::Value;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SearchResult {
@@ -160,15 +161,12 @@ pub struct Config {
 impl Config {
     pub fn env(&self) -> HashMap<String, String> {
         let mut map = HashMap::new();
-        match self.Env {
-            Some(ref vars) => {
-                for e in vars {
-                    let pair: Vec<&str> = e.split("=").collect();
-                    map.insert(pair[0].to_owned(), pair[1].to_owned());
-                }
+        if let Some(ref vars) = self.Env {
+            for e in vars {
+                let pair: Vec<&str> = e.split("=").collect();
+                map.insert(pair[0].to_owned(), pair[1].to_owned());
             }
-            _ => (),
-        };
+        }
         map
     }
 }
@@ -348,6 +346,7 @@ pub struct Top {
 #[allow(non_snake_case)]
 pub struct Version {
     pub ApiVersion: String,
+    pub MinAPIVersion: Option<String>,
     pub Version: String,
     pub GitCommit: String,
     pub GoVersion: String,
@@ -400,6 +399,10 @@ pub struct Exit {
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[allow(non_snake_case)]
 pub struct Event {
+    /// `container`, `image`, `network`, ...
+    pub Type: Option<String>,
+    pub Action: Option<String>,
+    pub Actor: Option<EventActor>,
     pub status: Option<String>,
     pub id: Option<String>,
     pub from: Option<String>,
@@ -407,6 +410,79 @@ pub struct Event {
     pub timeNano: u64,
 }
 
+/// Object an event is about
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub struct EventActor {
+    pub ID: String,
+    #[serde(default)]
+    pub Attributes: HashMap<String, String>,
+}
+
+/// Bytes of a layer transferred so far
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ProgressDetail {
+    pub current: Option<u64>,
+    pub total: Option<u64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ErrorDetail {
+    pub code: Option<i64>,
+    #[serde(default)]
+    pub message: String,
+}
+
+/// Progress message streamed while an image is pulled, pushed, loaded or
+/// built. Which fields are set depends on the operation
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub struct ProgressEvent {
+    /// Output such as build steps, e.g. `Step 1/3 : FROM alpine`, or
+    /// `Loaded image: app:prod`
+    pub stream: Option<String>,
+    pub status: Option<String>,
+    /// Layer the message is about
+    pub id: Option<String>,
+    pub progress: Option<String>,
+    pub progressDetail: Option<ProgressDetail>,
+    /// Auxiliary data, `{"ID": "sha256:..."}` once an image is built
+    pub aux: Option<Value>,
+    pub error: Option<String>,
+    pub errorDetail: Option<ErrorDetail>,
+}
+
+impl ProgressEvent {
+    /// Error the daemon reported in place of progress
+    pub fn error_message(&self) -> Option<&str> {
+        self.errorDetail.as_ref()
+            .map(|detail| detail.message.as_str())
+            .filter(|message| !message.is_empty())
+            .or(self.error.as_deref())
+    }
+
+    /// Id of the built image, carried by `aux` or, by older daemons, the
+    /// `Successfully built` output
+    pub fn image_id(&self) -> Option<&str> {
+        self.aux.as_ref()
+            .and_then(|aux| aux["ID"].as_str())
+            .or_else(|| self.stream.as_ref()
+                .and_then(|line| line.trim_end().strip_prefix("Successfully built ")))
+    }
+}
+
+/// Progress message of an image pull or import
+pub type PullEvent = ProgressEvent;
+
+/// Progress message of an image push
+pub type PushEvent = ProgressEvent;
+
+/// Message of an image load, either progress or a loaded image
+pub type LoadEvent = ProgressEvent;
+
+/// Output message of an image build
+pub type BuildEvent = ProgressEvent;
+
 #[derive(Clone, Debug)]
 pub enum Status {
     Untagged(String),
@@ -418,4 +494,20 @@ pub enum Status {
 pub struct IOString {
     #[serde(default)]
     pub content: String,
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ProgressEvent;
+
+    #[test]
+    fn error_message_skips_empty_detail() {
+        let event: ProgressEvent = serde_json::from_str(
+            r#"{"errorDetail":{"message":""},"error":"manifest unknown"}"#).expect("Event");
+        assert_eq!(Some("manifest unknown"), event.error_message());
+
+        let event: ProgressEvent = serde_json::from_str(
+            r#"{"errorDetail":{"message":"denied"},"error":"access denied"}"#).expect("Event");
+        assert_eq!(Some("denied"), event.error_message());
+    }
+}
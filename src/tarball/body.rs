@@ -0,0 +1,166 @@
+//! Request bodies produced incrementally by blocking writers
+//!
+//! Archiving a directory is blocking work. The writer runs on a blocking
+//! thread and hands chunks to the request body through a bounded channel,
+//! so at most `CHANNEL_CHUNKS` chunks of `CHUNK_SIZE` bytes are held in
+//! memory however large the archive gets: the writer blocks until hyper has
+//! sent the earlier chunks. The writer only starts once the body is first
+//! polled, so a body which is never sent costs nothing.
+
+use std::io::{self, Write};
+use std::mem;
+use std::thread;
+
+use bytes::Bytes;
+use futures::{stream, StreamExt};
+use tokio::runtime::Handle;
+use tokio::sync::mpsc;
+
+use crate::errors::Result;
+use crate::transport::body::Body;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+const CHANNEL_CHUNKS: usize = 8;
+
+/// `Write` half of a streamed body, sending a chunk whenever enough bytes
+/// are buffered
+struct ChannelWriter {
+    sender: mpsc::Sender<io::Result<Bytes>>,
+    buf: Vec<u8>,
+}
+
+impl ChannelWriter {
+    fn send(&mut self) -> io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+
+        let chunk = mem::replace(&mut self.buf, Vec::with_capacity(CHUNK_SIZE));
+        self.sender.blocking_send(Ok(Bytes::from(chunk)))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "Request body was dropped"))
+    }
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let len = data.len().min(CHUNK_SIZE - self.buf.len());
+        self.buf.extend_from_slice(&data[..len]);
+        if self.buf.len() >= CHUNK_SIZE {
+            self.send()?;
+        }
+
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.send()
+    }
+}
+
+/// Body of the bytes `write` produces on a blocking thread, started when the
+/// body is first polled. An error of `write` fails the body, and with it the
+/// request
+pub(crate) fn streamed<F>(write: F) -> Body
+where
+    F: FnOnce(&mut dyn Write) -> Result<()> + Send + 'static,
+{
+    let chunks = stream::once(async move { produce(write) })
+        .flat_map(|receiver| stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|chunk| (chunk, receiver))
+        }));
+
+    Body::wrap_stream(chunks)
+}
+
+/// Runs `write` on a blocking thread, returning the receiving end of its chunks
+fn produce<F>(write: F) -> mpsc::Receiver<io::Result<Bytes>>
+where
+    F: FnOnce(&mut dyn Write) -> Result<()> + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel(CHANNEL_CHUNKS);
+
+    let produce = move || {
+        let mut writer = ChannelWriter {
+            sender: sender.clone(),
+            buf: Vec::with_capacity(CHUNK_SIZE),
+        };
+
+        let written = write(&mut writer).and_then(|()| writer.send().map_err(Into::into));
+        if let Err(err) = written {
+            let _ = sender.blocking_send(Err(io::Error::other(err.to_string())));
+        }
+    };
+    match Handle::try_current() {
+        Ok(runtime) => {
+            runtime.spawn_blocking(produce);
+        }
+        Err(_) => {
+            thread::spawn(produce);
+        }
+    }
+
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{streamed, CHANNEL_CHUNKS, CHUNK_SIZE};
+    use crate::errors::ErrorKind;
+    use futures::StreamExt;
+    use std::sync::mpsc;
+    use tokio::runtime::Runtime;
+
+    #[test]
+    fn writer_starts_with_the_body() {
+        let (started, start) = mpsc::channel();
+        let body = streamed(move |_| {
+            let _ = started.send(());
+            Ok(())
+        });
+
+        // an eager writer would report its start before hanging up
+        drop(body);
+        assert!(start.recv().is_err());
+    }
+
+    #[test]
+    fn writer_waits_for_the_body() {
+        let runtime = Runtime::new().expect("Runtime");
+        let total = 4 * CHANNEL_CHUNKS;
+        let (progress, written) = mpsc::channel();
+
+        let mut body = streamed(move |w| {
+            for chunk in 1..=total {
+                w.write_all(&[0; CHUNK_SIZE])?;
+                let _ = progress.send(chunk);
+            }
+            Err(ErrorKind::Message("disk gone".to_owned()).into())
+        });
+
+        let chunks = runtime.block_on(async {
+            let mut chunks = 0;
+            let mut ahead = 0;
+            while let Some(chunk) = body.next().await {
+                match chunk {
+                    Ok(chunk) => {
+                        assert_eq!(CHUNK_SIZE, chunk.len());
+                        chunks += 1;
+                    }
+                    Err(err) => {
+                        assert!(err.to_string().contains("disk gone"));
+                        break;
+                    }
+                }
+
+                // the writer fills the channel, then blocks until a chunk is taken
+                while ahead < (chunks + CHANNEL_CHUNKS).min(total) {
+                    ahead = written.recv().expect("Writer progress");
+                }
+                ahead = written.try_iter().last().unwrap_or(ahead);
+                assert!(ahead <= chunks + CHANNEL_CHUNKS);
+            }
+            chunks
+        });
+        assert_eq!(total, chunks);
+    }
+}
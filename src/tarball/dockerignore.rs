@@ -0,0 +1,281 @@
+//! `.dockerignore` patterns, matched the way the docker CLI does
+//!
+//! Paths are slash separated and relative to the build context. A pattern
+//! excludes the paths it matches and everything below them, `!` patterns
+//! include them again, and the last matching pattern wins. Within a pattern
+//! `*` and `?` do not match `/`, `**` matches any number of directories and
+//! `[...]` is a character class, `[^...]` a negated one.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::errors::Result;
+
+pub(crate) const DOCKERIGNORE: &str = ".dockerignore";
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Char(char),
+    /// `*`
+    Any,
+    /// `?`
+    One,
+    /// `[...]`, ranges of chars
+    Class { negated: bool, ranges: Vec<(char, char)> },
+    /// `**` followed by more of the pattern, matches `(.*/)?`
+    Dirs,
+    /// `**` at the end of the pattern, matches everything
+    Rest,
+}
+
+#[derive(Clone, Debug)]
+struct Pattern {
+    tokens: Vec<Token>,
+    exception: bool,
+}
+
+/// Patterns of a `.dockerignore` file
+#[derive(Clone, Debug, Default)]
+pub(crate) struct DockerIgnore {
+    patterns: Vec<Pattern>,
+}
+
+impl DockerIgnore {
+    /// Patterns of the `.dockerignore` in the context directory, none if
+    /// there is no such file
+    pub(crate) fn load(context: &Path) -> Result<DockerIgnore> {
+        match fs::read_to_string(context.join(DOCKERIGNORE)) {
+            Ok(content) => Ok(DockerIgnore::parse(&content)),
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(DockerIgnore::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub(crate) fn parse(content: &str) -> DockerIgnore {
+        let patterns = content.trim_start_matches('\u{feff}')
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| match line.strip_prefix('!') {
+                Some(pattern) => Pattern::new(pattern.trim(), true),
+                None => Pattern::new(line, false),
+            })
+            .collect();
+
+        DockerIgnore {
+            patterns,
+        }
+    }
+
+    /// Always includes `path`, as the CLI does for the Dockerfile and the
+    /// `.dockerignore` itself
+    pub(crate) fn keep(mut self, path: &str) -> DockerIgnore {
+        self.patterns.push(Pattern::new(path, true));
+        self
+    }
+
+    /// Whether some pattern includes paths again, so that excluded
+    /// directories need to be looked into
+    pub(crate) fn has_exceptions(&self) -> bool {
+        self.patterns.iter().any(|pattern| pattern.exception)
+    }
+
+    /// Whether the path, relative to the context, is excluded by itself or
+    /// through one of its parent directories
+    pub(crate) fn excludes(&self, path: &str) -> bool {
+        let parents: Vec<usize> = path.match_indices('/').map(|(i, _)| i).collect();
+        let mut excluded = false;
+
+        for pattern in &self.patterns {
+            // only patterns which could change the outcome matter
+            if pattern.exception != excluded {
+                continue;
+            }
+
+            let matched = pattern.matches(path)
+                || parents.iter().any(|&end| pattern.matches(&path[..end]));
+            if matched {
+                excluded = !pattern.exception;
+            }
+        }
+
+        excluded
+    }
+}
+
+impl Pattern {
+    fn new(pattern: &str, exception: bool) -> Pattern {
+        let cleaned = clean(pattern);
+        let cleaned = if cleaned.len() > 1 {
+            cleaned.trim_start_matches('/')
+        } else {
+            &cleaned
+        };
+
+        Pattern {
+            tokens: tokenize(cleaned),
+            exception,
+        }
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        let chars: Vec<char> = path.chars().collect();
+
+        match_tokens(&self.tokens, &chars)
+    }
+}
+
+/// Lexical cleanup of a slash separated path, like go's `filepath.Clean`
+fn clean(path: &str) -> String {
+    let rooted = path.starts_with('/');
+    let mut segments: Vec<&str> = Vec::new();
+
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => (),
+            ".." => match segments.last() {
+                Some(&last) if last != ".." => {
+                    segments.pop();
+                }
+                _ if rooted => (),
+                _ => segments.push(".."),
+            },
+            segment => segments.push(segment),
+        }
+    }
+
+    let joined = segments.join("/");
+    match (rooted, joined.is_empty()) {
+        (true, _) => format!("/{}", joined),
+        (false, true) => ".".to_owned(),
+        (false, false) => joined,
+    }
+}
+
+fn tokenize(pattern: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = pattern.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let token = match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                // `**/` is the same as `**`
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                }
+                if chars.peek().is_none() { Token::Rest } else { Token::Dirs }
+            }
+            '*' => Token::Any,
+            '?' => Token::One,
+            '\\' => Token::Char(chars.next().unwrap_or('\\')),
+            '[' => {
+                let negated = chars.peek() == Some(&'^');
+                if negated {
+                    chars.next();
+                }
+                let mut ranges = Vec::new();
+                while let Some(c) = chars.next() {
+                    let low = match c {
+                        ']' => break,
+                        '\\' => chars.next().unwrap_or('\\'),
+                        c => c,
+                    };
+                    let mut lookahead = chars.clone();
+                    let high = match (lookahead.next(), lookahead.next()) {
+                        (Some('-'), Some(high)) if high != ']' => {
+                            chars.next();
+                            chars.next();
+                            if high == '\\' { chars.next().unwrap_or('\\') } else { high }
+                        }
+                        _ => low,
+                    };
+                    ranges.push((low, high));
+                }
+                Token::Class { negated, ranges }
+            }
+            c => Token::Char(c),
+        };
+        tokens.push(token);
+    }
+
+    tokens
+}
+
+fn match_tokens(tokens: &[Token], path: &[char]) -> bool {
+    let (token, rest) = match tokens.split_first() {
+        Some(split) => split,
+        None => return path.is_empty(),
+    };
+
+    match *token {
+        Token::Char(c) => path.first() == Some(&c) && match_tokens(rest, &path[1..]),
+        Token::One => match path.first() {
+            Some(&c) if c != '/' => match_tokens(rest, &path[1..]),
+            _ => false,
+        },
+        Token::Class { negated, ref ranges } => match path.first() {
+            Some(&c) => {
+                let inside = ranges.iter().any(|&(low, high)| low <= c && c <= high);
+                inside != negated && match_tokens(rest, &path[1..])
+            }
+            None => false,
+        },
+        Token::Any => {
+            let run = path.iter().take_while(|&&c| c != '/').count();
+            (0..=run).any(|skip| match_tokens(rest, &path[skip..]))
+        }
+        Token::Dirs => match_tokens(rest, path)
+            || (1..=path.len())
+                .filter(|&end| path[end - 1] == '/')
+                .any(|end| match_tokens(rest, &path[end..])),
+        Token::Rest => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DockerIgnore;
+
+    #[test]
+    fn docker_pattern_semantics() {
+        let ignore = DockerIgnore::parse(concat!(
+            "# comment\n",
+            "target\n",
+            "/.git\n",
+            "**/*.log\n",
+            "!keep.log\n",
+            "doc?/[a-c]*.md\n",
+            "tmp/**\n",
+            "vendor/[^x]*\n",
+        ));
+
+        assert!(ignore.excludes("target"));
+        assert!(ignore.excludes("target/debug/app"));
+        assert!(ignore.excludes(".git/HEAD"));
+        assert!(!ignore.excludes("src/target"));
+        assert!(ignore.excludes("build.log"));
+        assert!(ignore.excludes("logs/2024/app.log"));
+        assert!(!ignore.excludes("keep.log"));
+        assert!(ignore.excludes("docs/api.md"));
+        assert!(!ignore.excludes("docs/readme.md"));
+        assert!(!ignore.excludes("documents/api.md"));
+        assert!(ignore.excludes("tmp/cache/file"));
+        assert!(!ignore.excludes("tmp"));
+        assert!(ignore.excludes("vendor/lib"));
+        assert!(!ignore.excludes("vendor/xlib"));
+        assert!(!ignore.excludes("src/main.rs"));
+    }
+
+    #[test]
+    fn build_files_are_kept() {
+        let ignore = DockerIgnore::parse("*\n!src\n").keep("Dockerfile").keep(".dockerignore");
+
+        assert!(ignore.excludes("README.md"));
+        assert!(!ignore.excludes("src/main.rs"));
+        assert!(!ignore.excludes("Dockerfile"));
+        assert!(!ignore.excludes(".dockerignore"));
+        assert!(ignore.has_exceptions());
+    }
+}
@@ -1 +1,4 @@
-pub mod tarball;
\ No newline at end of file
+#[allow(clippy::module_inception)]
+pub mod tarball;
+pub(crate) mod body;
+pub(crate) mod dockerignore;
@@ -9,10 +9,32 @@ use std::io::Write;
 use std::path::Path;
 use self::tar::Archive;
 
-use errors::Result;
+use crate::errors::Result;
+use crate::tarball::dockerignore::{DockerIgnore, DOCKERIGNORE};
 
 // todo: this is pretty involved. (re)factor this into its own crate
 pub fn dir<W>(buf: W, path: &str) -> Result<()>
+where
+    W: Write,
+{
+    archive(buf, path, &DockerIgnore::default())
+}
+
+/// Archives a build context, leaving out what its `.dockerignore` excludes.
+/// The Dockerfile and the `.dockerignore` are always included, as the docker
+/// CLI does
+pub fn build_context<W>(buf: W, path: &str, dockerfile: &str) -> Result<()>
+where
+    W: Write,
+{
+    let ignore = DockerIgnore::load(Path::new(path))?
+        .keep(dockerfile)
+        .keep(DOCKERIGNORE);
+
+    archive(buf, path, &ignore)
+}
+
+fn archive<W>(buf: W, path: &str, ignore: &DockerIgnore) -> Result<()>
 where
     W: Write,
 {
@@ -26,7 +48,7 @@ where
         base_path = base_path.parent().expect("File has root filepath!");
     }
 
-    let mut append = |path: &Path| {
+    let append = |path: &Path| {
         let canonical = path.canonicalize()?;
         let relativized = canonical.strip_prefix(base_path)?;
 
@@ -38,31 +60,89 @@ where
         Ok(())
     };
 
-    bundle(Path::new(path), &mut append, false)?;
+    let filter = Filter {
+        base: Path::new(path),
+        ignore,
+    };
+    bundle(Path::new(path), &append, false, &filter)?;
 
     archive.finish()?;
 
     Ok(())
 }
 
-fn bundle<F>(dir: &Path, f: &F, bundle_dir: bool) -> Result<()>
+/// Paths of the context excluded by the ignore patterns
+struct Filter<'a> {
+    base: &'a Path,
+    ignore: &'a DockerIgnore,
+}
+
+impl<'a> Filter<'a> {
+    fn excludes(&self, path: &Path) -> bool {
+        let relative = match path.strip_prefix(self.base) {
+            Ok(relative) => relative,
+            Err(_) => return false,
+        };
+        let relative = relative.components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        self.ignore.excludes(&relative)
+    }
+}
+
+fn bundle<F>(dir: &Path, f: &F, bundle_dir: bool, filter: &Filter) -> Result<()>
 where
     F: Fn(&Path) -> Result<()>,
 {
     if fs::metadata(dir)?.is_dir() {
         if bundle_dir {
-            f(&dir)?;
+            if !filter.excludes(dir) {
+                f(dir)?;
+            } else if !filter.ignore.has_exceptions() {
+                // nothing below an excluded directory can be included again
+                return Ok(());
+            }
         }
         for entry in fs::read_dir(dir)? {
             let entry = entry?;
             if fs::metadata(entry.path())?.is_dir() {
-                bundle(&entry.path(), f, true)?;
-            } else {
-                f(&entry.path().as_path())?;
+                bundle(&entry.path(), f, true, filter)?;
+            } else if !filter.excludes(&entry.path()) {
+                f(entry.path().as_path())?;
             }
         }
     } else {
-        f(&dir)?;
+        f(dir)?;
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::build_context;
+    use super::flate2::read::GzDecoder;
+    use super::tar::Archive;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn build_context_honours_dockerignore() {
+        let dir = TempDir::new("context");
+        for file in &["Dockerfile", "src/main.rs", "target/debug/app", ".git/HEAD", "README.md"] {
+            dir.write(file, file);
+        }
+        dir.write(".dockerignore", "target\n.git\n*\n!src\n");
+
+        let mut bytes = Vec::new();
+        build_context(&mut bytes, dir.path().to_str().expect("Path"), "Dockerfile").expect("Context");
+
+        let mut archive = Archive::new(GzDecoder::new(bytes.as_slice()).expect("Gzip"));
+        let mut paths: Vec<String> = archive.files_mut().expect("Entries")
+            .map(|file| file.expect("Entry").header().path().expect("Path")
+                .to_string_lossy().trim_end_matches('/').to_owned())
+            .collect();
+        paths.sort();
+        assert_eq!(vec![".dockerignore", "Dockerfile", "src", "src/main.rs"], paths);
+    }
+}
@@ -0,0 +1,449 @@
+#![cfg(all(feature = "test-server", target_os = "linux"))]
+
+//! Engine API server for integration tests
+//!
+//! `TestServer` answers http requests on a temporary unix socket, or on a
+//! local tcp port, from a `FakeDocker`. The regular transports, including
+//! the parsing of multiplexed and line delimited streams, can so be run end
+//! to end on a machine without docker. Requests prefixed with an API
+//! version outside of the supported range are rejected like a daemon would.
+
+use std::convert::Infallible;
+use std::env;
+use std::fs;
+use std::io;
+use std::net::{self, SocketAddr};
+use std::os::unix::net::UnixListener as StdUnixListener;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use futures::channel::oneshot;
+use hyper::body::Incoming;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Request, Response, StatusCode, Uri};
+use hyper_util::rt::TokioIo;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, UnixListener};
+use tokio::runtime::Runtime;
+
+use crate::communicate::builder::parse_host;
+use crate::communicate::docker::{new_docker, DockerApi};
+use crate::communicate::fake::FakeDocker;
+use crate::communicate::util::RequestArgs;
+use crate::communicate::version::{ApiVersion, MAX_API_VERSION, MIN_API_VERSION};
+use crate::errors::{Error, Result};
+use crate::transport::body::Body;
+use crate::transport::interact::InteractApi;
+
+static SERVERS: AtomicUsize = AtomicUsize::new(0);
+
+/// Address a `TestServer` listens on
+#[derive(Clone, Debug)]
+enum Address {
+    Unix(PathBuf),
+    Tcp(SocketAddr),
+}
+
+/// Bound socket, handed to the server thread
+enum Listener {
+    Unix(StdUnixListener),
+    Tcp(net::TcpListener),
+}
+
+/// Engine API server backed by a `FakeDocker`, stopped when dropped
+pub struct TestServer {
+    fake: FakeDocker,
+    address: Address,
+    shutdown: Option<oneshot::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl TestServer {
+    /// Serves a new `FakeDocker` on a temporary unix socket
+    pub fn unix() -> Result<TestServer> {
+        TestServer::unix_with(FakeDocker::new())
+    }
+
+    /// Serves the given fake on a temporary unix socket
+    pub fn unix_with(fake: FakeDocker) -> Result<TestServer> {
+        let dir = env::temp_dir().join(format!("async-docker-test-server-{}-{}",
+                                               process::id(),
+                                               SERVERS.fetch_add(1, Ordering::SeqCst)));
+        fs::create_dir_all(&dir)?;
+        let path = dir.join("docker.sock");
+        let listener = StdUnixListener::bind(&path)?;
+
+        TestServer::spawn(fake, Address::Unix(path), Listener::Unix(listener))
+    }
+
+    /// Serves a new `FakeDocker` on a free port of the loopback interface
+    pub fn tcp() -> Result<TestServer> {
+        TestServer::tcp_with(FakeDocker::new())
+    }
+
+    /// Serves the given fake on a free port of the loopback interface
+    pub fn tcp_with(fake: FakeDocker) -> Result<TestServer> {
+        let listener = net::TcpListener::bind("127.0.0.1:0")?;
+        let address = listener.local_addr()?;
+
+        TestServer::spawn(fake, Address::Tcp(address), Listener::Tcp(listener))
+    }
+
+    /// The fake answering requests, to prepare state and inject failures
+    pub fn fake(&self) -> &FakeDocker {
+        &self.fake
+    }
+
+    /// `unix://` or `http://` uri of the server
+    pub fn uri(&self) -> Uri {
+        let uri = match self.address {
+            Address::Unix(ref path) => format!("unix://{}", path.display()),
+            Address::Tcp(ref address) => format!("http://{}", address),
+        };
+
+        parse_host(&uri, false).expect("Test server uri parsing error")
+    }
+
+    /// Path of the unix socket, if serving on one
+    pub fn socket_path(&self) -> Option<&Path> {
+        match self.address {
+            Address::Unix(ref path) => Some(path),
+            Address::Tcp(_) => None,
+        }
+    }
+
+    /// Client connected to the server through the transport of its uri
+    pub fn docker(&self) -> Result<Box<dyn DockerApi>> {
+        new_docker(Some(self.uri()))
+    }
+
+    fn spawn(fake: FakeDocker, address: Address, listener: Listener) -> Result<TestServer> {
+        let (shutdown, receiver) = oneshot::channel();
+        let runtime = Runtime::new()?;
+        let interact = fake.interact();
+
+        let thread = thread::spawn(move || {
+            runtime.block_on(serve(listener, interact, receiver));
+            // streams like events never end on their own
+            runtime.shutdown_background();
+        });
+
+        Ok(TestServer {
+            fake,
+            address,
+            shutdown: Some(shutdown),
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        if let Address::Unix(ref path) = self.address {
+            let _ = fs::remove_file(path);
+            if let Some(dir) = path.parent() {
+                let _ = fs::remove_dir(dir);
+            }
+        }
+    }
+}
+
+/// Serves connections of `listener` until `shutdown` resolves or is dropped
+async fn serve(listener: Listener, interact: Arc<dyn InteractApi>,
+               shutdown: oneshot::Receiver<()>)
+{
+    if let Err(err) = accept(listener, interact, shutdown).await {
+        error!("Test server failed: {}", err);
+    }
+}
+
+async fn accept(listener: Listener, interact: Arc<dyn InteractApi>,
+                mut shutdown: oneshot::Receiver<()>) -> io::Result<()>
+{
+    match listener {
+        Listener::Unix(listener) => {
+            listener.set_nonblocking(true)?;
+            let listener = UnixListener::from_std(listener)?;
+            loop {
+                tokio::select! {
+                    _ = &mut shutdown => return Ok(()),
+                    accepted = listener.accept() => connection(accepted?.0, interact.clone()),
+                }
+            }
+        }
+        Listener::Tcp(listener) => {
+            listener.set_nonblocking(true)?;
+            let listener = TcpListener::from_std(listener)?;
+            loop {
+                tokio::select! {
+                    _ = &mut shutdown => return Ok(()),
+                    accepted = listener.accept() => connection(accepted?.0, interact.clone()),
+                }
+            }
+        }
+    }
+}
+
+fn connection<S>(stream: S, interact: Arc<dyn InteractApi>)
+    where S: AsyncRead + AsyncWrite + Unpin + Send + 'static
+{
+    let service = service_fn(move |request| handle(interact.clone(), request));
+
+    tokio::spawn(async move {
+        if let Err(err) = http1::Builder::new()
+            .serve_connection(TokioIo::new(stream), service)
+            .await
+        {
+            debug!("Test server connection failed: {}", err);
+        }
+    });
+}
+
+async fn handle(interact: Arc<dyn InteractApi>, request: Request<Incoming>)
+    -> ::std::result::Result<Response<Body>, Infallible>
+{
+    let (parts, body) = request.into_parts();
+
+    let path = match unversioned(parts.uri.path()) {
+        Ok(path) => path.to_owned(),
+        Err(message) => return Ok(message_response(StatusCode::BAD_REQUEST, &message)),
+    };
+    let args = RequestArgs {
+        path,
+        query: parts.uri.query().unwrap_or_default().to_owned(),
+        body: Body::new(body),
+        header: parts.headers.clone(),
+        timeouts: Default::default(),
+    };
+
+    Ok(interact.request(args, parts.method.clone()).await
+        .unwrap_or_else(|err| internal_error(&err)))
+}
+
+/// Strips the `/v1.xx` prefix off the path, rejecting unsupported versions
+/// with the message of a daemon
+fn unversioned(path: &str) -> ::std::result::Result<&str, String> {
+    let prefix = match path.split('/').nth(1) {
+        Some(prefix) if prefix.starts_with('v') => prefix,
+        _ => return Ok(path),
+    };
+    let version = match prefix.parse::<ApiVersion>() {
+        Ok(version) => version,
+        Err(_) => return Ok(path),
+    };
+
+    let message = if version > MAX_API_VERSION {
+        format!("client version {} is too new. Maximum supported API version is {}",
+                version, MAX_API_VERSION)
+    } else if version < MIN_API_VERSION {
+        format!("client version {} is too old. Minimum supported API version is {}, \
+                 please upgrade your client to a newer version", version, MIN_API_VERSION)
+    } else {
+        return Ok(&path[prefix.len() + 1..]);
+    };
+
+    Err(message)
+}
+
+fn internal_error(err: &Error) -> Response<Body> {
+    message_response(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+}
+
+fn message_response(status: StatusCode, message: &str) -> Response<Body> {
+    let body = format!("{{\"message\":{}}}",
+                       ::serde_json::to_string(message).expect("String serialization"));
+
+    Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(Body::from(body))
+        .expect("Error response building")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TestServer;
+    use crate::build::{ContainerOptions, EventsOptions, ExecContainerOptions,
+                ImageLoadOptions, LogsOptions, NetworkCreateOptions};
+    use crate::communicate::fake::LogStream;
+    use crate::communicate::util::IntoRequestArgs;
+    use crate::communicate::version::ApiVersion;
+    use crate::errors::ErrorKind;
+    use crate::transport::body::Body;
+    use crate::transport::interact::InteractApiExt;
+    use bytes::Bytes;
+    use futures::{stream, StreamExt, TryStreamExt};
+    use hyper::header::HeaderValue;
+    use hyper::StatusCode;
+    use std::borrow::Cow;
+    use std::io;
+    use std::time::Duration;
+    use tar::{Archive, Header};
+    use tokio::runtime::Runtime;
+
+    #[test]
+    fn containers_over_unix_socket() {
+        let server = TestServer::unix().expect("Server");
+        server.fake().add_image("alpine");
+        let docker = server.docker().expect("Docker");
+        let runtime = Runtime::new().expect("Runtime");
+
+        assert_eq!(StatusCode::OK, runtime.block_on(docker.ping()).expect("Ping"));
+
+        // a tty container sends its logs unframed
+        let config = Body::from(r#"{"Image":"alpine","Tty":true}"#);
+        runtime.block_on(docker.interact().post_json(("/containers/create", Some("name=web"),
+                                                      Some(config))))
+            .expect("Created");
+        let container = docker.container(Cow::Borrowed("web"));
+        runtime.block_on(container.start()).expect("Started");
+
+        // split mid-line, the client has to reassemble lines across chunks
+        server.fake().write_log("web", LogStream::Stdout, b"hello\nwor").expect("Log");
+        server.fake().write_log("web", LogStream::Stderr, b"ld\n").expect("Log");
+        let logs = LogsOptions::builder().stdout(true).stderr(true).build();
+        let lines = runtime.block_on(container.logs(&logs).try_collect::<Vec<_>>()).expect("Logs");
+        assert_eq!(vec!["hello".to_owned(), "world".to_owned()], lines);
+
+        let exec = ExecContainerOptions::builder()
+            .cmd(vec!["echo", "hi"])
+            .attach_stdout(true)
+            .attach_stderr(true)
+            .build();
+        let output = runtime.block_on(container.exec(&exec).try_collect::<Vec<_>>()).expect("Exec");
+        assert_eq!(vec![(1, b"hi\n".to_vec())],
+                   output.into_iter().map(|(t, c)| (t, c.to_vec())).collect::<Vec<_>>());
+
+        let events = EventsOptions::builder().since(&0).until(&u64::MAX).build();
+        let statuses: Vec<String> = runtime.block_on(docker.events(&events).try_collect::<Vec<_>>())
+            .expect("Events")
+            .into_iter()
+            .map(|e| e.status.expect("Status"))
+            .collect();
+        assert_eq!(vec!["pull", "create", "start", "exec_create: echo hi",
+                        "exec_start: echo hi", "exec_die"], statuses);
+    }
+
+    #[test]
+    fn multiplexed_exec_output() {
+        let server = TestServer::unix().expect("Server");
+        server.fake().add_image("alpine");
+        server.fake().on_exec(|cmd| crate::communicate::fake::ExecOutput {
+            stdout: cmd.join(" ").into_bytes(),
+            stderr: b"warning".to_vec(),
+            exit_code: 0,
+        });
+        let docker = server.docker().expect("Docker");
+        let runtime = Runtime::new().expect("Runtime");
+
+        let id = runtime.block_on(docker.containers()
+            .create(&ContainerOptions::builder("alpine").build())).expect("Created").Id;
+        let container = docker.container(Cow::Owned(id));
+        runtime.block_on(container.start()).expect("Started");
+
+        let exec = ExecContainerOptions::builder()
+            .cmd(vec!["ls", "/"])
+            .attach_stdout(true)
+            .attach_stderr(true)
+            .build();
+        let output = runtime.block_on(container.exec(&exec).try_collect::<Vec<_>>()).expect("Exec");
+        assert_eq!(vec![(1, b"ls /".to_vec()), (2, b"warning".to_vec())],
+                   output.into_iter().map(|(t, c)| (t, c.to_vec())).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn networks_over_tcp() {
+        let server = TestServer::tcp().expect("Server");
+        let docker = server.docker().expect("Docker");
+        let runtime = Runtime::new().expect("Runtime");
+
+        let created = runtime.block_on(docker.networks()
+            .create(&NetworkCreateOptions::builder("backend").build())).expect("Created");
+        let network = docker.network(Cow::Owned(created.Id));
+        assert_eq!("backend", runtime.block_on(network.inspect()).expect("Inspected").Name);
+
+        let duplicate = runtime.block_on(docker.networks()
+            .create(&NetworkCreateOptions::builder("backend").build()));
+        match duplicate.map_err(|e| e.0) {
+            Err(ErrorKind::Conflict(..)) => {}
+            other => panic!("Unexpected result {:?}", other.map(|n| n.Id)),
+        }
+
+        runtime.block_on(network.delete()).expect("Deleted");
+        assert_eq!(3, runtime.block_on(docker.networks().list(&Default::default()))
+            .expect("Networks").len());
+    }
+
+    #[test]
+    fn versioned_requests() {
+        let server = TestServer::unix().expect("Server");
+        let docker = server.docker().expect("Docker");
+        let runtime = Runtime::new().expect("Runtime");
+
+        let negotiated = runtime.block_on(docker.negotiate_version()).expect("Negotiated");
+        assert_eq!(negotiated, docker.api_version().expect("Version set"));
+        assert_eq!(StatusCode::OK, runtime.block_on(docker.ping()).expect("Ping"));
+
+        docker.set_api_version(Some(ApiVersion::new(1, 99)));
+        match runtime.block_on(docker.ping()).map_err(|e| e.0) {
+            Err(ErrorKind::BadParameter(_, _, message)) => assert!(message.contains("too new")),
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn repeated_request_headers() {
+        let server = TestServer::tcp().expect("Server");
+        let docker = server.docker().expect("Docker");
+        let runtime = Runtime::new().expect("Runtime");
+
+        let mut args = "/_ping".into_request_args();
+        args.header.append("x-trace", HeaderValue::from_static("client"));
+        args.header.append("x-trace", HeaderValue::from_static("proxy"));
+        let response = runtime.block_on(docker.interact().get(args)).expect("Ping");
+        assert_eq!(StatusCode::OK, response.status());
+    }
+
+    #[test]
+    fn slow_uploads_outlast_the_response_timeout() {
+        let server = TestServer::unix().expect("Server");
+        let docker = server.docker().expect("Docker");
+        let runtime = Runtime::new().expect("Runtime");
+
+        let manifest = br#"[{"Config":"config.json","RepoTags":["app:slow"],"Layers":[]}]"#;
+        let mut header = Header::new();
+        header.set_path("manifest.json").expect("Path");
+        header.set_size(manifest.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        let archive = Archive::new(Vec::new());
+        archive.append(&header, &mut &manifest[..]).expect("Manifest");
+        archive.finish().expect("Tarball");
+
+        // eight chunks 50ms apart take four times the response timeout to send
+        let chunks: Vec<Bytes> = archive.into_inner().chunks(256).map(Bytes::copy_from_slice).collect();
+        assert_eq!(8, chunks.len());
+        let body = Body::wrap_stream(stream::iter(chunks).then(|chunk| async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok::<_, io::Error>(chunk)
+        }));
+        let opts = ImageLoadOptions::builder().timeout(Duration::from_millis(100)).build();
+
+        let events = runtime.block_on(docker.images().load(body, &opts).try_collect::<Vec<_>>())
+            .expect("Loaded before the response timeout");
+        assert_eq!(Some("Loaded image: app:slow\n"), events[0].stream.as_deref());
+        assert!(runtime.block_on(docker.images().list(&Default::default()))
+            .expect("Images").iter()
+            .any(|image| image.RepoTags.iter().any(|tag| tag == "app:slow")));
+    }
+}
@@ -0,0 +1,75 @@
+//! Fixtures shared by the unit tests
+
+use std::env;
+use std::fs;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use async_trait::async_trait;
+use hyper::{Method, Response};
+
+use crate::communicate::util::RequestArgs;
+use crate::communicate::version::ApiVersion;
+use crate::errors::Result;
+use crate::transport::body::Body;
+use crate::transport::interact::InteractApi;
+
+/// Fresh directory below the system's temporary one, removed on drop
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    /// `name` keeps tests running in parallel apart
+    pub fn new(name: &str) -> TempDir {
+        let path = env::temp_dir().join(format!("async-docker-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).expect("Temporary dir");
+
+        TempDir(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Writes `contents` to `name` within the directory, creating parents
+    pub fn write<C>(&self, name: &str, contents: C) -> PathBuf
+    where
+        C: AsRef<[u8]>,
+    {
+        let path = self.0.join(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).expect("Temporary parent dir");
+        }
+        fs::write(&path, contents).expect("Temporary file");
+
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Transport answering every request with the given closure, without an
+/// API version
+pub(crate) struct Stub<F>(pub F);
+
+#[async_trait]
+impl<F, R> InteractApi for Stub<F>
+where
+    F: Fn(RequestArgs, Method) -> R + Send + Sync,
+    R: Future<Output=Result<Response<Body>>> + Send,
+{
+    async fn request(&self, opts: RequestArgs, method: Method) -> Result<Response<Body>> {
+        (self.0)(opts, method).await
+    }
+
+    fn api_version(&self) -> Option<ApiVersion> {
+        None
+    }
+
+    fn set_api_version(&self, _version: Option<ApiVersion>) {}
+}
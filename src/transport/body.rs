@@ -0,0 +1,212 @@
+//! Body of the requests and responses exchanged with the daemon
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures::channel::oneshot;
+use futures::{Stream, TryStreamExt};
+use http_body::{Body as HttpBody, Frame, SizeHint};
+use http_body_util::combinators::UnsyncBoxBody;
+use http_body_util::{BodyExt, Empty, Full, StreamBody};
+
+use crate::errors::{Error, ErrorKind, Result};
+use crate::transport::timeout::IdleTimeout;
+
+/// Error of a body, boxed to be independent of where the body comes from
+pub type BoxError = Box<dyn StdError + Send + Sync>;
+
+/// Streaming http body, either sent to the daemon or received from it
+pub struct Body {
+    inner: UnsyncBoxBody<Bytes, BoxError>,
+}
+
+impl Body {
+    /// Wraps any http body
+    pub fn new<B>(body: B) -> Body
+        where B: http_body::Body<Data=Bytes> + Send + 'static,
+              B::Error: Into<BoxError>
+    {
+        Body {
+            inner: body.map_err(Into::into).boxed_unsync(),
+        }
+    }
+
+    pub fn empty() -> Body {
+        Body::new(Empty::<Bytes>::new())
+    }
+
+    /// Sends the chunks of a stream as they are produced
+    pub fn wrap_stream<S, O, E>(stream: S) -> Body
+        where S: Stream<Item=::std::result::Result<O, E>> + Send + 'static,
+              O: Into<Bytes> + 'static,
+              E: Into<BoxError> + 'static
+    {
+        let frames = stream
+            .map_ok(|chunk| Frame::data(chunk.into()))
+            .map_err(Into::into);
+
+        Body::new(StreamBody::new(frames))
+    }
+
+    /// Collects the whole body
+    pub async fn bytes(self) -> Result<Bytes> {
+        let collected = self.inner.collect().await.map_err(from_box_error)?;
+
+        Ok(collected.to_bytes())
+    }
+
+    /// This body, together with a receiver resolving once it is sent
+    /// completely. Failing or dropping the body resolves it as well
+    pub(crate) fn watch_sent(self) -> (Body, oneshot::Receiver<()>) {
+        let (sender, receiver) = oneshot::channel();
+        let mut body = SentBody {
+            inner: self,
+            sent: Some(sender),
+        };
+        // empty bodies are never polled
+        if body.inner.is_end_stream() {
+            body.signal();
+        }
+
+        (Body::new(body), receiver)
+    }
+}
+
+/// Body signalling the end of its data
+struct SentBody {
+    inner: Body,
+    sent: Option<oneshot::Sender<()>>,
+}
+
+impl SentBody {
+    fn signal(&mut self) {
+        if let Some(sent) = self.sent.take() {
+            let _ = sent.send(());
+        }
+    }
+}
+
+impl http_body::Body for SentBody {
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_frame(mut self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Option<::std::result::Result<Frame<Bytes>, BoxError>>>
+    {
+        let frame = Pin::new(&mut self.inner).poll_frame(cx);
+        match frame {
+            Poll::Ready(Some(Ok(_))) if self.inner.is_end_stream() => self.signal(),
+            Poll::Ready(Some(Err(_))) | Poll::Ready(None) => self.signal(),
+            _ => (),
+        }
+
+        frame
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        HttpBody::size_hint(&self.inner)
+    }
+}
+
+impl Default for Body {
+    fn default() -> Body {
+        Body::empty()
+    }
+}
+
+impl fmt::Debug for Body {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Body").finish()
+    }
+}
+
+impl From<Bytes> for Body {
+    fn from(bytes: Bytes) -> Body {
+        Body::new(Full::new(bytes))
+    }
+}
+
+impl From<Vec<u8>> for Body {
+    fn from(bytes: Vec<u8>) -> Body {
+        Body::from(Bytes::from(bytes))
+    }
+}
+
+impl From<String> for Body {
+    fn from(text: String) -> Body {
+        Body::from(Bytes::from(text))
+    }
+}
+
+impl From<&'static str> for Body {
+    fn from(text: &'static str) -> Body {
+        Body::from(Bytes::from_static(text.as_bytes()))
+    }
+}
+
+impl http_body::Body for Body {
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_frame(mut self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Option<::std::result::Result<Frame<Bytes>, BoxError>>>
+    {
+        Pin::new(&mut self.inner).poll_frame(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+/// The data chunks of the body, trailers are skipped
+impl Stream for Body {
+    type Item = Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Result<Bytes>>> {
+        loop {
+            let frame = match Pin::new(&mut self.inner).poll_frame(cx) {
+                Poll::Ready(Some(Ok(frame))) => frame,
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(from_box_error(err)))),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            };
+
+            if let Ok(data) = frame.into_data() {
+                return Poll::Ready(Some(Ok(data)));
+            }
+        }
+    }
+}
+
+/// Recovers the typed error of a failed body where possible
+pub(crate) fn from_box_error(err: BoxError) -> Error {
+    let err = match err.downcast::<IdleTimeout>() {
+        Ok(err) => return ErrorKind::Timeout(err.0).into(),
+        Err(err) => err,
+    };
+    let err = match err.downcast::<hyper::Error>() {
+        Ok(err) => return Error::from(*err),
+        Err(err) => err,
+    };
+    match err.downcast::<::std::io::Error>() {
+        Ok(err) => Error::from(*err),
+        Err(err) => ErrorKind::Message(err.to_string()).into(),
+    }
+}
+
+/// Converts an error of this crate for use within a body
+pub(crate) fn into_box_error(err: Error) -> BoxError {
+    Box::new(::std::io::Error::other(err.to_string()))
+}
@@ -1,79 +1,115 @@
 use hyper::Uri;
 use std::sync::Arc;
-use hyper::Client;
-use transport::parse::ResponseFutureWrapper;
-use Error;
-use futures::future;
+use std::sync::RwLock;
+use hyper_util::client::legacy::Client;
+use hyper_util::client::legacy::connect::Connect;
+use crate::Result;
 use hyper::Method;
-use transport::parse::compose_uri;
-use hyper::client::connect::Connect;
-use futures::Future;
+use hyper::Response;
+use crate::transport::body::Body;
+use crate::transport::parse::compose_uri;
+use crate::transport::parse::check_status;
+use async_trait::async_trait;
 use http::header::CONTENT_TYPE;
-use communicate::util::RequestArgs;
-use communicate::util::IntoRequestArgs;
+use crate::communicate::util::RequestArgs;
+use crate::communicate::util::IntoRequestArgs;
 use http::header::CONNECTION;
 use http::header::HeaderValue;
+use crate::communicate::version::ApiVersion;
+use crate::communicate::timeout::Timeouts;
+use crate::errors::{Error, ErrorKind};
+use crate::transport::timeout::idle_timeout;
+use std::error::Error as StdError;
+use std::io;
+
+
+/// Transport every docker interface sends its requests through
+///
+/// Implement it to plug in a custom transport and hand it to
+/// `new_docker_with_interact`. Non-2xx responses are turned into errors by
+/// the callers, implementations only report failures of the transport itself.
+#[async_trait]
+pub trait InteractApi: Send + Sync
+{
+    /// Sends a request for `opts.path` and `opts.query`, relative to the
+    /// daemon's base uri, and resolves to the raw response
+    async fn request(&self, opts: RequestArgs, method: Method) -> Result<Response<Body>>;
 
+    /// Engine API version prefixed to the path of every request
+    fn api_version(&self) -> Option<ApiVersion>;
 
-pub(crate) trait InteractApi: Send + Sync
-{
-    fn request(&self, opts: RequestArgs, method: Method)
-        -> ResponseFutureWrapper;
+    fn set_api_version(&self, version: Option<ApiVersion>);
 }
 
-impl InteractApi for Arc<InteractApi>
+#[async_trait]
+impl InteractApi for Arc<dyn InteractApi>
 {
-    fn request(&self, opts: RequestArgs, method: Method)
-               -> ResponseFutureWrapper
+    async fn request(&self, opts: RequestArgs, method: Method) -> Result<Response<Body>>
     {
-        (**self).request(opts, method)
+        (**self).request(opts, method).await
+    }
+
+    fn api_version(&self) -> Option<ApiVersion> {
+        (**self).api_version()
+    }
+
+    fn set_api_version(&self, version: Option<ApiVersion>) {
+        (**self).set_api_version(version)
     }
 }
 
+#[async_trait]
 pub(crate) trait InteractApiExt
 {
-    fn get<'a, 'b, A>(&self, opts: A) -> ResponseFutureWrapper
-        where A: IntoRequestArgs<'a, 'b>;
+    async fn get<A>(&self, opts: A) -> Result<Response<Body>>
+        where A: IntoRequestArgs + Send;
 
-    fn put<'a, 'b, A>(&self, opts: A)
-           -> ResponseFutureWrapper
-        where A: IntoRequestArgs<'a, 'b>;
+    async fn put<A>(&self, opts: A) -> Result<Response<Body>>
+        where A: IntoRequestArgs + Send;
 
-    fn post<'a, 'b, A>(&self, opts: A)
-            -> ResponseFutureWrapper
-        where A: IntoRequestArgs<'a, 'b>;
+    async fn post<A>(&self, opts: A) -> Result<Response<Body>>
+        where A: IntoRequestArgs + Send;
 
-    fn post_json<'a, 'b, A>(&self, opts: A)
-                 -> ResponseFutureWrapper
-        where A: IntoRequestArgs<'a, 'b>;
+    async fn post_json<A>(&self, opts: A) -> Result<Response<Body>>
+        where A: IntoRequestArgs + Send;
 
-    fn delete<'a, 'b, A>(&self, opts: A) -> ResponseFutureWrapper
-        where A: IntoRequestArgs<'a, 'b>;
+    async fn delete<A>(&self, opts: A) -> Result<Response<Body>>
+        where A: IntoRequestArgs + Send;
 }
 
+#[async_trait]
 impl <T> InteractApiExt for T
-    where T: InteractApi
+    where T: InteractApi + ?Sized
 {
-    fn get<'a, 'b, A>(&self, opts: A) -> ResponseFutureWrapper
-        where A: IntoRequestArgs<'a, 'b>
+    async fn get<A>(&self, opts: A) -> Result<Response<Body>>
+        where A: IntoRequestArgs + Send
     {
-        self.request(opts.into_request_args(), Method::GET)
+        let opts = opts.into_request_args();
+        let endpoint = opts.path.clone();
+
+        check_status(endpoint, self.request(opts, Method::GET).await).await
     }
 
-    fn put<'a, 'b, A>(&self, opts: A) -> ResponseFutureWrapper
-        where A: IntoRequestArgs<'a, 'b>
+    async fn put<A>(&self, opts: A) -> Result<Response<Body>>
+        where A: IntoRequestArgs + Send
     {
-        self.request(opts.into_request_args(), Method::PUT)
+        let opts = opts.into_request_args();
+        let endpoint = opts.path.clone();
+
+        check_status(endpoint, self.request(opts, Method::PUT).await).await
     }
 
-    fn post<'a, 'b, A>(&self, opts: A) -> ResponseFutureWrapper
-        where A: IntoRequestArgs<'a, 'b>
+    async fn post<A>(&self, opts: A) -> Result<Response<Body>>
+        where A: IntoRequestArgs + Send
     {
-        self.request(opts.into_request_args(), Method::POST)
+        let opts = opts.into_request_args();
+        let endpoint = opts.path.clone();
+
+        check_status(endpoint, self.request(opts, Method::POST).await).await
     }
 
-    fn post_json<'a, 'b, A>(&self, opts: A) -> ResponseFutureWrapper
-        where A: IntoRequestArgs<'a, 'b>
+    async fn post_json<A>(&self, opts: A) -> Result<Response<Body>>
+        where A: IntoRequestArgs + Send
     {
         let mut opts = opts.into_request_args();
         #[cfg(target_os = "linux")]
@@ -81,14 +117,18 @@ impl <T> InteractApiExt for T
             .expect("Constant connection header values's parse failed"));
         opts.set_header(CONTENT_TYPE, HeaderValue::from_str("application/json")
             .expect("Constant content type header value's parse failed"));
+        let endpoint = opts.path.clone();
 
-        self.request(opts, Method::POST)
+        check_status(endpoint, self.request(opts, Method::POST).await).await
     }
 
-    fn delete<'a, 'b, A>(&self, opts: A) -> ResponseFutureWrapper
-        where A: IntoRequestArgs<'a, 'b>
+    async fn delete<A>(&self, opts: A) -> Result<Response<Body>>
+        where A: IntoRequestArgs + Send
     {
-        self.request(opts.into_request_args(), Method::DELETE)
+        let opts = opts.into_request_args();
+        let endpoint = opts.path.clone();
+
+        check_status(endpoint, self.request(opts, Method::DELETE).await).await
     }
 }
 
@@ -96,53 +136,97 @@ impl <T> InteractApiExt for T
 #[derive(Clone)]
 pub(crate) struct Interact<I>
     where
-        I: Connect + 'static
+        I: Connect + Clone + Send + Sync + 'static
 {
-    client: Client<I>,
+    client: Client<I, Body>,
     host: Uri,
+    version: Arc<RwLock<Option<ApiVersion>>>,
+    timeouts: Timeouts,
 }
 
 impl <I> Interact<I>
     where
-        I: Connect + 'static
+        I: Connect + Clone + Send + Sync + 'static
 {
-    pub fn new(client: Client<I>, host: Uri) -> Self {
+    pub fn new(client: Client<I, Body>, host: Uri, timeouts: Timeouts) -> Self {
         Interact {
             client,
-            host
+            host,
+            version: Arc::new(RwLock::new(None)),
+            timeouts,
         }
     }
 }
 
+#[async_trait]
 impl <I> InteractApi for Interact<I>
     where
-        I: Connect + 'static
+        I: Connect + Clone + Send + Sync + 'static
 {
-    fn request(&self, opts: RequestArgs, method: Method) -> ResponseFutureWrapper
+    async fn request(&self, opts: RequestArgs, method: Method) -> Result<Response<Body>>
     {
-        let client = self.client.clone();
-        let uri_result = compose_uri(&self.host, opts.path, opts.query);
-
-        let b = opts.body;
-        let h = opts.header;
-
-        Box::new(future::result(uri_result)
-            .and_then(move |uri|
-                ::transport::build_request(method, uri, b)
-                    .map_err(Error::from)
-            )
-            .map_err(Error::from)
-            // Inserting header elements one-by-one
-            .and_then(move |mut request| {
-                for h in h {
-                    let key = h.0.expect("Empty header's key name");
-                    request.headers_mut().insert(key, h.1);
+        let endpoint = opts.path.clone();
+        let path = match self.api_version() {
+            Some(version) => format!("/v{}{}", version, opts.path),
+            None => opts.path,
+        };
+        let uri = compose_uri(&self.host, &path, &opts.query)?;
+        let response_timeout = opts.timeouts.response.resolve(self.timeouts.response());
+        let idle = opts.timeouts.idle.resolve(self.timeouts.idle());
+
+        let (body, sent) = opts.body.watch_sent();
+        let mut request = crate::transport::build_request(method, uri, body)?;
+        request.headers_mut().extend(opts.header);
+
+        let response = self.client.request(request);
+        let response = match response_timeout {
+            // uploads take as long as they take, the response is due once the body is sent
+            Some(duration) => {
+                let deadline = async move {
+                    let _ = sent.await;
+                    tokio::time::sleep(duration).await;
+                };
+                tokio::select! {
+                    response = response => response,
+                    _ = deadline => return Err(ErrorKind::Timeout(endpoint).into()),
                 }
-                Ok(request)
-            })
-            .and_then( move |request| {
-                Ok(client.request(request))
-            })
-        )
+            }
+            None => response.await,
+        };
+        let response = response.map_err(|e| if is_connect_timeout(&e) {
+            Error::from(ErrorKind::Timeout(endpoint.clone()))
+        } else {
+            Error::from(e)
+        })?;
+
+        Ok(response.map(|body| match idle {
+            Some(idle) => idle_timeout(Body::new(body), idle, endpoint),
+            None => Body::new(body),
+        }))
+    }
+
+    fn api_version(&self) -> Option<ApiVersion> {
+        *self.version.read().expect("API version lock poisoned")
+    }
+
+    fn set_api_version(&self, version: Option<ApiVersion>) {
+        *self.version.write().expect("API version lock poisoned") = version;
+    }
+}
+
+/// Whether connecting failed because the connect timeout elapsed
+fn is_connect_timeout(err: &hyper_util::client::legacy::Error) -> bool {
+    if !err.is_connect() {
+        return false;
     }
-}
\ No newline at end of file
+
+    let mut source = err.source();
+    while let Some(err) = source {
+        if let Some(err) = err.downcast_ref::<io::Error>() {
+            return err.kind() == io::ErrorKind::TimedOut;
+        }
+        source = err.source();
+    }
+
+    false
+}
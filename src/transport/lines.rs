@@ -4,71 +4,66 @@
 // https://github.com/hyperium/hyper/issues/1335
 // https://play.rust-lang.org/?gist=971e438cabd6f91efb76b7e45b15edf3&version=stable
 
-use std::mem::replace;
-use std::string::FromUtf8Error;
+use std::pin::Pin;
+use std::task::{Context, Poll};
 
-use futures::{Async, Poll, Stream};
-use futures::stream::{Fuse};
+use futures::Stream;
+use futures::stream::{Fuse, StreamExt};
 
-use std::iter::Iterator;
+use crate::Result;
 
 pub(crate) struct Lines<S: Stream> {
     buffered: Option<Vec<u8>>,
-    stream: Fuse<S>
+    stream: Pin<Box<Fuse<S>>>
 }
 
 impl<S: Stream> Lines<S> {
     pub fn new(stream: S) -> Lines<S> {
         Lines {
             buffered: None,
-            stream: stream.fuse()
+            stream: Box::pin(stream.fuse())
         }
     }
 
-    fn process(&mut self, flush: bool) -> Option<Result<String, FromUtf8Error>> {
-        let buffered = replace(&mut self.buffered, None);
+    fn process(&mut self, flush: bool) -> Option<Result<String>> {
+        let buffered = self.buffered.take();
         if let Some(ref buffer) = buffered {
             let mut split = buffer.splitn(2, |c| *c == b'\n');
             if let Some(first) = split.next() {
                 if let Some(second) = split.next() {
-                    replace(&mut self.buffered, Some(second.to_vec()));
-                    return Some(String::from_utf8(first.to_vec()));
-                } else if flush {
-                    return Some(String::from_utf8(first.to_vec()));
+                    self.buffered = Some(second.to_vec());
+                    return Some(String::from_utf8(first.to_vec()).map_err(Into::into));
+                } else if flush && !first.is_empty() {
+                    return Some(String::from_utf8(first.to_vec()).map_err(Into::into));
                 }
             }
         }
-        replace(&mut self.buffered, buffered);
+        self.buffered = buffered;
         None
     }
 }
 
-impl<S> Stream for Lines<S>
-    where S: Stream, S::Item: AsRef<[u8]>, S::Error: From<FromUtf8Error>
+impl<S, T> Stream for Lines<S>
+    where S: Stream<Item=Result<T>>, T: AsRef<[u8]>
 {
-    type Item = String;
-    type Error = S::Error;
+    type Item = Result<String>;
 
-    fn poll(&mut self) -> Poll<Option<String>, S::Error> {
-        match self.stream.poll()? {
-            Async::NotReady => Ok(Async::NotReady),
-            Async::Ready(None) => {
-                match self.process(true) {
-                    Some(Ok(line)) => Ok(Async::Ready(Some(line))),
-                    Some(Err(err)) => Err(err.into()),
-                    None => Ok(Async::Ready(None))
-                }
-            },
-            Async::Ready(Some(chunk)) => {
-                if let Some(ref mut buffer) = self.buffered {
-                    buffer.extend(chunk.as_ref());
-                } else {
-                    self.buffered = Some(chunk.as_ref().to_vec());
-                }
-                match self.process(false) {
-                    Some(Ok(line)) => Ok(Async::Ready(Some(line))),
-                    Some(Err(err)) => Err(err.into()),
-                    None => Ok(Async::NotReady)
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Result<String>>> {
+        loop {
+            if let Some(line) = self.process(false) {
+                return Poll::Ready(Some(line));
+            }
+
+            match self.stream.as_mut().poll_next(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(None) => return Poll::Ready(self.process(true)),
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
+                Poll::Ready(Some(Ok(chunk))) => {
+                    if let Some(ref mut buffer) = self.buffered {
+                        buffer.extend(chunk.as_ref());
+                    } else {
+                        self.buffered = Some(chunk.as_ref().to_vec());
+                    }
                 }
             }
         }
@@ -1,6 +1,8 @@
+pub mod body;
 mod lines;
 pub mod tty;
 pub mod parse;
 pub mod interact;
+pub(crate) mod timeout;
 
-pub use self::parse::*;
\ No newline at end of file
+pub(crate) use self::parse::*;
\ No newline at end of file
@@ -1,35 +1,35 @@
 //! Transports for communicating with the docker daemon
-extern crate tokio_codec;
 
-use Result;
+use crate::Result;
 
-use hyper::Body;
 use hyper::Method;
 use hyper::Uri;
 use hyper::Request;
-use hyper::client::ResponseFuture;
-use hyper::rt::Future;
+use hyper::Response;
 use std::convert::Into;
 
-use errors::Error;
-use futures::future;
+use crate::errors::Error;
+use crate::errors::ErrorKind;
+use crate::transport::body::Body;
+use futures::{Future, Stream, StreamExt, TryFutureExt, TryStreamExt};
 use http::StatusCode;
 use std::fmt::Debug;
-use futures::Stream;
 use super::lines::Lines;
 use http::uri::PathAndQuery;
 use serde_json::from_str as de_from_str;
+use serde_json::from_slice as de_from_slice;
+use serde_json::Value;
 use std::str::FromStr;
 use std::str;
-use tokio::fs::File;
+use tokio::fs::{self, File};
+use tokio::io::AsyncWriteExt;
 use std::path::Path;
-use self::tokio_codec::FramedWrite;
-use self::tokio_codec::BytesCodec;
-use futures::Sink;
-use hyper::Chunk;
-use bytes::Bytes;
-
-pub type ResponseFutureWrapper = Box<Future<Item=ResponseFuture, Error=Error> + Send>;
+use std::io::Write;
+use std::mem;
+use std::process;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use crate::representation::rep::ProgressEvent;
 
 pub(crate) fn build_request<B>(method: Method, uri: Uri, body: B)
     -> Result<Request<Body>>
@@ -46,130 +46,160 @@ pub(crate) fn build_request<B>(method: Method, uri: Uri, body: B)
 }
 
 
-pub(crate) fn status_code(future: ResponseFutureWrapper) -> impl Future<Item=StatusCode, Error=Error> + Send {
-    future
-        .and_then(|w| w.map_err(Error::from))
-        .and_then(|response| {
+/// Passes 2xx responses through and turns every other one into the
+/// `ErrorKind` matching its status, carrying the daemon's error message
+pub(crate) async fn check_status(endpoint: String, response: Result<Response<Body>>)
+    -> Result<Response<Body>>
+{
+    let response = response?;
+    let status = response.status();
 
-            debug!("GET");
-            future::ok(response.status())
-        })
-        .map_err(Error::from)
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let body = response.into_body().bytes().await?;
+    let message = get_error_message(body.as_ref());
+    Err(ErrorKind::from_status(endpoint, status, message).into())
 }
 
 
-pub(crate) fn parse_to_trait<T>(future: ResponseFutureWrapper) -> impl Future<Item=T, Error=Error> + Send
+pub(crate) async fn status_code<F>(response: F) -> Result<StatusCode>
     where
-        T : for<'a> ::serde::Deserialize<'a> + Send + 'static
+        F: Future<Output=Result<Response<Body>>>
 {
-    future
-        .and_then(|w| w
-            .and_then(|response|
-                response.into_body().concat2())
-            .map_err(Error::from)
-            .and_then(|chunk| {
-                println!("{:?}", chunk.as_ref());
-                de_from_str::<T>(str::from_utf8(chunk.as_ref())?)
-                    .map_err(Error::from)
-            })
-
-        )
+    Ok(response.await?.status())
 }
 
 
-pub(crate) fn parse_to_lines(future: ResponseFutureWrapper) ->
-    impl Stream<Item=String, Error=Error>
+pub(crate) async fn parse_to_trait<T, F>(response: F) -> Result<T>
+    where
+        T : for<'a> ::serde::Deserialize<'a> + Send + 'static,
+        F: Future<Output=Result<Response<Body>>>
 {
-    future
-        .and_then(|w| w
-            .map_err(Error::from)
-            .and_then(|response| {
-                let body = response
-                    .into_body()
-                    .map_err(Error::from)
-                    .map({ |a|
-                        a.into_bytes().clone()
-                    });
-
-                let lines = Lines::new(body);
-
-                Ok(lines)
-            })
-            .map_err(Error::from)
-        )
-        .flatten_stream()
+    let body = response.await?.into_body().bytes().await?;
+
+    debug!("{:?}", body.as_ref());
+    de_from_str::<T>(str::from_utf8(body.as_ref())?)
+        .map_err(Error::from)
 }
 
 
-pub(crate) fn parse_to_stream<T>(future: ResponseFutureWrapper) ->
-        impl Stream<Item=Result<T>, Error=Error>
+pub(crate) fn parse_to_lines<F>(response: F) -> impl Stream<Item=Result<String>> + Send
     where
-        T : for<'a> ::serde::Deserialize<'a> + Send + Debug + 'static
+        F: Future<Output=Result<Response<Body>>> + Send + 'static
 {
-    future
-        .and_then(|w| w
-            .map_err(Error::from)
-            .and_then(|response| {
-                let body = response
-                    .into_body()
-                    .map_err(Error::from)
-                    .map({ |a|
-                        a.into_bytes().clone()
-                    });
-
-                let lines = Lines::new(body);
-
-                let mapped = lines
-                    .map(|chunk| {
-                        let as_str = str::from_utf8(chunk.as_ref())?;
-                        let t = de_from_str::<T>(as_str)
-                            .map_err(Error::from);
-                        t
-                    });
-
-                Ok(mapped)
-            })
-            .map_err(Error::from)
-        )
-        .flatten_stream()
+    response
+        .map_ok(|response| Lines::new(response.into_body()))
+        .try_flatten_stream()
 }
 
-#[allow(dead_code)]
-pub(crate) fn parse_to_file(future: ResponseFutureWrapper, filepath: &'static str)
-    -> impl Future<Item=(), Error=Error>
+
+pub(crate) fn parse_to_stream<T, F>(response: F) -> impl Stream<Item=Result<T>> + Send
+    where
+        T : for<'a> ::serde::Deserialize<'a> + Send + Debug + 'static,
+        F: Future<Output=Result<Response<Body>>> + Send + 'static
 {
-    let stream = future
-        .and_then(|w| w
-            .map_err(Error::from)
-            .and_then(|response| {
-                let body = response
-                    .into_body()
-                    .map_err(Error::from);
-
-                Ok(body)
-            })
-        )
-        .flatten_stream();
-
-    let file = File::create(Path::new(filepath));
-
-    file
-        .map_err(Error::from)
-        .and_then(|file| {
-            let write = FramedWrite::new(file, BytesCodec::new())
-                .with(|chunk : Chunk| {
-                    future::ok::<_, Error>(Bytes::from(chunk))
-                });
-
-            stream
-                .forward(write)
-                .and_then(|_|
-                    Ok(()))
-                .map_err(Error::from)
+    parse_to_lines(response)
+        .map(|line| {
+            let line = line?;
+            de_from_str::<T>(&line).map_err(Error::from)
+        })
+}
+
+
+/// Progress messages of the response, failing on the first error the daemon
+/// reports although the status was 200
+pub(crate) fn parse_to_progress<F>(endpoint: String, response: F)
+    -> impl Stream<Item=Result<ProgressEvent>> + Send
+    where
+        F: Future<Output=Result<Response<Body>>> + Send + 'static
+{
+    parse_to_stream::<ProgressEvent, F>(response)
+        .map(move |message| {
+            let message = message?;
+            match message.error_message() {
+                Some(error) => Err(ErrorKind::StreamError(endpoint.clone(), error.to_owned()).into()),
+                None => Ok(message),
+            }
         })
 }
 
 
+/// Data chunks of the response body
+pub(crate) fn parse_to_chunks<F>(response: F) -> impl Stream<Item=Result<::bytes::Bytes>> + Send
+    where
+        F: Future<Output=Result<Response<Body>>> + Send + 'static
+{
+    response
+        .map_ok(|response| response.into_body())
+        .try_flatten_stream()
+}
+
+
+/// Writes the response body to `filepath`, gzipped if asked to. The body
+/// goes to a temporary file next to it which is renamed once complete, so
+/// the file is never seen half written. resolves to the bytes written
+pub(crate) async fn parse_to_file<F>(response: F, filepath: &Path, gzip: bool) -> Result<u64>
+    where
+        F: Future<Output=Result<Response<Body>>> + Send + 'static
+{
+    let name = filepath.file_name()
+        .ok_or_else(|| ErrorKind::Message(format!("{} is not a file path", filepath.display())))?;
+    let temp = filepath.with_file_name(
+        format!(".{}.{}.part", name.to_string_lossy(), process::id()));
+
+    match write_to_file(response, &temp, gzip).await {
+        Ok(written) => {
+            fs::rename(&temp, filepath).await?;
+            Ok(written)
+        }
+        Err(err) => {
+            let _ = fs::remove_file(&temp).await;
+            Err(err)
+        }
+    }
+}
+
+async fn write_to_file<F>(response: F, filepath: &Path, gzip: bool) -> Result<u64>
+    where
+        F: Future<Output=Result<Response<Body>>> + Send + 'static
+{
+    let mut chunks = Box::pin(parse_to_chunks(response));
+    let mut file = File::create(filepath).await?;
+    let mut encoder = if gzip {
+        Some(GzEncoder::new(Vec::new(), Compression::Default))
+    } else {
+        None
+    };
+    let mut written = 0;
+
+    while let Some(chunk) = chunks.try_next().await? {
+        match encoder {
+            Some(ref mut encoder) => {
+                encoder.write_all(&chunk)?;
+                let compressed = mem::take(encoder.get_mut());
+                file.write_all(&compressed).await?;
+                written += compressed.len() as u64;
+            }
+            None => {
+                file.write_all(&chunk).await?;
+                written += chunk.len() as u64;
+            }
+        }
+    }
+    if let Some(encoder) = encoder {
+        let compressed = encoder.finish()?;
+        file.write_all(&compressed).await?;
+        written += compressed.len() as u64;
+    }
+    file.flush().await?;
+    file.sync_all().await?;
+
+    Ok(written)
+}
+
+
 pub(crate) fn compose_uri(host: &Uri, path: &str, query: &str) -> Result<Uri>
 {
     let mut parts = host.clone().into_parts();
@@ -181,27 +211,97 @@ pub(crate) fn compose_uri(host: &Uri, path: &str, query: &str) -> Result<Uri>
 
     debug!("URI parse result: {:?}", res);
 
-    Ok(Uri::from(res?))
+    Ok(res?)
 }
 
 
-/*
-/// Extract the error message content from an HTTP response that
-/// contains a Docker JSON error structure.
-#[allow(dead_code)]
-fn get_error_message(res: &mut Response) -> Option<String> {
-    let mut output = String::new();
-
-    if res.read_to_string(&mut output).is_ok() {
-        json::Json::from_str(output.as_str())
-            .ok()
-            .as_ref()
-            .and_then(|x| x.as_object())
-            .and_then(|x| x.get("message"))
-            .and_then(|x| x.as_string())
-            .map(|x| x.to_owned())
-    } else {
-        None
+/// Extract the error message content from the body of a response that
+/// contains a Docker JSON error structure, falling back to the raw body.
+fn get_error_message(body: &[u8]) -> String {
+    de_from_slice::<Value>(body)
+        .ok()
+        .as_ref()
+        .and_then(|x| x.as_object())
+        .and_then(|x| x.get("message"))
+        .and_then(|x| x.as_str())
+        .map(|x| x.to_owned())
+        .unwrap_or_else(|| String::from_utf8_lossy(body).trim().to_owned())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::get_error_message;
+    use crate::errors::ErrorKind;
+    use http::StatusCode;
+
+    #[test]
+    fn error_message_from_json() {
+        let body = br#"{"message":"No such container: foo"}"#;
+
+        assert_eq!("No such container: foo", get_error_message(body));
+    }
+
+    #[test]
+    fn error_message_from_plain_text() {
+        assert_eq!("page not found", get_error_message(b"page not found\n"));
+    }
+
+    #[test]
+    fn error_kind_from_status() {
+        let kind = |code| ErrorKind::from_status(
+            "/containers/foo/json".to_owned(), code, String::new());
+
+        match kind(StatusCode::NOT_FOUND) {
+            ErrorKind::NotFound(ref endpoint, StatusCode::NOT_FOUND, _) =>
+                assert_eq!("/containers/foo/json", endpoint),
+            other => panic!("unexpected {:?}", other),
+        }
+        match kind(StatusCode::NOT_MODIFIED) {
+            ErrorKind::NotModified(..) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+        match kind(StatusCode::CONFLICT) {
+            ErrorKind::Conflict(..) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+        match kind(StatusCode::BAD_REQUEST) {
+            ErrorKind::BadParameter(..) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+        match kind(StatusCode::INTERNAL_SERVER_ERROR) {
+            ErrorKind::ServerError(..) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn file_written_atomically_and_gzipped() {
+        use super::parse_to_file;
+        use crate::test_util::TempDir;
+        use crate::transport::body::Body;
+        use flate2::read::GzDecoder;
+        use hyper::Response;
+        use std::fs;
+        use std::io::Read;
+
+        let dir = TempDir::new("export");
+        let path = dir.path().join("images.tar.gz");
+        let tarball = vec![7; 4096];
+        let response = Response::new(Body::from(tarball.clone()));
+
+        let written = tokio::runtime::Runtime::new().expect("Runtime")
+            .block_on(parse_to_file(async { Ok(response) }, &path, true))
+            .expect("Written");
+
+        let compressed = fs::read(&path).expect("Export");
+        let mut decompressed = Vec::new();
+        GzDecoder::new(compressed.as_slice()).expect("Gzip header")
+            .read_to_end(&mut decompressed).expect("Gzip");
+        let files = fs::read_dir(dir.path()).expect("Dir").count();
+
+        assert_eq!(compressed.len() as u64, written);
+        assert_eq!(tarball, decompressed);
+        assert_eq!(1, files);
     }
 }
-*/
\ No newline at end of file
@@ -0,0 +1,191 @@
+//! Deadlines enforced on connecting and on idle response bodies
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use bytes::Bytes;
+use http::Uri;
+use http_body::{Body as HttpBody, Frame, SizeHint};
+use tokio::time::{sleep, timeout, Instant, Sleep};
+use tower_service::Service;
+
+use crate::transport::body::{Body, BoxError};
+
+/// Connector failing with `io::ErrorKind::TimedOut` if connecting takes too long
+#[derive(Clone)]
+pub(crate) struct TimeoutConnector<C> {
+    inner: C,
+    timeout: Option<Duration>,
+}
+
+impl<C> TimeoutConnector<C> {
+    pub(crate) fn new(inner: C, timeout: Option<Duration>) -> TimeoutConnector<C> {
+        TimeoutConnector {
+            inner,
+            timeout,
+        }
+    }
+}
+
+impl<C> Service<Uri> for TimeoutConnector<C>
+    where C: Service<Uri> + Send + 'static,
+          C::Error: Into<BoxError>,
+          C::Future: Send + 'static
+{
+    type Response = C::Response;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output=Result<Self::Response, BoxError>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), BoxError>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, dst: Uri) -> Self::Future {
+        let connecting = self.inner.call(dst);
+
+        match self.timeout {
+            Some(duration) => Box::pin(async move {
+                match timeout(duration, connecting).await {
+                    Ok(connected) => connected.map_err(Into::into),
+                    Err(_) => Err(io::Error::new(io::ErrorKind::TimedOut,
+                                                 "Timed out connecting to the daemon").into()),
+                }
+            }),
+            None => Box::pin(async move { connecting.await.map_err(Into::into) }),
+        }
+    }
+}
+
+/// Error of a body which did not produce a chunk in time, holds the endpoint
+#[derive(Debug)]
+pub(crate) struct IdleTimeout(pub String);
+
+impl fmt::Display for IdleTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: timed out waiting for the response body", self.0)
+    }
+}
+
+impl StdError for IdleTimeout {}
+
+/// Body failing with `IdleTimeout` if no frame arrives for `idle`
+struct IdleBody {
+    inner: Body,
+    endpoint: String,
+    idle: Duration,
+    deadline: Pin<Box<Sleep>>,
+}
+
+/// Wraps a response body into one enforcing the idle timeout
+pub(crate) fn idle_timeout(body: Body, idle: Duration, endpoint: String) -> Body {
+    Body::new(IdleBody {
+        inner: body,
+        endpoint,
+        idle,
+        deadline: Box::pin(sleep(idle)),
+    })
+}
+
+impl HttpBody for IdleBody {
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_frame(mut self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Option<Result<Frame<Bytes>, BoxError>>>
+    {
+        match Pin::new(&mut self.inner).poll_frame(cx) {
+            Poll::Ready(frame) => {
+                let next = Instant::now() + self.idle;
+                self.deadline.as_mut().reset(next);
+                Poll::Ready(frame)
+            }
+            Poll::Pending => match self.deadline.as_mut().poll(cx) {
+                Poll::Ready(()) => Poll::Ready(Some(Err(Box::new(IdleTimeout(self.endpoint.clone()))))),
+                Poll::Pending => Poll::Pending,
+            },
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::build::LogsOptions;
+    use crate::communicate::timeout::Timeouts;
+    use crate::docker::new_docker_with_connector_and_timeouts;
+    use crate::errors::{Error, ErrorKind};
+    use futures::TryStreamExt;
+    use hyper_util::client::legacy::connect::HttpConnector;
+    use std::borrow::Cow;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::Duration;
+    use tokio::runtime::Runtime;
+
+    /// Serves a single connection, answering with `response` and then
+    /// stalling for a while
+    fn stalling_daemon(response: &'static [u8]) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Listener");
+        let port = listener.local_addr().expect("Address").port();
+
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().expect("Connection");
+            let mut buf = [0; 1024];
+            let _ = stream.read(&mut buf);
+            let _ = stream.write_all(response);
+            thread::sleep(Duration::from_secs(5));
+        });
+
+        port
+    }
+
+    fn assert_timeout<T: ::std::fmt::Debug>(result: Result<T, Error>) {
+        match result {
+            Err(Error(ErrorKind::Timeout(_), _)) => (),
+            other => panic!("Expected a timeout, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn response_timeout() {
+        let port = stalling_daemon(b"");
+        let timeouts = Timeouts::builder().response(Some(Duration::from_millis(100))).build();
+        let uri = format!("http://127.0.0.1:{}", port).parse().expect("Uri");
+        let docker = new_docker_with_connector_and_timeouts(HttpConnector::new(), uri, timeouts);
+
+        assert_timeout(Runtime::new().expect("Runtime").block_on(docker.ping()));
+    }
+
+    #[test]
+    fn idle_timeout_unless_following() {
+        let port = stalling_daemon(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n6\r\nhello\n\r\n");
+        let timeouts = Timeouts::builder().idle(Some(Duration::from_millis(100))).build();
+        let uri = format!("http://127.0.0.1:{}", port).parse().expect("Uri");
+        let docker = new_docker_with_connector_and_timeouts(HttpConnector::new(), uri, timeouts);
+        let logs = docker.container(Cow::Borrowed("web")).logs(&LogsOptions::default());
+
+        let lines = Runtime::new().expect("Runtime").block_on(logs.try_collect::<Vec<_>>());
+        assert_timeout(lines);
+
+        let mut follow = LogsOptions::builder();
+        follow.follow(true);
+        assert_eq!(None, follow.build().timeouts().idle.resolve(timeouts.idle()));
+        follow.idle_timeout(Duration::from_secs(1));
+        assert_eq!(Some(Duration::from_secs(1)),
+                   follow.build().timeouts().idle.resolve(timeouts.idle()));
+    }
+}
@@ -1,13 +1,11 @@
-extern crate byteorder;
-
-use self::byteorder::{BigEndian, ReadBytesExt};
+use byteorder::{BigEndian, ReadBytesExt};
 use std::io::Cursor;
+use std::pin::Pin;
+use std::task::{Context, Poll};
 use futures::Stream;
-use Error;
-use hyper::Chunk;
-use futures::Async;
-use futures::Poll;
-use errors::ErrorKind;
+use crate::Result;
+use bytes::Bytes;
+use crate::errors::ErrorKind;
 use std::collections::VecDeque;
 
 #[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
@@ -19,22 +17,24 @@ enum State {
     }
 }
 
-struct TtyDecoder<T> where T : Stream<Item=Chunk> {
+struct TtyDecoder<T> where T : Stream<Item=Result<Bytes>> {
     state : State,
     buf : VecDeque<u8>,
-    inner : T
+    inner : Pin<Box<T>>
 }
 
-impl<T> TtyDecoder<T> where T : Stream<Item=Chunk> {
+impl<T> TtyDecoder<T> where T : Stream<Item=Result<Bytes>> {
 
-    fn eat_buffer(&mut self) -> Poll<Option<(u32,Chunk)>, Error> {
+    fn eat_buffer(&mut self) -> Option<Result<(u32, Bytes)>> {
         if let State::Header = self.state {
             if self.buf.len()>=8 {
                 {
                     let header: Vec<u8> = self.buf.drain(0..8).collect();
                     debug!("{:?}", header);
-                    let size = Cursor::new(&header[4..8])
-                        .read_u32::<BigEndian>()? as usize;
+                    let size = match Cursor::new(&header[4..8]).read_u32::<BigEndian>() {
+                        Ok(size) => size as usize,
+                        Err(e) => return Some(Err(e.into())),
+                    };
                     self.state = State::Body {
                         message_size: size,
                         message_type: header[0] as u32,
@@ -42,7 +42,7 @@ impl<T> TtyDecoder<T> where T : Stream<Item=Chunk> {
                 }
             }
             else {
-                return Ok(Async::NotReady)
+                return None
             }
         }
 
@@ -50,10 +50,10 @@ impl<T> TtyDecoder<T> where T : Stream<Item=Chunk> {
             if self.buf.len() >= message_size {
                 let chunk : Vec<u8> = self.buf.drain(0..message_size).collect();
                 self.state = State::Header;
-                return Ok(Async::Ready(Some((message_type, chunk.into()))))
+                return Some(Ok((message_type, chunk.into())))
             }
             else {
-                return Ok(Async::NotReady)
+                return None
             }
         }
 
@@ -62,44 +62,43 @@ impl<T> TtyDecoder<T> where T : Stream<Item=Chunk> {
 
 }
 
-impl<T> Stream for TtyDecoder<T> where T : Stream<Item=Chunk>, Error : From<T::Error> {
-    type Item = (u32, Chunk);
-    type Error = Error;
+impl<T> Stream for TtyDecoder<T> where T : Stream<Item=Result<Bytes>> {
+    type Item = Result<(u32, Bytes)>;
 
-    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
         loop {
-            match self.eat_buffer() {
-                v @ Ok(Async::Ready(Some(_))) => return v,
-                v @ Err(_) => return v,
-                _ => ()
-            };
+            if let Some(item) = self.eat_buffer() {
+                return Poll::Ready(Some(item))
+            }
 
-            let it: Option<Chunk> = match self.inner.poll() {
-                Ok(Async::NotReady) => {
+            let it: Option<Bytes> = match self.inner.as_mut().poll_next(cx) {
+                Poll::Pending => {
                     debug!("not_ready");
-                    return Ok(Async::NotReady)
+                    return Poll::Pending
                 },
-                Ok(Async::Ready(item)) => item,
-                Err(e) => return Err(e.into())
+                Poll::Ready(Some(Ok(chunk))) => Some(chunk),
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(None) => None,
             };
 
             if let Some(chunk) = it {
-                for b in chunk {
-                    self.buf.push_back(b);
-                }
+                self.buf.extend(chunk.iter());
             }
             else {
-                if self.buf.len() == 0 {
-                    return Ok(Async::Ready(None))
+                if self.buf.is_empty() {
+                    return Poll::Ready(None)
                 }
                 else {
-                    return Err(ErrorKind::Eof.into())
+                    self.buf.clear();
+                    return Poll::Ready(Some(Err(ErrorKind::Eof.into())))
                 }
             }
         }
     }
 }
 
-pub fn decode<F>(stream : F) -> impl Stream<Item=(u32, Chunk), Error=Error> where F : Stream<Item=Chunk, Error=Error> {
-    TtyDecoder { state: State::Header, buf: VecDeque::new(), inner: stream }
-}
\ No newline at end of file
+pub fn decode<F>(stream : F) -> impl Stream<Item=Result<(u32, Bytes)>> + Send
+    where F : Stream<Item=Result<Bytes>> + Send
+{
+    TtyDecoder { state: State::Header, buf: VecDeque::new(), inner: Box::pin(stream) }
+}